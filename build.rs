@@ -0,0 +1,32 @@
+use std::env;
+use std::process::Command;
+
+/// Run a command and return its trimmed stdout, or an empty string if it fails.
+fn command_output(program: &str, args: &[&str]) -> String {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn main() {
+    let git_sha = command_output("git", &["rev-parse", "--short=12", "HEAD"]);
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = command_output(&rustc, &["--version"]);
+    let target = env::var("TARGET").unwrap_or_default();
+
+    println!("cargo:rustc-env=CLAUDE_SESSIONS_GIT_SHA={}", git_sha);
+    println!(
+        "cargo:rustc-env=CLAUDE_SESSIONS_RUSTC_VERSION={}",
+        rustc_version
+    );
+    println!("cargo:rustc-env=CLAUDE_SESSIONS_TARGET={}", target);
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use directories::BaseDirs;
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Overrides the location of Claude's `projects` directory (mainly for testing).
+pub const PROJECTS_DIR_ENV: &str = "CLAUDE_SESSIONS_PROJECTS_DIR";
+
+/// Claude stores each project's sessions under a directory named after the
+/// project path with every non-alphanumeric character replaced by `-`.
+pub fn encode_project_dir(path: &Path) -> String {
+    path.to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+pub fn projects_root() -> Result<PathBuf> {
+    if let Some(dir) = env::var_os(PROJECTS_DIR_ENV) {
+        return Ok(PathBuf::from(dir));
+    }
+    let base_dirs = BaseDirs::new().context("Could not determine home directory")?;
+    Ok(base_dirs.home_dir().join(".claude").join("projects"))
+}
+
+/// The directory where Claude keeps session transcripts for `cwd`.
+pub fn project_dir(cwd: &Path) -> Result<PathBuf> {
+    Ok(projects_root()?.join(encode_project_dir(cwd)))
+}
+
+/// Session ids (the `.jsonl` file stems) currently present in a project directory.
+/// A missing directory has no sessions.
+pub fn session_ids(dir: &Path) -> Result<BTreeSet<String>> {
+    if !dir.exists() {
+        return Ok(BTreeSet::new());
+    }
+
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("Could not read directory: {:?}", dir))?;
+
+    let mut ids = BTreeSet::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "jsonl") {
+            if let Some(stem) = path.file_stem() {
+                ids.insert(stem.to_string_lossy().to_string());
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// Session ids present in `after` but not in `before`, in sorted order.
+pub fn new_session_ids(before: &BTreeSet<String>, after: &BTreeSet<String>) -> Vec<String> {
+    after.difference(before).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "claude-sessions-claude-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn set(ids: &[&str]) -> BTreeSet<String> {
+        ids.iter().map(|s| s.to_string()).collect()
+    }
+
+    // ==================== Encoding Tests ====================
+
+    #[test]
+    fn test_encode_project_dir() {
+        assert_eq!(
+            encode_project_dir(Path::new("/Users/me/Projects/my-app")),
+            "-Users-me-Projects-my-app"
+        );
+        assert_eq!(
+            encode_project_dir(Path::new("/home/me/my.app_v2")),
+            "-home-me-my-app-v2"
+        );
+    }
+
+    // ==================== Snapshot Tests ====================
+
+    #[test]
+    fn test_session_ids_reads_jsonl_stems() {
+        let dir = temp_dir("snapshot");
+        fs::write(dir.join("abc-123.jsonl"), "").unwrap();
+        fs::write(dir.join("def-456.jsonl"), "").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        assert_eq!(session_ids(&dir).unwrap(), set(&["abc-123", "def-456"]));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_session_ids_missing_dir_is_empty() {
+        let dir = temp_dir("snapshot-missing").join("nope");
+
+        assert!(session_ids(&dir).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_new_session_ids_between_snapshots() {
+        let dir = temp_dir("diff");
+        fs::write(dir.join("old.jsonl"), "").unwrap();
+        let before = session_ids(&dir).unwrap();
+
+        fs::write(dir.join("new-b.jsonl"), "").unwrap();
+        fs::write(dir.join("new-a.jsonl"), "").unwrap();
+        let after = session_ids(&dir).unwrap();
+
+        assert_eq!(new_session_ids(&before, &after), vec!["new-a", "new-b"]);
+        assert!(new_session_ids(&after, &after).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_new_session_ids_ignores_removed() {
+        let before = set(&["a", "b"]);
+        let after = set(&["b", "c"]);
+
+        assert_eq!(new_session_ids(&before, &after), vec!["c"]);
+    }
+}
@@ -1,51 +1,401 @@
+mod output;
+
 use anyhow::{bail, Context, Result};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
 use colored::Colorize;
-use dialoguer::{theme::ColorfulTheme, Select};
+use dialoguer::{theme::ColorfulTheme, Confirm, FuzzySelect, Input, Select};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::env;
-use std::process::Command;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::time::Duration;
+use unicode_width::UnicodeWidthStr;
+
+use crate::claude;
+use crate::data::{
+    newest_first, ImportOutcome, KeepOnConflict, Label, LabelResolution, Session, Store,
+};
+use crate::doctor::{self, PathRepair, Problem};
+use crate::editor;
+use crate::format::{
+    expand_template, format_ts, heat_color, humanize_age, normalize_color_name,
+    render_markdown_wrapped, style_label_name, truncate_to_width, wrap_to_width, DisplayZone, Heat,
+    OutputMode,
+};
+use crate::history::{self, HistoryEntry};
+use crate::parse::{parse_time_bound, parse_time_upper_bound, time_ago};
+use crate::paths::{
+    abbreviate_home, home_dir, nearest_existing_dir, path_is_under, resolve_subdir,
+};
+use crate::session_lock::{self, SessionLock};
+use crate::settings::{self, Layout, SETTING_KEYS};
+use crate::shutdown::{for_each_until_stopped, sleep_unless_stopped, StopFlag};
+use crate::storage::{list_profiles, profile_file_name, Storage};
+use crate::version::build_info;
+use output::{LabelOut, LabelSummaryOut, TimeFormat};
+
+#[derive(Debug, Default)]
+pub struct AddOptions {
+    pub resume: bool,
+    pub meta: Vec<(String, String)>,
+    /// Warn about session ids that don't look like a UUID.
+    pub warn_id: bool,
+    pub strict_id: bool,
+    /// Backdate the session instead of using the current time.
+    pub at: Option<DateTime<Utc>>,
+    /// Also set the label's own description.
+    pub label_description: Option<String>,
+    /// Move the session out of any other label holding its id instead of
+    /// adding a second copy.
+    pub move_existing: bool,
+    /// Skip the insert when the label already has this id at this path.
+    pub idempotent: bool,
+    /// Record the session even outside the `allowed_roots` setting, and
+    /// with `resume` launch it even if it looks already running.
+    pub force: bool,
+    /// Record the invoking shell and whether stdin was a terminal in the
+    /// session's metadata.
+    pub capture_env: bool,
+}
+
+/// Metadata `add --capture-env` records: `shell` from `$SHELL` (omitted when
+/// unset or empty) and `tty` as `true`/`false`.
+fn captured_env_meta(shell_var: Option<OsString>, tty: bool) -> Vec<(String, String)> {
+    let mut meta = Vec::new();
+    if let Some(shell) = shell_var.filter(|s| !s.is_empty()) {
+        meta.push(("shell".to_string(), shell.to_string_lossy().to_string()));
+    }
+    meta.push(("tty".to_string(), tty.to_string()));
+    meta
+}
+
+/// The metadata `add` attaches: the captured environment when asked for,
+/// with explicit `--meta` values taking precedence.
+fn add_meta(options: &AddOptions) -> Vec<(String, String)> {
+    let mut meta = Vec::new();
+    if options.capture_env {
+        meta = captured_env_meta(env::var_os("SHELL"), io::stdin().is_terminal());
+    }
+    meta.extend(options.meta.iter().cloned());
+    meta
+}
 
-use crate::data::Session;
-use crate::storage::Storage;
+/// Refuse a session path that isn't under any of `roots`. No roots allows
+/// every path.
+fn check_allowed_root(roots: &[String], path: &str) -> Result<()> {
+    if roots.is_empty()
+        || roots
+            .iter()
+            .any(|root| path_is_under(Path::new(path), Path::new(root)))
+    {
+        return Ok(());
+    }
+    bail!(
+        "{} is not under any allowed root ({}); use --force to add it anyway",
+        path,
+        roots.join(", ")
+    )
+}
+
+/// Whether `s` has the 8-4-4-4-12 hex shape of a Claude session id.
+pub fn looks_like_session_id(s: &str) -> bool {
+    let groups: Vec<&str> = s.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// With `warn`, warn about ids that aren't UUID-shaped; with `strict`,
+/// reject them. Neither checks anything.
+fn check_session_id(session_id: &str, warn: bool, strict: bool) -> Result<()> {
+    if (warn || strict) && !looks_like_session_id(session_id) {
+        if strict {
+            bail!(
+                "'{}' does not look like a Claude session id (expected a UUID)",
+                session_id
+            );
+        }
+        eprintln!(
+            "{} '{}' does not look like a Claude session id (expected a UUID)",
+            "warning:".yellow(),
+            session_id
+        );
+    }
+    Ok(())
+}
 
 pub fn add(
     storage: &Storage,
     label: &str,
     session_id: &str,
     description: Option<String>,
+    options: &AddOptions,
 ) -> Result<()> {
-    let mut store = storage.load()?;
+    add_with_launcher(storage, label, session_id, description, options, |s| {
+        let store = storage.load()?;
+        resume_session(
+            storage,
+            store.resolve_label_name(label),
+            s,
+            Path::new(&s.path),
+            &LaunchOptions::default(),
+            options.force,
+        )
+    })
+}
 
-    let current_path = env::current_dir()
-        .context("Could not get current directory")?
-        .to_string_lossy()
-        .to_string();
+fn add_with_launcher<F>(
+    storage: &Storage,
+    label: &str,
+    session_id: &str,
+    description: Option<String>,
+    options: &AddOptions,
+    launch: F,
+) -> Result<()>
+where
+    F: FnOnce(&Session) -> Result<()>,
+{
+    check_session_id(session_id, options.warn_id, options.strict_id)?;
+
+    let current_path = current_dir_string()?;
 
     let session = Session {
         session_id: session_id.to_string(),
-        path: current_path.clone(),
-        description: description.clone(),
-        created_at: Utc::now(),
+        path: current_path,
+        description,
+        created_at: options.at.unwrap_or_else(Utc::now),
+        meta: add_meta(options).into_iter().collect(),
+        ..Default::default()
     };
 
-    let label_entry = store.get_or_create_label(label);
-    label_entry.add_session(session);
+    insert_session(storage, label, session, options, launch)
+}
 
-    storage.save(&store)?;
+fn current_dir_string() -> Result<String> {
+    Ok(env::current_dir()
+        .context("Could not get current directory")?
+        .to_string_lossy()
+        .to_string())
+}
+
+/// A session as accepted by `add --stdin-json`: only `session_id` is required,
+/// so both a full `Session` and a minimal object deserialize into it.
+#[derive(Debug, Deserialize)]
+struct SessionInput {
+    session_id: String,
+    path: Option<String>,
+    description: Option<String>,
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    meta: HashMap<String, String>,
+    #[serde(default)]
+    resume_count: u32,
+    last_resumed_at: Option<DateTime<Utc>>,
+}
+
+/// Parse a session object from JSON, filling a missing path with `cwd` and a
+/// missing `created_at` with `now`.
+fn session_from_json(json: &str, cwd: &str, now: DateTime<Utc>) -> Result<Session> {
+    let input: SessionInput =
+        serde_json::from_str(json).context("Invalid session JSON on stdin")?;
+    if input.session_id.trim().is_empty() {
+        bail!("Invalid session JSON on stdin: session_id is empty");
+    }
+
+    Ok(Session {
+        session_id: input.session_id,
+        path: input.path.unwrap_or_else(|| cwd.to_string()),
+        description: input.description,
+        created_at: input.created_at.unwrap_or(now),
+        meta: input.meta,
+        resume_count: input.resume_count,
+        last_resumed_at: input.last_resumed_at,
+    })
+}
+
+/// Add a session read as a JSON object (`add --stdin-json`). `--meta` values
+/// (and `--capture-env`) are merged over the object's own metadata.
+pub fn add_json(storage: &Storage, label: &str, json: &str, options: &AddOptions) -> Result<()> {
+    let mut session = session_from_json(json, &current_dir_string()?, Utc::now())?;
+    check_session_id(&session.session_id, options.warn_id, options.strict_id)?;
+    session.meta.extend(add_meta(options));
+
+    insert_session(storage, label, session, options, |s| {
+        let store = storage.load()?;
+        resume_session(
+            storage,
+            store.resolve_label_name(label),
+            s,
+            Path::new(&s.path),
+            &LaunchOptions::default(),
+            options.force,
+        )
+    })
+}
+
+fn insert_session<F>(
+    storage: &Storage,
+    label: &str,
+    session: Session,
+    options: &AddOptions,
+    launch: F,
+) -> Result<()>
+where
+    F: FnOnce(&Session) -> Result<()>,
+{
+    let inserted = storage.update(|store| {
+        // Checked under the lock, so concurrent re-runs can't both insert
+        if options.idempotent && already_present(store, label, &session) {
+            return Err(AlreadyPresent.into());
+        }
+        if !options.force {
+            check_allowed_root(&store.settings.allowed_roots, &session.path)?;
+        }
+        let mut session = session.clone();
+        let moved_from = if options.move_existing {
+            take_from_other_labels(store, label, &mut session)
+        } else {
+            Vec::new()
+        };
+        let label_entry = store.get_or_create_label(label);
+        label_entry.add_session(session.clone());
+        if let Some(ref desc) = options.label_description {
+            label_entry.set_description(Some(desc.clone()), Utc::now());
+        }
+        Ok((session, moved_from))
+    });
+    let (session, moved_from) = match inserted {
+        Ok(inserted) => inserted,
+        Err(err) if err.is::<AlreadyPresent>() => {
+            println!(
+                "{} Session '{}' already present in label '{}'",
+                OutputMode::current().ok_mark(),
+                session.session_id,
+                label.cyan()
+            );
+            if options.resume {
+                println!();
+                launch(&session)?;
+            }
+            return Ok(());
+        }
+        Err(err) => return Err(err),
+    };
 
-    println!("{} Added session to label '{}'", "✓".green(), label.cyan());
-    println!("  Session: {}", session_id);
-    println!("  Path: {}", current_path);
-    if let Some(desc) = description {
+    if moved_from.is_empty() {
+        println!(
+            "{} Added session to label '{}'",
+            OutputMode::current().ok_mark(),
+            label.cyan()
+        );
+    } else {
+        println!(
+            "{} Moved session from '{}' to label '{}'",
+            OutputMode::current().ok_mark(),
+            moved_from.join("', '"),
+            label.cyan()
+        );
+    }
+    if let Some(ref desc) = options.label_description {
+        println!("  Label description: {}", desc);
+    }
+    println!("  Session: {}", session.session_id);
+    println!("  Path: {}", session.path);
+    if let Some(ref desc) = session.description {
         println!("  Description: {}", desc);
     }
+    if !session.meta.is_empty() {
+        println!("  Meta: {}", format_meta(&session.meta));
+    }
+
+    if options.resume {
+        println!();
+        launch(&session)?;
+    }
 
     Ok(())
 }
 
-pub fn resume(storage: &Storage, label: &str, pick: bool) -> Result<()> {
-    let store = storage.load()?;
+/// Ends the `add --idempotent` update without saving when the session is
+/// already there.
+#[derive(Debug)]
+struct AlreadyPresent;
+
+impl std::fmt::Display for AlreadyPresent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "session already present")
+    }
+}
+
+impl std::error::Error for AlreadyPresent {}
+
+/// Whether `label` already holds `session`'s id at the same path, which makes
+/// `add --idempotent` a no-op.
+fn already_present(store: &Store, label: &str, session: &Session) -> bool {
+    store
+        .get_label(label)
+        .is_some_and(|l| l.has_session_at(&session.session_id, &session.path))
+}
+
+/// Remove every copy of `session`'s id from labels other than `label`,
+/// folding them into `session` so their description and metadata carry over.
+/// Returns the labels it was taken from. Nothing moves if `label` already has
+/// the id.
+fn take_from_other_labels(store: &mut Store, label: &str, session: &mut Session) -> Vec<String> {
+    let id = session.session_id.clone();
+    if store
+        .get_label(label)
+        .is_some_and(|l| l.find_session(&id).is_some())
+    {
+        return Vec::new();
+    }
 
+    let mut moved_from = Vec::new();
+    while let Some(from) = store.find_session_label(&id).cloned() {
+        let source = store.labels.get_mut(&from).expect("label was just found");
+        for old in source.sessions.iter().filter(|s| s.session_id == id) {
+            session.merge_from(old);
+        }
+        source.remove_session(&id);
+        moved_from.push(from);
+    }
+    moved_from
+}
+
+#[derive(Debug, Default)]
+pub struct ResumeOptions {
+    pub pick: bool,
+    /// Show whole session ids in the picker instead of the first few characters.
+    pub full_ids: bool,
+    pub cd: Option<String>,
+    pub allow_escape: bool,
+    pub fallback: Option<String>,
+    /// First message to send after resuming.
+    pub prompt: Option<String>,
+    /// Environment variables set for the `claude` process only.
+    pub env: Vec<(String, String)>,
+    /// Zone for picker timestamps, overriding the `time_zone` setting.
+    pub zone: Option<DisplayZone>,
+    /// Which session the picker starts on.
+    pub picker_default: PickerDefault,
+    /// Start in the closest existing parent when the session path is gone.
+    pub nearest_dir: bool,
+    /// Open an interactive shell in the session directory instead of Claude.
+    pub shell: bool,
+    /// Resume even if the session looks like it's already running.
+    pub force: bool,
+}
+
+fn usable_label<'a>(store: &'a Store, label: &str) -> Result<&'a Label> {
     let label_entry = store
         .get_label(label)
         .with_context(|| format!("Label '{}' not found", label))?;
@@ -54,45 +404,247 @@ pub fn resume(storage: &Storage, label: &str, pick: bool) -> Result<()> {
         bail!("Label '{}' has no sessions", label);
     }
 
-    let session = if pick && label_entry.sessions.len() > 1 {
-        pick_session(label_entry)?
+    Ok(label_entry)
+}
+
+/// Pick the label to resume: `label` if it exists and has sessions, otherwise
+/// `fallback` (one level only). Returns the chosen name alongside the label.
+fn resolve_resume_label<'a>(
+    store: &'a Store,
+    label: &'a str,
+    fallback: Option<&'a str>,
+) -> Result<(&'a str, &'a Label)> {
+    match usable_label(store, label) {
+        Ok(label_entry) => Ok((label, label_entry)),
+        Err(primary_err) => match fallback {
+            Some(fallback) => {
+                let label_entry = usable_label(store, fallback)
+                    .with_context(|| format!("{}; fallback also unusable", primary_err))?;
+                Ok((fallback, label_entry))
+            }
+            None => Err(primary_err),
+        },
+    }
+}
+
+pub fn resume(storage: &Storage, label: &str, options: &ResumeOptions) -> Result<()> {
+    let store = storage.load()?;
+
+    let (chosen, label_entry) = resolve_resume_label(&store, label, options.fallback.as_deref())?;
+    if chosen != label {
+        println!(
+            "{} Label '{}' is missing or empty, falling back to '{}'",
+            "!".yellow(),
+            label,
+            chosen.cyan()
+        );
+    }
+
+    let session = if options.pick && label_entry.sessions.len() > 1 {
+        let zone = options
+            .zone
+            .unwrap_or_else(|| store.settings.display_zone());
+        pick_session(label_entry, options.full_ids, zone, options.picker_default)?
     } else {
         label_entry
             .latest_session()
             .context("No sessions available")?
     };
 
+    let mut dir = match options.cd {
+        Some(ref subdir) => resolve_subdir(Path::new(&session.path), subdir, options.allow_escape)?,
+        None => PathBuf::from(&session.path),
+    };
+    if options.nearest_dir && !dir.is_dir() {
+        let nearest = nearest_existing_dir(&dir)
+            .with_context(|| format!("Neither {:?} nor any parent of it exists", dir))?;
+        eprintln!(
+            "{} {} no longer exists; resuming in {}",
+            "warning:".yellow(),
+            dir.display(),
+            nearest.display()
+        );
+        dir = nearest;
+    }
+
+    let launch = LaunchOptions {
+        prompt: options.prompt.clone(),
+        env: options.env.clone(),
+        shell: options.shell,
+    };
+    resume_session(
+        storage,
+        store.resolve_label_name(chosen),
+        session,
+        &dir,
+        &launch,
+        options.force,
+    )
+}
+
+/// Launch `session` from `dir` the way every command that resumes one does:
+/// unless it's a shell, lock the session (a live lock refuses the launch
+/// unless `force` is set) and bump its resume counter first.
+fn resume_session(
+    storage: &Storage,
+    label: &str,
+    session: &Session,
+    dir: &Path,
+    launch: &LaunchOptions,
+    force: bool,
+) -> Result<()> {
+    // Held until claude exits, so a second resume of the same session is caught
+    let _lock = if launch.shell {
+        None
+    } else {
+        let lock = SessionLock::acquire(&session_lock::runtime_dir(), &session.session_id, force)?;
+        record_resume(storage, label, &session.session_id)?;
+        Some(lock)
+    };
+    launch_and_log(storage, label, session, dir, launch)
+}
+
+/// Bump the resume counter and timestamp of a session.
+fn record_resume(storage: &Storage, label: &str, session_id: &str) -> Result<()> {
+    let now = Utc::now();
+    storage.update(|store| {
+        if let Some(session) = store
+            .get_label_mut(label)
+            .and_then(|l| l.find_session_mut(session_id))
+        {
+            session.resume_count += 1;
+            session.last_resumed_at = Some(now);
+        }
+        Ok(())
+    })
+}
+
+/// Extra ways to start Claude beyond picking the session and directory.
+#[derive(Debug, Default)]
+struct LaunchOptions {
+    /// A first message, passed to `claude` as a trailing positional argument.
+    prompt: Option<String>,
+    /// Extra environment variables for the spawned process.
+    env: Vec<(String, String)>,
+    /// Run the user's shell in the session directory instead of `claude`.
+    shell: bool,
+}
+
+/// The shell `resume --shell` starts: `$SHELL`, or `/bin/sh` when unset.
+fn shell_program(shell_var: Option<OsString>) -> OsString {
+    shell_var
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| OsString::from("/bin/sh"))
+}
+
+/// The command `resume` runs for a session: `claude --resume`, or with
+/// `shell` set an interactive shell, either way from `dir`.
+fn session_command(session: &Session, dir: &Path, launch: &LaunchOptions) -> Command {
+    if !launch.shell {
+        return claude_command(session, dir, launch);
+    }
+    let mut command = Command::new(shell_program(env::var_os("SHELL")));
+    command.current_dir(dir);
+    command.envs(launch.env.iter().map(|(k, v)| (k, v)));
+    command
+}
+
+/// Build the `claude --resume` command for a session, run from `dir`.
+fn claude_command(session: &Session, dir: &Path, launch: &LaunchOptions) -> Command {
+    let mut command = Command::new("claude");
+    command
+        .arg("--resume")
+        .arg(&session.session_id)
+        .current_dir(dir);
+    if let Some(ref prompt) = launch.prompt {
+        // After `--`, a prompt starting with `-` can't be taken for a flag
+        command.arg("--").arg(prompt);
+    }
+    command.envs(launch.env.iter().map(|(k, v)| (k, v)));
+    command
+}
+
+fn launch_session(session: &Session, dir: &Path, launch: &LaunchOptions) -> Result<ExitStatus> {
+    let action = if launch.shell {
+        "Opening a shell for session"
+    } else {
+        "Resuming session"
+    };
     println!(
-        "{} Resuming session: {}",
-        "→".blue(),
+        "{} {}: {}",
+        OutputMode::current().arrow(),
+        action,
         session.session_id.cyan()
     );
-    println!("  In directory: {}", session.path);
+    println!("  In directory: {}", dir.display());
     if let Some(ref desc) = session.description {
         println!("  Description: {}", desc);
     }
     println!();
 
-    // Change to the session's directory and run claude --resume
-    let status = Command::new("claude")
-        .arg("--resume")
-        .arg(&session.session_id)
-        .current_dir(&session.path)
-        .status()
-        .context("Failed to execute claude command")?;
+    let mut command = session_command(session, dir, launch);
+    command.status().with_context(|| {
+        format!(
+            "Failed to execute {}",
+            command.get_program().to_string_lossy()
+        )
+    })
+}
+
+/// Launch a session and append the outcome to the resume history. Failing to
+/// write the history only warns; it never fails the resume.
+fn launch_and_log(
+    storage: &Storage,
+    label: &str,
+    session: &Session,
+    dir: &Path,
+    launch: &LaunchOptions,
+) -> Result<()> {
+    let started = Utc::now();
+    let status = launch_session(session, dir, launch);
+
+    log_resume(
+        storage,
+        &HistoryEntry {
+            at: started,
+            label: label.to_string(),
+            session_id: session.session_id.clone(),
+            dir: dir.to_string_lossy().to_string(),
+            exit_code: status.as_ref().ok().and_then(|s| s.code()),
+        },
+    );
 
-    if !status.success() {
+    let status = status?;
+    // A shell's exit status is just that of the last thing run in it
+    if !status.success() && !launch.shell {
         bail!("Claude exited with status: {}", status);
     }
 
     Ok(())
 }
 
-fn pick_session(label: &crate::data::Label) -> Result<&Session> {
-    let mut sessions: Vec<&Session> = label.sessions.iter().collect();
-    sessions.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+/// Append `entry` to the resume history, unless this is a dry run, which
+/// writes nothing. A failure is only worth a warning.
+fn log_resume(storage: &Storage, entry: &HistoryEntry) {
+    if storage.is_dry_run() {
+        return;
+    }
+    if let Err(err) = history::append(&storage.history_path(), entry) {
+        eprintln!(
+            "{} could not write resume history: {:#}",
+            "warning:".yellow(),
+            err
+        );
+    }
+}
 
-    let items: Vec<String> = sessions
+/// How many characters of a session id the picker shows by default.
+const PICKER_ID_LEN: usize = 8;
+
+/// One picker line per session: the id (cut to `id_len` characters, or in
+/// full when `None`), the creation time in `zone` and the description.
+fn picker_items(sessions: &[&Session], id_len: Option<usize>, zone: DisplayZone) -> Vec<String> {
+    sessions
         .iter()
         .map(|s| {
             let desc = s
@@ -100,169 +652,3087 @@ fn pick_session(label: &crate::data::Label) -> Result<&Session> {
                 .as_ref()
                 .map(|d| format!(" - {}", d))
                 .unwrap_or_default();
-            format!(
-                "{} ({}){}",
-                s.session_id.chars().take(8).collect::<String>(),
-                s.created_at.format("%Y-%m-%d %H:%M"),
-                desc
-            )
+            let id: String = match id_len {
+                Some(len) => s.session_id.chars().take(len).collect(),
+                None => s.session_id.clone(),
+            };
+            let created = format_ts(s.created_at, zone, "%Y-%m-%d %H:%M");
+            format!("{} ({}){}", id, created, desc)
         })
-        .collect();
+        .collect()
+}
+
+/// Which session the picker highlights when it opens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum PickerDefault {
+    /// The most recently created session
+    #[default]
+    Latest,
+    /// The earliest created session, for labels used like a queue
+    Oldest,
+}
+
+/// The index to highlight in picker items built from `sessions`, which are
+/// in `newest_first` order.
+fn picker_default_index(sessions: &[&Session], preference: PickerDefault) -> usize {
+    match preference {
+        PickerDefault::Latest => 0,
+        PickerDefault::Oldest => sessions.len().saturating_sub(1),
+    }
+}
+
+fn pick_session(
+    label: &crate::data::Label,
+    full_ids: bool,
+    zone: DisplayZone,
+    preference: PickerDefault,
+) -> Result<&Session> {
+    let mut sessions: Vec<&Session> = label.sessions.iter().collect();
+    sessions.sort_by(|a, b| newest_first(a, b));
+
+    let id_len = if full_ids { None } else { Some(PICKER_ID_LEN) };
+    let items = picker_items(&sessions, id_len, zone);
 
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Select a session")
         .items(&items)
-        .default(0)
+        .default(picker_default_index(&sessions, preference))
         .interact()
         .context("Failed to get selection")?;
 
     Ok(sessions[selection])
 }
 
-pub fn list(storage: &Storage, label: Option<&str>) -> Result<()> {
-    let store = storage.load()?;
+/// Optional per-session fields that `list <label>` can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListField {
+    Meta,
+}
 
-    match label {
-        Some(label_name) => {
-            let label_entry = store
-                .get_label(label_name)
-                .with_context(|| format!("Label '{}' not found", label_name))?;
+/// Ordering for the all-labels `list` view.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LabelSort {
+    #[default]
+    Name,
+    /// Most recent session activity first
+    Recent,
+}
 
-            println!("{}", label_name.cyan().bold());
-            if let Some(ref desc) = label_entry.description {
-                println!("  {}", desc.dimmed());
-            }
-            println!();
+#[derive(Debug, Default)]
+pub struct ListOptions {
+    pub json: bool,
+    pub compact: bool,
+    /// Spaces per level in JSON output (0 for a single line); overrides `compact`.
+    pub indent: Option<usize>,
+    pub porcelain: bool,
+    pub fields: Vec<ListField>,
+    pub age: bool,
+    /// Color label names by how recent their latest session is.
+    pub heat: bool,
+    /// With `json`, print one summary object per label instead of the store.
+    pub overview: bool,
+    pub sort: LabelSort,
+    pub reverse: bool,
+    pub tree: bool,
+    pub path_filter: Option<PathFilter>,
+    pub resume_filter: ResumeFilter,
+    /// Render markdown in descriptions (only set when output is a color TTY).
+    pub render: bool,
+    /// Terminate porcelain records with NUL instead of newline.
+    pub null: bool,
+    /// Show full paths instead of abbreviating the home directory to `~`.
+    pub no_abbrev: bool,
+    /// Wrap (or, in the label overview, truncate) human output to this many columns.
+    pub width: Option<usize>,
+    /// Only list labels with a session created within this window.
+    pub active_since: Option<chrono::Duration>,
+    /// Write timestamps in JSON and porcelain output as Unix seconds.
+    pub epoch: bool,
+    /// Zone for displayed timestamps, overriding the `time_zone` setting.
+    pub zone: Option<DisplayZone>,
+    /// Which of the sorted labels (or a label's sessions) to show.
+    pub page: Page,
+    /// Colors and Unicode markers, or plain text for pipes.
+    pub mode: OutputMode,
+}
 
-            if label_entry.sessions.is_empty() {
-                println!("  No sessions");
-            } else {
-                let mut sessions: Vec<&Session> = label_entry.sessions.iter().collect();
-                sessions.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-
-                for session in sessions {
-                    println!("  {} {}", "•".green(), session.session_id);
-                    println!("    Path: {}", session.path.dimmed());
-                    println!(
-                        "    Created: {}",
-                        session
-                            .created_at
-                            .format("%Y-%m-%d %H:%M:%S")
-                            .to_string()
-                            .dimmed()
-                    );
-                    if let Some(ref desc) = session.description {
-                        println!("    Description: {}", desc);
-                    }
-                    println!();
-                }
-            }
-        }
-        None => {
-            if store.labels.is_empty() {
-                println!("No labels found.");
-                println!(
-                    "\nUse {} to add a session.",
-                    "claude-sessions add <label> <session-id>".cyan()
-                );
-                return Ok(());
-            }
+/// A `--offset`/`--limit` window over a sorted list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Page {
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
 
-            let mut labels: Vec<(&String, &crate::data::Label)> = store.labels.iter().collect();
-            labels.sort_by_key(|(name, _)| *name);
+impl Page {
+    fn is_active(&self) -> bool {
+        self.offset > 0 || self.limit.is_some()
+    }
+}
 
-            for (name, label_entry) in labels {
-                let session_count = label_entry.sessions.len();
-                let desc = label_entry
-                    .description
-                    .as_ref()
-                    .map(|d| format!(" - {}", d))
-                    .unwrap_or_default();
+/// The items of `items` that fall in `page`, with a footer like
+/// "showing 21–40 of 134" when a window was asked for. An offset past the end
+/// leaves nothing.
+fn paginate<T>(items: Vec<T>, page: Page, mode: OutputMode) -> (Vec<T>, Option<String>) {
+    if !page.is_active() {
+        return (items, None);
+    }
 
-                println!(
-                    "{} ({} session{}){}",
-                    name.cyan().bold(),
-                    session_count,
-                    if session_count == 1 { "" } else { "s" },
-                    desc.dimmed()
-                );
-            }
+    let total = items.len();
+    let start = page.offset.min(total);
+    let end = match page.limit {
+        Some(limit) => start.saturating_add(limit).min(total),
+        None => total,
+    };
+    let footer = if start == end {
+        format!("showing none of {}", total)
+    } else {
+        format!(
+            "showing {}{}{} of {}",
+            start + 1,
+            mode.range_dash(),
+            end,
+            total
+        )
+    };
+    let shown = items.into_iter().skip(start).take(end - start).collect();
+    (shown, Some(footer))
+}
+
+/// Print `text` after `prefix`, wrapped to `width` columns with continuation
+/// lines lined up under the first. Without a width it is printed as-is.
+fn write_wrapped<W: Write>(
+    out: &mut W,
+    prefix: &str,
+    text: &str,
+    width: Option<usize>,
+    dim: bool,
+    mode: OutputMode,
+) -> io::Result<()> {
+    let lines = match width {
+        Some(width) => wrap_to_width(text, width.saturating_sub(prefix.width()).max(10)),
+        None => vec![text.to_string()],
+    };
+    let indent = " ".repeat(prefix.width());
+    for (i, line) in lines.iter().enumerate() {
+        let lead = if i == 0 { prefix } else { indent.as_str() };
+        if dim {
+            writeln!(out, "{}{}", lead, mode.style(line.dimmed()))?;
+        } else {
+            writeln!(out, "{}{}", lead, line)?;
         }
     }
-
     Ok(())
 }
 
-pub fn remove(storage: &Storage, label: &str, session_id: Option<&str>) -> Result<()> {
-    let mut store = storage.load()?;
+/// Print markdown `text` rendered after `prefix`, wrapped like
+/// `write_wrapped` by the width of the rendered text.
+fn write_rendered<W: Write>(
+    out: &mut W,
+    prefix: &str,
+    text: &str,
+    width: Option<usize>,
+    mode: OutputMode,
+) -> io::Result<()> {
+    let width = width.map_or(0, |width| width.saturating_sub(prefix.width()).max(10));
+    let indent = " ".repeat(prefix.width());
+    for (i, line) in render_markdown_wrapped(text, width, mode)
+        .iter()
+        .enumerate()
+    {
+        let lead = if i == 0 { prefix } else { indent.as_str() };
+        writeln!(out, "{}{}", lead, line)?;
+    }
+    Ok(())
+}
 
-    match session_id {
-        Some(sid) => {
-            let label_entry = store
-                .get_label_mut(label)
-                .with_context(|| format!("Label '{}' not found", label))?;
+impl ListOptions {
+    fn time_format(&self) -> TimeFormat {
+        if self.epoch {
+            TimeFormat::Epoch
+        } else {
+            TimeFormat::Rfc3339
+        }
+    }
 
-            if label_entry.remove_session(sid) {
-                storage.save(&store)?;
-                println!(
-                    "{} Removed session '{}' from label '{}'",
-                    "✓".green(),
-                    sid,
-                    label
-                );
-            } else {
-                bail!("Session '{}' not found in label '{}'", sid, label);
-            }
+    fn json_indent(&self) -> usize {
+        match self.indent {
+            Some(indent) => indent,
+            None if self.compact => 0,
+            None => 2,
         }
-        None => {
-            if store.remove_label(label) {
-                storage.save(&store)?;
-                println!("{} Removed label '{}'", "✓".green(), label);
-            } else {
-                bail!("Label '{}' not found", label);
-            }
+    }
+}
+
+/// The home directory to abbreviate in human output, unless disabled.
+fn display_home(no_abbrev: bool) -> Option<PathBuf> {
+    if no_abbrev {
+        None
+    } else {
+        home_dir()
+    }
+}
+
+fn display_path(path: &str, home: Option<&Path>) -> String {
+    match home {
+        Some(home) => abbreviate_home(Path::new(path), home),
+        None => path.to_string(),
+    }
+}
+
+/// Keep only sessions whose recorded directory exists, or only those whose
+/// directory is gone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathFilter {
+    Exists,
+    Missing,
+}
+
+impl PathFilter {
+    fn keeps(self, dir_exists: bool) -> bool {
+        match self {
+            PathFilter::Exists => dir_exists,
+            PathFilter::Missing => !dir_exists,
         }
     }
+}
 
-    Ok(())
+/// Drop sessions rejected by `filter`, using `is_dir` to check each path.
+/// With `drop_empty_labels`, labels left without sessions are removed too.
+fn filter_sessions_by_path<F>(
+    store: &mut Store,
+    filter: PathFilter,
+    is_dir: F,
+    drop_empty_labels: bool,
+) where
+    F: Fn(&Path) -> bool,
+{
+    for label in store.labels.values_mut() {
+        label
+            .sessions
+            .retain(|s| filter.keeps(is_dir(Path::new(&s.path))));
+    }
+    if drop_empty_labels {
+        store.labels.retain(|_, label| !label.sessions.is_empty());
+    }
 }
 
-pub fn describe(storage: &Storage, label: &str, description: Option<String>) -> Result<()> {
-    let mut store = storage.load()?;
+/// Keep only sessions that were never resumed and/or whose last resume is
+/// older than a cutoff. When both are set a session matching either is kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResumeFilter {
+    pub never_resumed: bool,
+    pub resumed_before: Option<DateTime<Utc>>,
+}
 
-    let label_entry = store
-        .get_label_mut(label)
-        .with_context(|| format!("Label '{}' not found", label))?;
+impl ResumeFilter {
+    fn is_active(&self) -> bool {
+        self.never_resumed || self.resumed_before.is_some()
+    }
 
-    label_entry.description = description.clone();
-    storage.save(&store)?;
+    fn keeps(&self, session: &Session) -> bool {
+        (self.never_resumed && is_never_resumed(session))
+            || self
+                .resumed_before
+                .is_some_and(|cutoff| was_resumed_before(session, cutoff))
+    }
+}
 
-    match description {
-        Some(desc) => println!(
-            "{} Updated description for '{}': {}",
-            "✓".green(),
-            label.cyan(),
-            desc
-        ),
-        None => println!("{} Cleared description for '{}'", "✓".green(), label.cyan()),
+/// Keep only labels whose latest session was created at or after `cutoff`;
+/// labels without sessions are dropped.
+fn retain_labels_active_since(store: &mut Store, cutoff: DateTime<Utc>) {
+    store.labels.retain(|_, label| {
+        label
+            .latest_session()
+            .is_some_and(|s| s.created_at >= cutoff)
+    });
+}
+
+/// Sessions without a recorded resume (including data from before resumes
+/// were tracked).
+fn is_never_resumed(session: &Session) -> bool {
+    session.last_resumed_at.is_none()
+}
+
+fn was_resumed_before(session: &Session, cutoff: DateTime<Utc>) -> bool {
+    session.last_resumed_at.is_some_and(|at| at < cutoff)
+}
+
+/// Labels grouped by their `/`-separated name segments, with session counts
+/// aggregated up to every ancestor.
+#[derive(Debug, Default)]
+struct LabelTree {
+    /// Sessions in this node's own label plus all descendants.
+    count: usize,
+    /// Whether a label with exactly this path exists.
+    is_label: bool,
+    children: BTreeMap<String, LabelTree>,
+}
+
+fn build_label_tree(store: &Store) -> LabelTree {
+    let mut root = LabelTree::default();
+    for (name, label) in &store.labels {
+        let count = label.sessions.len();
+        root.count += count;
+
+        let mut node = &mut root;
+        for segment in name.split('/') {
+            node = node.children.entry(segment.to_string()).or_default();
+            node.count += count;
+        }
+        node.is_label = true;
     }
+    root
+}
 
-    Ok(())
+/// A node of the label tree, flattened in display order.
+#[derive(Debug, PartialEq, Eq)]
+struct TreeLine {
+    depth: usize,
+    name: String,
+    count: usize,
+    is_label: bool,
 }
 
-pub fn config(storage: &Storage) -> Result<()> {
-    println!("{}", "Configuration".cyan().bold());
-    println!("  Data file: {}", storage.path().display());
-    Ok(())
+fn flatten_label_tree(tree: &LabelTree) -> Vec<TreeLine> {
+    fn walk(tree: &LabelTree, depth: usize, lines: &mut Vec<TreeLine>) {
+        for (name, child) in &tree.children {
+            lines.push(TreeLine {
+                depth,
+                name: name.clone(),
+                count: child.count,
+                is_label: child.is_label,
+            });
+            walk(child, depth + 1, lines);
+        }
+    }
+
+    let mut lines = Vec::new();
+    walk(tree, 0, &mut lines);
+    lines
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::data::{Label, Store};
-    use chrono::{TimeZone, Utc};
-    use std::fs;
-    use std::path::PathBuf;
+fn sort_labels(labels: &mut [(&String, &Label)], sort: LabelSort, reverse: bool) {
+    match sort {
+        LabelSort::Name => labels.sort_by_key(|(name, _)| *name),
+        LabelSort::Recent => {
+            labels.sort_by_key(|(name, label)| (Reverse(label.last_activity()), *name))
+        }
+    }
+    if reverse {
+        labels.reverse();
+    }
+}
+
+fn format_last_activity(label: &Label, now: DateTime<Utc>) -> String {
+    label
+        .last_activity()
+        .map(|at| humanize_age(now - at))
+        .unwrap_or_else(|| "never".to_string())
+}
+
+/// Write each record followed by a newline, or by a NUL byte for `--null`.
+fn write_records<W, I>(out: &mut W, records: I, null: bool) -> Result<()>
+where
+    W: Write,
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let terminator: &[u8] = if null { b"\0" } else { b"\n" };
+    for record in records {
+        out.write_all(record.as_ref().as_bytes())?;
+        out.write_all(terminator)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Escape a field for porcelain output so it can never contain a tab or newline.
+fn porcelain_escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn porcelain_record(fields: &[&str]) -> String {
+    fields
+        .iter()
+        .map(|f| porcelain_escape(f))
+        .collect::<Vec<_>>()
+        .join("\t")
+}
+
+/// One record per label: `label, session_count, description`.
+fn porcelain_labels(store: &Store) -> Vec<String> {
+    sorted_label_names(store)
+        .into_iter()
+        .map(|name| {
+            let label = &store.labels[name];
+            porcelain_record(&[
+                name,
+                &label.sessions.len().to_string(),
+                label.description.as_deref().unwrap_or(""),
+            ])
+        })
+        .collect()
+}
+
+/// One record per session, newest first:
+/// `label, session_id, created_at, path, description`.
+fn porcelain_sessions(label_name: &str, label: &Label, time: TimeFormat) -> Vec<String> {
+    let mut sessions: Vec<&Session> = label.sessions.iter().collect();
+    sessions.sort_by(|a, b| newest_first(a, b));
+    sessions
+        .into_iter()
+        .map(|s| {
+            porcelain_record(&[
+                label_name,
+                &s.session_id,
+                &time.format(s.created_at),
+                &s.path,
+                s.description.as_deref().unwrap_or(""),
+            ])
+        })
+        .collect()
+}
+
+fn format_meta(meta: &HashMap<String, String>) -> String {
+    let mut pairs: Vec<(&String, &String)> = meta.iter().collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn render_json<T: Serialize>(value: &T, compact: bool) -> Result<String> {
+    render_json_indented(value, if compact { 0 } else { 2 })
+}
+
+/// Serialize `value` pretty-printed with `indent` spaces per level, or on a
+/// single line when `indent` is 0.
+fn render_json_indented<T: Serialize>(value: &T, indent: usize) -> Result<String> {
+    if indent == 0 {
+        return serde_json::to_string(value).context("Could not serialize output");
+    }
+
+    let indent = " ".repeat(indent);
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut buf = Vec::new();
+    let mut serializer = serde_json::Serializer::with_formatter(&mut buf, formatter);
+    value
+        .serialize(&mut serializer)
+        .context("Could not serialize output")?;
+    String::from_utf8(buf).context("Could not serialize output")
+}
+
+pub fn list(storage: &Storage, label: Option<&str>, options: &ListOptions) -> Result<()> {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    list_to(&mut out, storage, label, options)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// `list`, writing to `out` instead of stdout.
+fn list_to<W: Write>(
+    out: &mut W,
+    storage: &Storage,
+    label: Option<&str>,
+    options: &ListOptions,
+) -> Result<()> {
+    if options.json && options.porcelain {
+        bail!("--json and --porcelain cannot be used together");
+    }
+
+    let mut store = storage.load_view()?;
+    let zone = options
+        .zone
+        .unwrap_or_else(|| store.settings.display_zone());
+    if let Some(filter) = options.path_filter {
+        filter_sessions_by_path(&mut store, filter, Path::is_dir, label.is_none());
+    }
+    if let Some(window) = options.active_since {
+        retain_labels_active_since(&mut store, time_ago(window, Utc::now())?);
+    }
+    if options.resume_filter.is_active() {
+        for label_entry in store.labels.values_mut() {
+            label_entry
+                .sessions
+                .retain(|s| options.resume_filter.keeps(s));
+        }
+        if label.is_none() {
+            store.labels.retain(|_, l| !l.sessions.is_empty());
+        }
+    }
+
+    match label {
+        Some(label_name) => {
+            let label_entry = store
+                .get_label(label_name)
+                .with_context(|| format!("Label '{}' not found", label_name))?;
+
+            if options.json {
+                let label_out = LabelOut::new(label_entry, options.time_format());
+                writeln!(
+                    out,
+                    "{}",
+                    render_json_indented(&label_out, options.json_indent())?
+                )?;
+                return Ok(());
+            }
+            if options.porcelain {
+                let (records, _) = paginate(
+                    porcelain_sessions(label_name, label_entry, options.time_format()),
+                    options.page,
+                    OutputMode::Plain,
+                );
+                return write_records(out, records, options.null).context("Could not write output");
+            }
+
+            write_label_details(out, label_name, label_entry, options, zone)?;
+        }
+        None => {
+            if options.json && options.overview {
+                let overview = label_overview(&store, options);
+                writeln!(
+                    out,
+                    "{}",
+                    render_json_indented(&overview, options.json_indent())?
+                )?;
+                return Ok(());
+            }
+            if options.json {
+                // Streamed so a large store isn't rendered into one big string
+                output::write_store_json(
+                    &mut *out,
+                    &store,
+                    options.time_format(),
+                    options.json_indent(),
+                )
+                .context("Could not serialize output")?;
+                writeln!(out)?;
+                return Ok(());
+            }
+            if options.porcelain {
+                let (records, _) =
+                    paginate(porcelain_labels(&store), options.page, OutputMode::Plain);
+                return write_records(out, records, options.null).context("Could not write output");
+            }
+
+            write_label_summaries(out, &store, options)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The human `list <label>` view: the label's description, then its
+/// sessions newest first.
+fn write_label_details<W: Write>(
+    out: &mut W,
+    label_name: &str,
+    label_entry: &Label,
+    options: &ListOptions,
+    zone: DisplayZone,
+) -> io::Result<()> {
+    let mode = options.mode;
+    writeln!(
+        out,
+        "{}",
+        mode.style(style_label_name(label_name, label_entry.color.as_deref()))
+    )?;
+    if let Some(ref desc) = label_entry.description {
+        if options.render {
+            write_rendered(out, "  ", desc, options.width, mode)?;
+        } else {
+            write_wrapped(out, "  ", desc, options.width, true, mode)?;
+        }
+    }
+    writeln!(out)?;
+
+    if label_entry.sessions.is_empty() {
+        return writeln!(out, "  No sessions");
+    }
+
+    let home = display_home(options.no_abbrev);
+    let mut sessions: Vec<&Session> = label_entry.sessions.iter().collect();
+    sessions.sort_by(|a, b| newest_first(a, b));
+    let (sessions, footer) = paginate(sessions, options.page, mode);
+
+    for session in sessions {
+        writeln!(out, "  {} {}", mode.bullet(), session.session_id)?;
+        write_wrapped(
+            out,
+            "    Path: ",
+            &display_path(&session.path, home.as_deref()),
+            options.width,
+            true,
+            mode,
+        )?;
+        writeln!(
+            out,
+            "    Created: {}",
+            mode.style(format_ts(session.created_at, zone, "%Y-%m-%d %H:%M:%S").dimmed())
+        )?;
+        if let Some(ref desc) = session.description {
+            if options.render {
+                write_rendered(out, "    Description: ", desc, options.width, mode)?;
+            } else {
+                write_wrapped(out, "    Description: ", desc, options.width, false, mode)?;
+            }
+        }
+        if options.fields.contains(&ListField::Meta) && !session.meta.is_empty() {
+            writeln!(out, "    Meta: {}", format_meta(&session.meta))?;
+        }
+        writeln!(out)?;
+    }
+    if let Some(footer) = footer {
+        writeln!(out, "  {}", mode.style(footer.dimmed()))?;
+    }
+    Ok(())
+}
+
+/// The human `list` view of the whole store: a line per label, or with
+/// `tree` the labels nested by their `/`-separated names.
+fn write_label_summaries<W: Write>(
+    out: &mut W,
+    store: &Store,
+    options: &ListOptions,
+) -> io::Result<()> {
+    let mode = options.mode;
+    if store.labels.is_empty() {
+        writeln!(out, "No labels found.")?;
+        return writeln!(
+            out,
+            "\nUse {} to add a session.",
+            mode.style("claude-sessions add <label> <session-id>".cyan())
+        );
+    }
+
+    if options.tree {
+        for line in flatten_label_tree(&build_label_tree(store)) {
+            let name = if line.is_label {
+                line.name.cyan().bold()
+            } else {
+                line.name.normal()
+            };
+            writeln!(
+                out,
+                "{}{} ({} session{})",
+                "  ".repeat(line.depth),
+                mode.style(name),
+                line.count,
+                if line.count == 1 { "" } else { "s" }
+            )?;
+        }
+        return Ok(());
+    }
+
+    let mut labels: Vec<(&String, &Label)> = store.labels.iter().collect();
+    sort_labels(&mut labels, options.sort, options.reverse);
+    let (labels, footer) = paginate(labels, options.page, mode);
+
+    let now = Utc::now();
+    let colors_on =
+        mode == OutputMode::Styled && colored::control::SHOULD_COLORIZE.should_colorize();
+    for (name, label_entry) in labels {
+        let session_count = label_entry.sessions.len();
+        let activity_age = label_entry.last_activity().map(|at| now - at);
+        let mut styled_name = mode.style(style_label_name(name, label_entry.color.as_deref()));
+        let mut marker = String::new();
+        if options.heat {
+            if colors_on {
+                styled_name = name.color(heat_color(activity_age)).bold();
+            } else {
+                marker = format!(" {}", Heat::of(activity_age).marker());
+            }
+        }
+        let age = if options.age {
+            format!(", {}", format_last_activity(label_entry, now))
+        } else {
+            String::new()
+        };
+        let mut desc = label_entry
+            .description
+            .as_ref()
+            .map(|d| format!(" - {}", d))
+            .unwrap_or_default();
+        if let Some(width) = options.width {
+            let summary = format!(
+                "{}{} ({} session{}{})",
+                name,
+                marker,
+                session_count,
+                if session_count == 1 { "" } else { "s" },
+                age
+            );
+            desc = truncate_to_width(&desc, width.saturating_sub(summary.width()));
+        }
+
+        writeln!(
+            out,
+            "{}{} ({} session{}{}){}",
+            styled_name,
+            marker,
+            session_count,
+            if session_count == 1 { "" } else { "s" },
+            age,
+            mode.style(desc.dimmed())
+        )?;
+    }
+    if let Some(footer) = footer {
+        writeln!(out, "{}", mode.style(footer.dimmed()))?;
+    }
+    Ok(())
+}
+
+/// The `list --json --overview` array: a summary per label, in the order
+/// `--sort` and `--reverse` give.
+fn label_overview<'a>(store: &'a Store, options: &ListOptions) -> Vec<LabelSummaryOut<'a>> {
+    let mut labels: Vec<(&String, &Label)> = store.labels.iter().collect();
+    sort_labels(&mut labels, options.sort, options.reverse);
+    labels
+        .into_iter()
+        .map(|(name, label)| LabelSummaryOut::new(name, label, options.time_format()))
+        .collect()
+}
+
+fn sorted_label_names(store: &Store) -> Vec<&String> {
+    let mut names: Vec<&String> = store.labels.keys().collect();
+    names.sort();
+    names
+}
+
+/// The names offered by the label picker: every label, in name order.
+fn label_candidates(store: &Store) -> Vec<String> {
+    sorted_label_names(store).into_iter().cloned().collect()
+}
+
+/// Ask for a label with a fuzzy-searchable picker, for commands run without
+/// one in a terminal.
+pub fn pick_label(storage: &Storage) -> Result<String> {
+    let store = storage.load()?;
+    let mut candidates = label_candidates(&store);
+    if candidates.is_empty() {
+        bail!("No labels found");
+    }
+
+    let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select a label")
+        .items(&candidates)
+        .default(0)
+        .interact()
+        .context("Failed to get selection")?;
+    Ok(candidates.swap_remove(selection))
+}
+
+pub fn labels(storage: &Storage, count: bool, null: bool) -> Result<()> {
+    labels_to(&mut io::stdout().lock(), storage, count, null).context("Could not write output")
+}
+
+/// `labels`, writing to `out` instead of stdout.
+fn labels_to<W: Write>(out: &mut W, storage: &Storage, count: bool, null: bool) -> Result<()> {
+    let names = storage.load_label_names()?;
+
+    if count {
+        writeln!(out, "{}", names.len())?;
+        out.flush()?;
+        return Ok(());
+    }
+
+    write_records(out, names, null)
+}
+
+/// Sessions across all labels created within `[since, until]`, newest first,
+/// capped at `count`.
+fn recent_sessions(
+    store: &Store,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    count: usize,
+) -> Vec<(&String, &Session)> {
+    let mut sessions: Vec<(&String, &Session)> = store
+        .sessions()
+        .filter(|(_, s)| since.is_none_or(|since| s.created_at >= since))
+        .filter(|(_, s)| until.is_none_or(|until| s.created_at <= until))
+        .collect();
+    sessions.sort_by(|(_, a), (_, b)| newest_first(a, b));
+    sessions.truncate(count);
+    sessions
+}
+
+pub fn recent(
+    storage: &Storage,
+    count: usize,
+    since: Option<&str>,
+    until: Option<&str>,
+    zone: Option<DisplayZone>,
+) -> Result<()> {
+    let store = storage.load()?;
+    let zone = zone.unwrap_or_else(|| store.settings.display_zone());
+
+    let now = Utc::now();
+    let since = since.map(|s| parse_time_bound(s, now)).transpose()?;
+    let until = until.map(|s| parse_time_upper_bound(s, now)).transpose()?;
+
+    let sessions = recent_sessions(&store, since, until, count);
+    if sessions.is_empty() {
+        println!("No sessions found.");
+        return Ok(());
+    }
+
+    for (label, session) in sessions {
+        let desc = session
+            .description
+            .as_ref()
+            .map(|d| format!(" - {}", d))
+            .unwrap_or_default();
+        println!(
+            "{} {} {}{}",
+            format_ts(session.created_at, zone, "%Y-%m-%d %H:%M").dimmed(),
+            label.cyan(),
+            session.session_id,
+            desc.dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+pub fn clone_label(storage: &Storage, source: &str, dest: &str, with_sessions: bool) -> Result<()> {
+    let mut store = storage.load()?;
+
+    if store.get_label(source).is_none() {
+        bail!("Label '{}' not found", source);
+    }
+    if store.get_label(dest).is_some() {
+        bail!("Label '{}' already exists", dest);
+    }
+
+    store.clone_label(source, dest, with_sessions);
+    let session_count = store.get_label(dest).map_or(0, |l| l.sessions.len());
+
+    storage.save(&store)?;
+
+    println!(
+        "{} Cloned label '{}' to '{}'",
+        OutputMode::current().ok_mark(),
+        source.cyan(),
+        dest.cyan()
+    );
+    if with_sessions {
+        println!(
+            "  Copied {} session{}",
+            session_count,
+            if session_count == 1 { "" } else { "s" }
+        );
+    }
+
+    Ok(())
+}
+
+/// Rename every label in an incoming store to `<prefix><name>`.
+fn prefix_labels(incoming: Store, prefix: &str) -> Store {
+    Store {
+        labels: incoming
+            .labels
+            .into_iter()
+            .map(|(name, label)| (format!("{}{}", prefix, name), label))
+            .collect(),
+        ..incoming
+    }
+}
+
+/// Ask what to do with an imported label whose name is already taken.
+fn prompt_label_resolution(name: &str) -> Result<LabelResolution> {
+    let choice = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Label '{}' already exists", name))
+        .items(&["Merge", "Overwrite", "Skip"])
+        .default(0)
+        .interact()
+        .context("Failed to get selection")?;
+    Ok(match choice {
+        0 => LabelResolution::Merge,
+        1 => LabelResolution::Overwrite,
+        _ => LabelResolution::Skip,
+    })
+}
+
+pub fn import(
+    storage: &Storage,
+    file: &Path,
+    overwrite: bool,
+    interactive: bool,
+    prefix: Option<&str>,
+    keep: KeepOnConflict,
+) -> Result<()> {
+    if !file.exists() {
+        bail!("Import file not found: {:?}", file);
+    }
+    let mut incoming = load_import_file(file, storage.is_strict_load())?;
+    if let Some(prefix) = prefix {
+        incoming = prefix_labels(incoming, prefix);
+    }
+
+    let mut store = storage.load()?;
+
+    let mut names: Vec<String> = incoming.labels.keys().cloned().collect();
+    names.sort();
+
+    let default_resolution = if overwrite {
+        LabelResolution::Overwrite
+    } else {
+        LabelResolution::Merge
+    };
+
+    let (mut added, mut merged, mut overwritten, mut skipped) = (0, 0, 0, 0);
+    for name in names {
+        let label = incoming.labels.remove(&name).unwrap();
+        let resolution = if interactive && store.labels.contains_key(&name) {
+            prompt_label_resolution(&name)?
+        } else {
+            default_resolution
+        };
+        match store.import_label(name.clone(), label, resolution, keep) {
+            ImportOutcome::Added => {
+                added += 1;
+                println!("  {} {}", "+".green(), name);
+            }
+            ImportOutcome::Merged {
+                sessions,
+                conflicts,
+            } => {
+                merged += 1;
+                println!(
+                    "  {} {} ({} new session{})",
+                    "~".yellow(),
+                    name,
+                    sessions,
+                    if sessions == 1 { "" } else { "s" }
+                );
+                for conflict in conflicts {
+                    eprintln!(
+                        "{} session '{}' in '{}' has a different created_at (existing {}, imported {}); kept the {} copy",
+                        "warning:".yellow(),
+                        conflict.session_id,
+                        name,
+                        conflict.existing.to_rfc3339(),
+                        conflict.incoming.to_rfc3339(),
+                        if conflict.kept_incoming { "imported" } else { "existing" }
+                    );
+                }
+            }
+            ImportOutcome::Overwritten => {
+                overwritten += 1;
+                println!("  {} {} (overwritten)", "!".red(), name);
+            }
+            ImportOutcome::Skipped => {
+                skipped += 1;
+                println!("  {} {} (skipped)", "-".dimmed(), name);
+            }
+        }
+    }
+
+    storage.save(&store)?;
+
+    println!(
+        "{} Imported {} new, {} merged, {} overwritten, {} skipped",
+        OutputMode::current().ok_mark(),
+        added,
+        merged,
+        overwritten,
+        skipped
+    );
+
+    Ok(())
+}
+
+/// Print `problems` as a report; fails when there are any, so the process
+/// exits non-zero.
+fn report_problems(problems: &[Problem]) -> Result<()> {
+    if problems.is_empty() {
+        println!("{} No problems found", OutputMode::current().ok_mark());
+        return Ok(());
+    }
+    for problem in problems {
+        println!("  {} {}", OutputMode::current().fail_mark(), problem);
+    }
+    bail!(
+        "{} problem{} found",
+        problems.len(),
+        if problems.len() == 1 { "" } else { "s" }
+    )
+}
+
+/// Check the live store for duplicate ids, missing paths and empty labels.
+/// With `fix_paths`, ask what to do about each missing path instead.
+pub fn doctor(storage: &Storage, fix_paths: bool) -> Result<()> {
+    let store = storage.load()?;
+    let problems = doctor::scan(&store, Path::is_dir);
+    if fix_paths {
+        return fix_missing_paths(storage, &problems);
+    }
+    report_problems(&problems)
+}
+
+/// Prompt for a replacement directory, removal or skip for each missing path,
+/// then apply every decision in one update.
+fn fix_missing_paths(storage: &Storage, problems: &[Problem]) -> Result<()> {
+    let mut repairs = Vec::new();
+    for problem in problems {
+        let Problem::MissingPath {
+            label,
+            session_id,
+            path,
+        } = problem
+        else {
+            continue;
+        };
+
+        println!("{}", problem);
+        let choice = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("What should happen to this session?")
+            .items(&["Enter a new path", "Remove the session", "Skip"])
+            .default(0)
+            .interact()
+            .context("Failed to get selection")?;
+        let repair = match choice {
+            0 => {
+                let new_path: String = Input::with_theme(&ColorfulTheme::default())
+                    .with_prompt("New path")
+                    .validate_with(|input: &String| {
+                        if Path::new(input).is_dir() {
+                            Ok(())
+                        } else {
+                            Err("not an existing directory")
+                        }
+                    })
+                    .interact_text()
+                    .context("Failed to read path")?;
+                PathRepair::Replace(new_path)
+            }
+            1 => PathRepair::Remove,
+            _ => PathRepair::Skip,
+        };
+        repairs.push((label, session_id, path, repair));
+    }
+
+    if repairs.is_empty() {
+        println!("{} No missing paths", OutputMode::current().ok_mark());
+        return Ok(());
+    }
+
+    let changed = storage.update(|store| {
+        Ok(repairs
+            .iter()
+            .filter(|(label, session_id, path, repair)| {
+                doctor::apply_repair(store, label, session_id, path, repair)
+            })
+            .count())
+    })?;
+    println!(
+        "{} Repaired {} session{}",
+        OutputMode::current().ok_mark(),
+        changed,
+        if changed == 1 { "" } else { "s" }
+    );
+    Ok(())
+}
+
+/// Read an export file, or a directory of flat-layout `<label>.json` files,
+/// rejecting unknown keys when `strict` is set.
+fn load_import_file(file: &Path, strict: bool) -> Result<Store> {
+    let mut reader = Storage::with_path(file.to_path_buf());
+    reader.set_strict_load(strict);
+    if file.is_dir() {
+        return reader.load_label_dir(file);
+    }
+    reader.load()
+}
+
+/// Run the `doctor` checks on an export file without importing it.
+pub fn validate_import(file: &Path, strict: bool) -> Result<()> {
+    validate_import_with(file, strict, Path::is_dir)
+}
+
+fn validate_import_with<F>(file: &Path, strict: bool, is_dir: F) -> Result<()>
+where
+    F: Fn(&Path) -> bool,
+{
+    if !file.exists() {
+        bail!("Import file not found: {:?}", file);
+    }
+    let incoming = load_import_file(file, strict)?;
+    println!(
+        "{} parses: {} label{}",
+        file.display(),
+        incoming.labels.len(),
+        if incoming.labels.len() == 1 { "" } else { "s" }
+    );
+    report_problems(&doctor::scan(&incoming, is_dir))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// The data.json format, importable with `import`
+    #[default]
+    Json,
+    /// One row per session: label, session_id, path, created_at, description
+    Csv,
+}
+
+fn export_csv(store: &Store) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["label", "session_id", "path", "created_at", "description"])?;
+
+    for name in sorted_label_names(store) {
+        let mut sessions: Vec<&Session> = store.labels[name].sessions.iter().collect();
+        sessions.sort_by_key(|s| s.created_at);
+        for session in sessions {
+            writer.write_record([
+                name.as_str(),
+                &session.session_id,
+                &session.path,
+                &session.created_at.to_rfc3339(),
+                session.description.as_deref().unwrap_or(""),
+            ])?;
+        }
+    }
+
+    let bytes = writer.into_inner().context("Could not write CSV")?;
+    String::from_utf8(bytes).context("CSV output was not valid UTF-8")
+}
+
+fn render_export(store: &Store, format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Json => {
+            // Preferences are personal; only share the labels.
+            let exported = Store {
+                labels: store.labels.clone(),
+                ..Store::new()
+            };
+            render_json(&exported, false)
+        }
+        ExportFormat::Csv => export_csv(store),
+    }
+}
+
+/// A store holding only the label `name` (resolving aliases), so exporting it
+/// imports cleanly elsewhere.
+fn single_label_store(store: &Store, name: &str) -> Result<Store> {
+    let label = store
+        .get_label(name)
+        .with_context(|| format!("Label '{}' not found", name))?;
+    let mut single = Store::new();
+    single
+        .labels
+        .insert(store.resolve_label_name(name).to_string(), label.clone());
+    Ok(single)
+}
+
+/// A placeholder for `path` that reveals nothing about it but is the same
+/// for the same path, so sessions sharing a directory still do.
+fn anonymized_path(path: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(path.as_bytes()));
+    format!("/anonymized/{}", &digest[..12])
+}
+
+/// A copy of `store` safe to attach to a bug report: paths are replaced with
+/// placeholders, and descriptions and metadata values are blanked. Label
+/// names, session ids, timestamps and counts are kept.
+fn anonymize_store(store: &Store) -> Store {
+    let mut anonymized = store.clone();
+    for label in anonymized.labels.values_mut() {
+        label.description = None;
+        label.description_history.clear();
+        for session in &mut label.sessions {
+            session.path = anonymized_path(&session.path);
+            session.description = None;
+            for value in session.meta.values_mut() {
+                value.clear();
+            }
+        }
+    }
+    anonymized
+}
+
+pub fn export(
+    storage: &Storage,
+    file: Option<&Path>,
+    format: ExportFormat,
+    label: Option<&str>,
+    anonymize: bool,
+) -> Result<()> {
+    let mut store = storage.load_view()?;
+    if let Some(label) = label {
+        store = single_label_store(&store, label)?;
+    }
+    if anonymize {
+        store = anonymize_store(&store);
+    }
+    let output = render_export(&store, format)?;
+
+    match file {
+        Some(file) => {
+            fs::write(file, &output)
+                .with_context(|| format!("Could not write export file: {:?}", file))?;
+            println!(
+                "{} Exported {} label{} to {}",
+                OutputMode::current().ok_mark(),
+                store.labels.len(),
+                if store.labels.len() == 1 { "" } else { "s" },
+                file.display()
+            );
+        }
+        None => print!("{}", output),
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct SearchOptions {
+    /// Only match label names and session ids.
+    pub names_only: bool,
+    /// Only scan the first N characters of each description.
+    pub max_desc_len: Option<usize>,
+    /// Print the number of matching sessions instead of listing them.
+    pub count_only: bool,
+    /// Show full paths instead of abbreviating the home directory to `~`.
+    pub no_abbrev: bool,
+    /// Only scan labels matching one of these patterns (`*` and `?` wildcards).
+    /// Empty scans every label.
+    pub labels: Vec<String>,
+}
+
+/// Match `text` against a pattern where `*` stands for any run of characters
+/// and `?` for exactly one. Everything else matches literally.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` seen and the text index it is currently absorbing up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, absorbed)) => {
+                    p = star + 1;
+                    t = absorbed + 1;
+                    backtrack = Some((star, t));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn contains_ci(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(needle)
+}
+
+fn description_matches(description: Option<&str>, needle: &str, max_len: Option<usize>) -> bool {
+    let Some(description) = description else {
+        return false;
+    };
+    match max_len {
+        Some(max_len) => {
+            let scanned: String = description.chars().take(max_len).collect();
+            contains_ci(&scanned, needle)
+        }
+        None => contains_ci(description, needle),
+    }
+}
+
+/// Sessions matching `query` (case-insensitive), ordered by label name then newest first.
+fn search_sessions<'a>(
+    store: &'a Store,
+    query: &str,
+    options: &SearchOptions,
+) -> Vec<(&'a String, &'a Session)> {
+    let needle = query.to_lowercase();
+    let mut hits = Vec::new();
+
+    for name in sorted_label_names(store) {
+        if !options.labels.is_empty() && !options.labels.iter().any(|p| glob_match(p, name)) {
+            continue;
+        }
+        let label = &store.labels[name];
+        let label_matches = contains_ci(name, &needle)
+            || (!options.names_only
+                && description_matches(
+                    label.description.as_deref(),
+                    &needle,
+                    options.max_desc_len,
+                ));
+
+        let mut sessions: Vec<&Session> = label.sessions.iter().collect();
+        sessions.sort_by(|a, b| newest_first(a, b));
+        for session in sessions {
+            let session_matches = contains_ci(&session.session_id, &needle)
+                || (!options.names_only
+                    && (contains_ci(&session.path, &needle)
+                        || description_matches(
+                            session.description.as_deref(),
+                            &needle,
+                            options.max_desc_len,
+                        )));
+            if label_matches || session_matches {
+                hits.push((name, session));
+            }
+        }
+    }
+
+    hits
+}
+
+pub fn search(storage: &Storage, query: &str, options: &SearchOptions) -> Result<()> {
+    let store = storage.load_view()?;
+
+    let hits = search_sessions(&store, query, options);
+    if options.count_only {
+        println!("{}", hits.len());
+        return Ok(());
+    }
+
+    if hits.is_empty() {
+        println!("No sessions matching '{}'", query);
+        return Ok(());
+    }
+
+    let home = display_home(options.no_abbrev);
+    for (label, session) in hits {
+        let desc = session
+            .description
+            .as_ref()
+            .map(|d| format!(" - {}", d))
+            .unwrap_or_default();
+        println!(
+            "{} {} {}{}",
+            label.cyan(),
+            session.session_id,
+            display_path(&session.path, home.as_deref()).dimmed(),
+            desc.dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+pub fn dedup(storage: &Storage, label: Option<&str>, merge: bool) -> Result<()> {
+    let mut store = storage.load()?;
+
+    let names: Vec<String> = match label {
+        Some(name) => {
+            if store.get_label(name).is_none() {
+                bail!("Label '{}' not found", name);
+            }
+            vec![name.to_string()]
+        }
+        None => sorted_label_names(&store).into_iter().cloned().collect(),
+    };
+
+    let mut total = 0;
+    for name in names {
+        let removed = store.labels.get_mut(&name).unwrap().dedup_sessions(merge);
+        if removed > 0 {
+            println!(
+                "  {}: removed {} duplicate session{}",
+                name.cyan(),
+                removed,
+                if removed == 1 { "" } else { "s" }
+            );
+        }
+        total += removed;
+    }
+
+    if total == 0 {
+        println!("No duplicate sessions found.");
+        return Ok(());
+    }
+
+    storage.save(&store)?;
+    println!(
+        "{} Removed {} duplicate session{}{}",
+        OutputMode::current().ok_mark(),
+        total,
+        if total == 1 { "" } else { "s" },
+        if merge { " (merged)" } else { "" }
+    );
+
+    Ok(())
+}
+
+/// Which menu `browse` is showing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BrowseScreen {
+    Labels,
+    Sessions { label: String },
+    Session { label: String, session_id: String },
+}
+
+/// An entry in a `browse` menu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BrowseItem {
+    Label { name: String, sessions: usize },
+    Session { id: String, summary: String },
+    Resume,
+    Details,
+    Remove,
+    Back,
+    Quit,
+}
+
+impl BrowseItem {
+    fn text(&self) -> String {
+        match self {
+            BrowseItem::Label { name, sessions } => format!(
+                "{} ({} session{})",
+                name,
+                sessions,
+                if *sessions == 1 { "" } else { "s" }
+            ),
+            BrowseItem::Session { id, summary } => format!("{} {}", id, summary),
+            BrowseItem::Resume => "Resume".to_string(),
+            BrowseItem::Details => "Show details".to_string(),
+            BrowseItem::Remove => "Remove".to_string(),
+            BrowseItem::Back => "← Back".to_string(),
+            BrowseItem::Quit => "Quit".to_string(),
+        }
+    }
+}
+
+/// What `browse` does after an item is chosen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BrowseStep {
+    Show(BrowseScreen),
+    Resume { label: String, session_id: String },
+    Details { label: String, session_id: String },
+    Remove { label: String, session_id: String },
+    Quit,
+}
+
+/// The items to offer on `screen`, with times shown in `zone`. Labels or
+/// sessions that disappeared (e.g. after a removal) simply leave the menu
+/// with only `Back`.
+fn browse_menu(store: &Store, screen: &BrowseScreen, zone: DisplayZone) -> Vec<BrowseItem> {
+    match screen {
+        BrowseScreen::Labels => {
+            let mut items: Vec<BrowseItem> = sorted_label_names(store)
+                .into_iter()
+                .map(|name| BrowseItem::Label {
+                    name: name.clone(),
+                    sessions: store.labels[name].sessions.len(),
+                })
+                .collect();
+            items.push(BrowseItem::Quit);
+            items
+        }
+        BrowseScreen::Sessions { label } => {
+            let mut items = Vec::new();
+            if let Some(label_entry) = store.get_label(label) {
+                let mut sessions: Vec<&Session> = label_entry.sessions.iter().collect();
+                sessions.sort_by(|a, b| newest_first(a, b));
+                items.extend(sessions.into_iter().map(|s| BrowseItem::Session {
+                    id: s.session_id.clone(),
+                    summary: format!(
+                        "({}){}",
+                        format_ts(s.created_at, zone, "%Y-%m-%d %H:%M"),
+                        s.description
+                            .as_ref()
+                            .map(|d| format!(" - {}", d))
+                            .unwrap_or_default()
+                    ),
+                }));
+            }
+            items.push(BrowseItem::Back);
+            items
+        }
+        BrowseScreen::Session { label, session_id } => {
+            let exists = store
+                .get_label(label)
+                .is_some_and(|l| l.sessions.iter().any(|s| &s.session_id == session_id));
+            if exists {
+                vec![
+                    BrowseItem::Resume,
+                    BrowseItem::Details,
+                    BrowseItem::Remove,
+                    BrowseItem::Back,
+                ]
+            } else {
+                vec![BrowseItem::Back]
+            }
+        }
+    }
+}
+
+/// The transition taken when `item` is chosen on `screen`.
+fn browse_next(screen: &BrowseScreen, item: &BrowseItem) -> BrowseStep {
+    match (screen, item) {
+        (_, BrowseItem::Quit) => BrowseStep::Quit,
+        (BrowseScreen::Labels, BrowseItem::Back) => BrowseStep::Quit,
+        (_, BrowseItem::Label { name, .. }) => BrowseStep::Show(BrowseScreen::Sessions {
+            label: name.clone(),
+        }),
+        (BrowseScreen::Sessions { label }, BrowseItem::Session { id, .. }) => {
+            BrowseStep::Show(BrowseScreen::Session {
+                label: label.clone(),
+                session_id: id.clone(),
+            })
+        }
+        (BrowseScreen::Session { label, session_id }, BrowseItem::Resume) => BrowseStep::Resume {
+            label: label.clone(),
+            session_id: session_id.clone(),
+        },
+        (BrowseScreen::Session { label, session_id }, BrowseItem::Details) => BrowseStep::Details {
+            label: label.clone(),
+            session_id: session_id.clone(),
+        },
+        (BrowseScreen::Session { label, session_id }, BrowseItem::Remove) => BrowseStep::Remove {
+            label: label.clone(),
+            session_id: session_id.clone(),
+        },
+        (BrowseScreen::Session { label, .. }, BrowseItem::Back) => {
+            BrowseStep::Show(BrowseScreen::Sessions {
+                label: label.clone(),
+            })
+        }
+        // Back from a session list, or an item that doesn't belong on this screen
+        _ => BrowseStep::Show(BrowseScreen::Labels),
+    }
+}
+
+fn find_session<'a>(store: &'a Store, label: &str, session_id: &str) -> Result<&'a Session> {
+    store
+        .get_label(label)
+        .with_context(|| format!("Label '{}' not found", label))?
+        .sessions
+        .iter()
+        .find(|s| s.session_id == session_id)
+        .with_context(|| format!("Session '{}' not found in label '{}'", session_id, label))
+}
+
+/// Interactively browse labels and their sessions. Times are shown in `zone`,
+/// or the `time_zone` setting when `None`.
+pub fn browse(
+    storage: &Storage,
+    no_abbrev: bool,
+    force: bool,
+    zone: Option<DisplayZone>,
+) -> Result<()> {
+    let home = display_home(no_abbrev);
+    let mut screen = BrowseScreen::Labels;
+
+    loop {
+        let store = storage.load_view()?;
+        let zone = zone.unwrap_or_else(|| store.settings.display_zone());
+        let items = browse_menu(&store, &screen, zone);
+        let prompt = match &screen {
+            BrowseScreen::Labels => "Select a label".to_string(),
+            BrowseScreen::Sessions { label } => format!("Sessions in '{}'", label),
+            BrowseScreen::Session { session_id, .. } => format!("Session {}", session_id),
+        };
+        let texts: Vec<String> = items.iter().map(BrowseItem::text).collect();
+
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
+            .items(&texts)
+            .default(0)
+            .interact_opt()
+            .context("Failed to get selection")?;
+
+        // Escape behaves like choosing Back (or Quit on the label list)
+        let item = match selection {
+            Some(index) => &items[index],
+            None => &BrowseItem::Back,
+        };
+
+        match browse_next(&screen, item) {
+            BrowseStep::Show(next) => screen = next,
+            BrowseStep::Resume { label, session_id } => {
+                let session = find_session(&store, &label, &session_id)?;
+                return resume_session(
+                    storage,
+                    &label,
+                    session,
+                    Path::new(&session.path),
+                    &LaunchOptions::default(),
+                    force,
+                );
+            }
+            BrowseStep::Details { label, session_id } => {
+                let session = find_session(&store, &label, &session_id)?;
+                println!("  Session: {}", session.session_id);
+                println!("  Path: {}", display_path(&session.path, home.as_deref()));
+                println!(
+                    "  Created: {}",
+                    format_ts(session.created_at, zone, "%Y-%m-%d %H:%M:%S")
+                );
+                if let Some(ref desc) = session.description {
+                    println!("  Description: {}", desc);
+                }
+                if !session.meta.is_empty() {
+                    println!("  Meta: {}", format_meta(&session.meta));
+                }
+                println!();
+            }
+            BrowseStep::Remove { label, session_id } => {
+                let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+                    .with_prompt(format!("Remove session '{}'?", session_id))
+                    .default(false)
+                    .interact()
+                    .context("Failed to get confirmation")?;
+                if confirmed {
+                    remove(
+                        storage,
+                        &label,
+                        Some(&session_id),
+                        &RemoveOptions::default(),
+                    )?;
+                    screen = BrowseScreen::Sessions { label };
+                }
+            }
+            BrowseStep::Quit => return Ok(()),
+        }
+    }
+}
+
+/// Summary figures for one label.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LabelStats {
+    pub sessions: usize,
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+    pub resumes: u64,
+    pub description_length: usize,
+}
+
+pub fn label_stats(label: &Label) -> LabelStats {
+    LabelStats {
+        sessions: label.sessions.len(),
+        oldest: label.sessions.iter().map(|s| s.created_at).min(),
+        newest: label.sessions.iter().map(|s| s.created_at).max(),
+        resumes: label
+            .sessions
+            .iter()
+            .map(|s| u64::from(s.resume_count))
+            .sum(),
+        description_length: label.description.as_ref().map_or(0, |d| d.chars().count()),
+    }
+}
+
+/// Summary figures for the whole store.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct StoreStats {
+    pub labels: usize,
+    pub empty_labels: usize,
+    pub sessions: usize,
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+}
+
+fn store_stats(store: &Store) -> StoreStats {
+    let per_label: Vec<LabelStats> = store.labels.values().map(label_stats).collect();
+    StoreStats {
+        labels: per_label.len(),
+        empty_labels: per_label.iter().filter(|s| s.sessions == 0).count(),
+        sessions: per_label.iter().map(|s| s.sessions).sum(),
+        oldest: per_label.iter().filter_map(|s| s.oldest).min(),
+        newest: per_label.iter().filter_map(|s| s.newest).max(),
+    }
+}
+
+fn format_stats_time(time: Option<DateTime<Utc>>, zone: DisplayZone) -> String {
+    match time {
+        Some(time) => format_ts(time, zone, "%Y-%m-%d %H:%M:%S"),
+        None => "-".to_string(),
+    }
+}
+
+/// How much one directory is used across the store.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct PathStats {
+    pub sessions: usize,
+    /// How many distinct labels have a session there.
+    pub labels: usize,
+}
+
+fn path_stats(store: &Store) -> BTreeMap<String, PathStats> {
+    let mut stats: BTreeMap<String, PathStats> = BTreeMap::new();
+    let mut seen = HashSet::new();
+    for (name, session) in store.sessions() {
+        let entry = stats.entry(session.path.clone()).or_default();
+        entry.sessions += 1;
+        if seen.insert((session.path.as_str(), name.as_str())) {
+            entry.labels += 1;
+        }
+    }
+    stats
+}
+
+/// The paths in `stats`, busiest first (ties by path).
+fn paths_by_sessions(stats: &BTreeMap<String, PathStats>) -> Vec<(&String, &PathStats)> {
+    let mut paths: Vec<_> = stats.iter().collect();
+    paths.sort_by_key(|(_, s)| Reverse(s.sessions));
+    paths
+}
+
+/// `stats --paths`: how many sessions and labels each directory has.
+fn print_path_stats(store: &Store, json: bool) -> Result<()> {
+    let stats = path_stats(store);
+    let paths = paths_by_sessions(&stats);
+
+    if json {
+        let rows: Vec<_> = paths
+            .iter()
+            .map(|(path, s)| {
+                serde_json::json!({
+                    "path": path,
+                    "sessions": s.sessions,
+                    "labels": s.labels,
+                })
+            })
+            .collect();
+        println!("{}", render_json(&rows, false)?);
+        return Ok(());
+    }
+
+    if paths.is_empty() {
+        println!("No sessions found.");
+        return Ok(());
+    }
+    println!("{}", "Sessions by path".cyan().bold());
+    for (path, s) in paths {
+        println!(
+            "  {} ({} session{}, {} label{})",
+            path,
+            s.sessions,
+            if s.sessions == 1 { "" } else { "s" },
+            s.labels,
+            if s.labels == 1 { "" } else { "s" }
+        );
+    }
+    Ok(())
+}
+
+pub fn stats(
+    storage: &Storage,
+    label: Option<&str>,
+    paths: bool,
+    json: bool,
+    zone: Option<DisplayZone>,
+) -> Result<()> {
+    let store = storage.load_view()?;
+    let zone = zone.unwrap_or_else(|| store.settings.display_zone());
+    if paths {
+        return print_path_stats(&store, json);
+    }
+
+    match label {
+        Some(name) => {
+            let label_entry = store
+                .get_label(name)
+                .with_context(|| format!("Label '{}' not found", name))?;
+            let stats = label_stats(label_entry);
+
+            if json {
+                println!("{}", render_json(&stats, false)?);
+                return Ok(());
+            }
+
+            println!("{}", name.cyan().bold());
+            println!("  Sessions: {}", stats.sessions);
+            println!("  Oldest: {}", format_stats_time(stats.oldest, zone));
+            println!("  Newest: {}", format_stats_time(stats.newest, zone));
+            println!("  Resumes: {}", stats.resumes);
+            println!("  Description length: {}", stats.description_length);
+        }
+        None => {
+            let stats = store_stats(&store);
+
+            if json {
+                println!("{}", render_json(&stats, false)?);
+                return Ok(());
+            }
+
+            println!("{}", "Statistics".cyan().bold());
+            println!("  Labels: {} ({} empty)", stats.labels, stats.empty_labels);
+            println!("  Sessions: {}", stats.sessions);
+            println!("  Oldest: {}", format_stats_time(stats.oldest, zone));
+            println!("  Newest: {}", format_stats_time(stats.newest, zone));
+        }
+    }
+
+    Ok(())
+}
+
+/// What `count --by` groups sessions by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CountBy {
+    /// The label a session is filed under
+    Label,
+    /// The session's working directory
+    Path,
+    /// The month the session was created, as YYYY-MM
+    Month,
+}
+
+/// Count every session in the store under the key `by` picks for it.
+fn count_sessions(store: &Store, by: CountBy) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for (name, session) in store.sessions() {
+        let key = match by {
+            CountBy::Label => name.clone(),
+            CountBy::Path => session.path.clone(),
+            CountBy::Month => session.created_at.format("%Y-%m").to_string(),
+        };
+        *counts.entry(key).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Print `key<TAB>count` rows (or a JSON object) of sessions grouped by `by`.
+pub fn count(storage: &Storage, by: CountBy, json: bool) -> Result<()> {
+    let store = storage.load_view()?;
+    let counts = count_sessions(&store, by);
+
+    if json {
+        println!("{}", render_json(&counts, false)?);
+        return Ok(());
+    }
+
+    for (key, n) in &counts {
+        println!("{}\t{}", key, n);
+    }
+    Ok(())
+}
+
+pub fn alias_list(storage: &Storage) -> Result<()> {
+    let store = storage.load()?;
+
+    if store.settings.aliases.is_empty() {
+        println!("No aliases defined.");
+        return Ok(());
+    }
+
+    let mut aliases: Vec<(&String, &String)> = store.settings.aliases.iter().collect();
+    aliases.sort();
+    for (alias, target) in aliases {
+        let note = if store.labels.contains_key(alias) {
+            " (shadowed by a label of the same name)"
+        } else if !store.labels.contains_key(target) {
+            " (label missing)"
+        } else {
+            ""
+        };
+        println!(
+            "{} {} {}{}",
+            alias.cyan(),
+            OutputMode::current().arrow(),
+            target,
+            note.dimmed()
+        );
+    }
+    Ok(())
+}
+
+pub fn alias_add(storage: &Storage, alias: &str, label: &str, create: bool) -> Result<()> {
+    if alias.is_empty() {
+        bail!("Alias name cannot be empty");
+    }
+
+    let target = storage.update(|store| {
+        // Point at the real label even when `label` is itself an alias
+        let target = store.resolve_label_name(label).to_string();
+        label_for_update(store, &target, create)?;
+        if target == alias {
+            bail!("Alias '{}' would point at itself", alias);
+        }
+        store
+            .settings
+            .aliases
+            .insert(alias.to_string(), target.clone());
+        Ok(target)
+    })?;
+
+    println!(
+        "{} Alias '{}' now points to '{}'",
+        OutputMode::current().ok_mark(),
+        alias.cyan(),
+        target
+    );
+    if storage.load()?.labels.contains_key(alias) {
+        println!(
+            "{} A label named '{}' exists and takes precedence over the alias",
+            "!".yellow(),
+            alias
+        );
+    }
+    Ok(())
+}
+
+pub fn alias_remove(storage: &Storage, alias: &str) -> Result<()> {
+    storage.update(|store| {
+        if store.settings.aliases.remove(alias).is_none() {
+            bail!("Alias '{}' not found", alias);
+        }
+        Ok(())
+    })?;
+
+    println!(
+        "{} Removed alias '{}'",
+        OutputMode::current().ok_mark(),
+        alias
+    );
+    Ok(())
+}
+
+/// What `sweep` removed: per-label session counts (sorted by label) and the
+/// labels dropped because they ended up empty.
+#[derive(Debug, Default, PartialEq)]
+struct SweepReport {
+    removed: Vec<(String, usize)>,
+    pruned: Vec<String>,
+}
+
+fn sweep_sessions(store: &mut Store, cutoff: DateTime<Utc>, prune_empty: bool) -> SweepReport {
+    let mut report = SweepReport::default();
+
+    let names: Vec<String> = sorted_label_names(store).into_iter().cloned().collect();
+    for name in names {
+        let label = store
+            .labels
+            .get_mut(&name)
+            .expect("name comes from the store");
+        let removed = label.remove_sessions_before(cutoff);
+        if removed > 0 {
+            report.removed.push((name.clone(), removed));
+        }
+        if prune_empty && removed > 0 && label.sessions.is_empty() {
+            store.labels.remove(&name);
+            report.pruned.push(name);
+        }
+    }
+
+    report
+}
+
+pub fn sweep(
+    storage: &Storage,
+    older_than: chrono::Duration,
+    prune_empty: bool,
+    zone: Option<DisplayZone>,
+) -> Result<()> {
+    let cutoff = time_ago(older_than, Utc::now())?;
+    let (report, stored_zone) = storage.update(|store| {
+        let report = sweep_sessions(store, cutoff, prune_empty);
+        Ok((report, store.settings.display_zone()))
+    })?;
+
+    if report.removed.is_empty() {
+        let zone = zone.unwrap_or(stored_zone);
+        println!(
+            "No sessions older than {}",
+            format_ts(cutoff, zone, "%Y-%m-%d %H:%M")
+        );
+        return Ok(());
+    }
+
+    let mut total = 0;
+    for (label, removed) in &report.removed {
+        total += removed;
+        println!(
+            "  {}: {} session{}",
+            label.cyan(),
+            removed,
+            if *removed == 1 { "" } else { "s" }
+        );
+    }
+    println!(
+        "{} Swept {} session{} from {} label{}",
+        OutputMode::current().ok_mark(),
+        total,
+        if total == 1 { "" } else { "s" },
+        report.removed.len(),
+        if report.removed.len() == 1 { "" } else { "s" }
+    );
+    if !report.pruned.is_empty() {
+        println!("  Removed empty labels: {}", report.pruned.join(", "));
+    }
+
+    Ok(())
+}
+
+pub fn history(
+    storage: &Storage,
+    label: Option<&str>,
+    count: usize,
+    json: bool,
+    zone: Option<DisplayZone>,
+) -> Result<()> {
+    let entries = history::read(&storage.history_path())?;
+    let store = storage.load()?;
+    let zone = zone.unwrap_or_else(|| store.settings.display_zone());
+    let label = label.map(|label| store.resolve_label_name(label).to_string());
+    let selected = history::select(&entries, label.as_deref(), count);
+
+    if json {
+        println!("{}", render_json(&selected, false)?);
+        return Ok(());
+    }
+
+    if selected.is_empty() {
+        println!("No resumes recorded.");
+        return Ok(());
+    }
+
+    let home = display_home(false);
+    for entry in selected {
+        let status = match entry.exit_code {
+            Some(0) => "ok".green(),
+            Some(code) => format!("exit {}", code).red(),
+            None => "failed".red(),
+        };
+        println!(
+            "{} {} {} {} {}",
+            format_ts(entry.at, zone, "%Y-%m-%d %H:%M").dimmed(),
+            entry.label.cyan(),
+            entry.session_id,
+            display_path(&entry.dir, home.as_deref()).dimmed(),
+            status
+        );
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct RemoveOptions {
+    pub all_sessions: bool,
+    /// Remove the sessions whose description contains this text.
+    pub match_desc: Option<String>,
+    /// Keep only this session and remove the label's others.
+    pub keep: Option<String>,
+    /// Confirm removing several sessions at once (`match_desc`, `keep`).
+    pub yes: bool,
+    /// Remove each of these sessions; ids the label lacks are reported.
+    pub sessions: Vec<String>,
+}
+
+/// Remove every id in `ids` from `label`, returning the ids removed and the
+/// ids it didn't have, each in the order given.
+fn remove_listed(label: &mut Label, ids: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut removed = Vec::new();
+    let mut missing = Vec::new();
+    for id in ids {
+        if label.remove_session(id) {
+            removed.push(id.clone());
+        } else {
+            missing.push(id.clone());
+        }
+    }
+    (removed, missing)
+}
+
+/// `remove --session ID...`: one load and save for all of them. Only an error
+/// when none of the ids were in the label.
+fn remove_sessions(storage: &Storage, label: &str, ids: &[String]) -> Result<()> {
+    let (removed, missing) = storage.update(|store| {
+        let label_entry = label_for_update(store, label, false)?;
+        let (removed, missing) = remove_listed(label_entry, ids);
+        if removed.is_empty() {
+            bail!(
+                "None of the sessions were found in label '{}': {}",
+                label,
+                missing.join(", ")
+            );
+        }
+        Ok((removed, missing))
+    })?;
+
+    println!(
+        "{} Removed {} session{} from label '{}'",
+        OutputMode::current().ok_mark(),
+        removed.len(),
+        if removed.len() == 1 { "" } else { "s" },
+        label
+    );
+    for id in &removed {
+        println!("  {} {}", "-".red(), id);
+    }
+    if !missing.is_empty() {
+        println!("  Not found: {}", missing.join(", ").yellow());
+    }
+    Ok(())
+}
+
+fn remove_all_but(storage: &Storage, label: &str, keep: &str, yes: bool) -> Result<()> {
+    let store = storage.load()?;
+    let label_entry = store
+        .get_label(label)
+        .with_context(|| format!("Label '{}' not found", label))?;
+    if label_entry.find_session(keep).is_none() {
+        bail!("Session '{}' not found in label '{}'", keep, label);
+    }
+    let count = label_entry
+        .sessions
+        .iter()
+        .filter(|s| s.session_id != keep)
+        .count();
+    if count > 1 && !yes {
+        bail!(
+            "This would remove {} sessions from '{}'; pass --yes to confirm",
+            count,
+            label
+        );
+    }
+
+    let removed = storage.update(|store| {
+        label_for_update(store, label, false)?
+            .retain_only(keep)
+            .with_context(|| format!("in label '{}'", label))
+    })?;
+    println!(
+        "{} Kept '{}' and removed {} other session{} from label '{}'",
+        OutputMode::current().ok_mark(),
+        keep,
+        removed,
+        if removed == 1 { "" } else { "s" },
+        label
+    );
+    Ok(())
+}
+
+fn description_contains(session: &Session, needle: &str) -> bool {
+    session
+        .description
+        .as_deref()
+        .is_some_and(|d| d.contains(needle))
+}
+
+fn remove_matching_description(
+    storage: &Storage,
+    label: &str,
+    needle: &str,
+    yes: bool,
+) -> Result<()> {
+    let store = storage.load()?;
+    let label_entry = store
+        .get_label(label)
+        .with_context(|| format!("Label '{}' not found", label))?;
+    let matching: Vec<&Session> = label_entry
+        .sessions
+        .iter()
+        .filter(|s| description_contains(s, needle))
+        .collect();
+
+    if matching.is_empty() {
+        println!(
+            "No sessions in '{}' have a description containing '{}'",
+            label, needle
+        );
+        return Ok(());
+    }
+    for session in &matching {
+        println!(
+            "  {} {} {}",
+            "-".red(),
+            session.session_id,
+            session.description.as_deref().unwrap_or_default().dimmed()
+        );
+    }
+
+    let count = matching.len();
+    let takes_all = count == label_entry.sessions.len();
+    if (count > 1 || takes_all) && !yes {
+        bail!(
+            "This would remove {} session{}{} from '{}'; pass --yes to confirm",
+            count,
+            if count == 1 { "" } else { "s" },
+            if takes_all { " (all of them)" } else { "" },
+            label
+        );
+    }
+
+    let removed = storage.update(|store| {
+        let label_entry = label_for_update(store, label, false)?;
+        Ok(label_entry.remove_sessions_where(|s| description_contains(s, needle)))
+    })?;
+    println!(
+        "{} Removed {} session{} from label '{}'",
+        OutputMode::current().ok_mark(),
+        removed,
+        if removed == 1 { "" } else { "s" },
+        label
+    );
+    Ok(())
+}
+
+/// What a `remove` would take out of a label, worked out without changing it.
+#[derive(Debug, Default)]
+struct RemovalPlan<'a> {
+    /// The whole label goes, not just sessions.
+    label: bool,
+    sessions: Vec<&'a Session>,
+    /// `--session` ids the label doesn't have.
+    missing: Vec<String>,
+}
+
+/// The target set of every `remove` form, failing the same way the real
+/// removal would (missing label or session, no `--session` id found).
+fn removal_plan<'a>(
+    store: &'a Store,
+    label: &str,
+    session_id: Option<&str>,
+    options: &RemoveOptions,
+) -> Result<RemovalPlan<'a>> {
+    let label_entry = store
+        .get_label(label)
+        .with_context(|| format!("Label '{}' not found", label))?;
+    let sessions = &label_entry.sessions;
+    let mut plan = RemovalPlan::default();
+
+    if let Some(ref needle) = options.match_desc {
+        plan.sessions = sessions
+            .iter()
+            .filter(|s| description_contains(s, needle))
+            .collect();
+    } else if let Some(ref keep) = options.keep {
+        if label_entry.find_session(keep).is_none() {
+            bail!("Session '{}' not found in label '{}'", keep, label);
+        }
+        plan.sessions = sessions.iter().filter(|s| s.session_id != *keep).collect();
+    } else if !options.sessions.is_empty() {
+        for id in &options.sessions {
+            let before = plan.sessions.len();
+            plan.sessions
+                .extend(sessions.iter().filter(|s| s.session_id == *id));
+            if plan.sessions.len() == before {
+                plan.missing.push(id.clone());
+            }
+        }
+        if plan.sessions.is_empty() {
+            bail!(
+                "None of the sessions were found in label '{}': {}",
+                label,
+                plan.missing.join(", ")
+            );
+        }
+    } else if options.all_sessions {
+        plan.sessions = sessions.iter().collect();
+    } else if let Some(sid) = session_id {
+        plan.sessions = sessions.iter().filter(|s| s.session_id == sid).collect();
+        if plan.sessions.is_empty() {
+            bail!("Session '{}' not found in label '{}'", sid, label);
+        }
+    } else {
+        plan.label = true;
+        plan.sessions = sessions.iter().collect();
+    }
+    Ok(plan)
+}
+
+fn print_removal_plan(label: &str, plan: &RemovalPlan) {
+    let count = plan.sessions.len();
+    let sessions = format!("{} session{}", count, if count == 1 { "" } else { "s" });
+    if plan.label {
+        println!("Would remove label '{}' and its {}", label, sessions);
+    } else {
+        println!("Would remove {} from label '{}'", sessions, label);
+    }
+    for session in &plan.sessions {
+        println!(
+            "  {} {} {}",
+            "-".red(),
+            session.session_id,
+            session.description.as_deref().unwrap_or_default().dimmed()
+        );
+    }
+    if !plan.missing.is_empty() {
+        println!("  Not found: {}", plan.missing.join(", ").yellow());
+    }
+}
+
+pub fn remove(
+    storage: &Storage,
+    label: &str,
+    session_id: Option<&str>,
+    options: &RemoveOptions,
+) -> Result<()> {
+    // A dry run reports the target set and stops before anything is saved
+    // (or any --yes confirmation is asked for).
+    if storage.is_dry_run() {
+        let store = storage.load()?;
+        let plan = removal_plan(&store, label, session_id, options)?;
+        print_removal_plan(label, &plan);
+        return Ok(());
+    }
+
+    if let Some(ref needle) = options.match_desc {
+        return remove_matching_description(storage, label, needle, options.yes);
+    }
+    if let Some(ref keep) = options.keep {
+        return remove_all_but(storage, label, keep, options.yes);
+    }
+    if !options.sessions.is_empty() {
+        return remove_sessions(storage, label, &options.sessions);
+    }
+    if options.all_sessions {
+        let removed = storage.update(|store| {
+            let label_entry = label_for_update(store, label, false)?;
+            Ok(label_entry.clear_sessions())
+        })?;
+        println!(
+            "{} Removed {} session{} from label '{}'",
+            OutputMode::current().ok_mark(),
+            removed,
+            if removed == 1 { "" } else { "s" },
+            label
+        );
+        return Ok(());
+    }
+
+    match session_id {
+        Some(sid) => {
+            storage.update(|store| {
+                let label_entry = label_for_update(store, label, false)?;
+                if !label_entry.remove_session(sid) {
+                    bail!("Session '{}' not found in label '{}'", sid, label);
+                }
+                Ok(())
+            })?;
+            println!(
+                "{} Removed session '{}' from label '{}'",
+                OutputMode::current().ok_mark(),
+                sid,
+                label
+            );
+        }
+        None => {
+            storage.update(|store| {
+                if !store.remove_label(label) {
+                    bail!("Label '{}' not found", label);
+                }
+                Ok(())
+            })?;
+            println!(
+                "{} Removed label '{}'",
+                OutputMode::current().ok_mark(),
+                label
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// The label `name` for modification. A missing label is an error unless
+/// `create` is set, in which case an empty one is added.
+fn label_for_update<'a>(store: &'a mut Store, name: &str, create: bool) -> Result<&'a mut Label> {
+    if create {
+        return Ok(store.get_or_create_label(name));
+    }
+    store
+        .get_label_mut(name)
+        .with_context(|| format!("Label '{}' not found", name))
+}
+
+/// Set the color a label is shown in, or clear it when `color` is `None`.
+pub fn color(storage: &Storage, label: &str, color: Option<&str>) -> Result<()> {
+    let color = color.map(normalize_color_name).transpose()?;
+
+    storage.update(|store| {
+        label_for_update(store, label, false)?.color = color.clone();
+        Ok(())
+    })?;
+
+    match color {
+        Some(ref name) => println!(
+            "{} Set color of '{}' to {}",
+            OutputMode::current().ok_mark(),
+            style_label_name(label, Some(name)),
+            name
+        ),
+        None => println!(
+            "{} Cleared color of '{}'",
+            OutputMode::current().ok_mark(),
+            label
+        ),
+    }
+    Ok(())
+}
+
+/// Parse a label name → description map: TOML when `toml` is set, JSON
+/// otherwise.
+fn parse_description_map(content: &str, toml: bool) -> Result<BTreeMap<String, String>> {
+    if toml {
+        toml::from_str(content)
+            .context("Invalid description map (expected TOML `label = \"description\"` pairs)")
+    } else {
+        serde_json::from_str(content)
+            .context("Invalid description map (expected a JSON object of label: description)")
+    }
+}
+
+/// Set each mapped label's description. Labels that don't exist fail the
+/// whole map, before anything is changed, unless `skip_unknown` is set; the
+/// skipped names are returned.
+fn apply_description_map(
+    store: &mut Store,
+    map: &BTreeMap<String, String>,
+    skip_unknown: bool,
+) -> Result<Vec<String>> {
+    let unknown: Vec<String> = map
+        .keys()
+        .filter(|name| store.get_label(name).is_none())
+        .cloned()
+        .collect();
+    if !unknown.is_empty() && !skip_unknown {
+        bail!(
+            "Unknown label{} in map: {} (use --skip-unknown to ignore)",
+            if unknown.len() == 1 { "" } else { "s" },
+            unknown.join(", ")
+        );
+    }
+
+    let now = Utc::now();
+    for (name, description) in map {
+        if let Some(label) = store.get_label_mut(name) {
+            label.set_description(Some(description.clone()), now);
+        }
+    }
+    Ok(unknown)
+}
+
+/// `describe --from-map`: set many labels' descriptions from a JSON or TOML
+/// file mapping label name to description.
+pub fn describe_from_map(storage: &Storage, file: &Path, skip_unknown: bool) -> Result<()> {
+    let content = fs::read_to_string(file)
+        .with_context(|| format!("Could not read description map: {:?}", file))?;
+    let is_toml = file
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+    let map = parse_description_map(&content, is_toml)?;
+
+    let skipped = storage.update(|store| apply_description_map(store, &map, skip_unknown))?;
+
+    for name in &skipped {
+        println!("  {} {} (no such label, skipped)", "-".dimmed(), name);
+    }
+    let applied = map.len() - skipped.len();
+    println!(
+        "{} Updated {} description{}",
+        OutputMode::current().ok_mark(),
+        applied,
+        if applied == 1 { "" } else { "s" }
+    );
+    Ok(())
+}
+
+/// Give a session in `label` a new id, keeping its path, description and
+/// timestamps.
+pub fn rename_session(storage: &Storage, label: &str, old_id: &str, new_id: &str) -> Result<()> {
+    storage.update(|store| {
+        label_for_update(store, label, false)?
+            .rename_session(old_id, new_id)
+            .with_context(|| format!("Could not rename session in label '{}'", label))
+    })?;
+
+    println!(
+        "{} Renamed session '{}' to '{}' in '{}'",
+        OutputMode::current().ok_mark(),
+        old_id,
+        new_id,
+        label.cyan()
+    );
+    Ok(())
+}
+
+/// Make a session (or with `all` every session in the label) current by
+/// moving its creation time to now. See `Label::touch_all` for how `all`
+/// keeps the sessions' order.
+pub fn touch(storage: &Storage, label: &str, session_id: Option<&str>, all: bool) -> Result<()> {
+    let now = Utc::now();
+    let touched = storage.update(|store| {
+        let label_entry = label_for_update(store, label, false)?;
+        match session_id {
+            Some(sid) if !all => {
+                if !label_entry.touch_session(sid, now) {
+                    bail!("Session '{}' not found in label '{}'", sid, label);
+                }
+                Ok(1)
+            }
+            _ => Ok(label_entry.touch_all(now)),
+        }
+    })?;
+
+    match session_id {
+        Some(sid) if !all => println!(
+            "{} Touched session '{}' in '{}'",
+            OutputMode::current().ok_mark(),
+            sid,
+            label.cyan()
+        ),
+        _ => println!(
+            "{} Touched {} session{} in '{}'",
+            OutputMode::current().ok_mark(),
+            touched,
+            if touched == 1 { "" } else { "s" },
+            label.cyan()
+        ),
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct DescribeOptions {
+    /// Edit the current description in `$EDITOR` instead of passing it.
+    pub edit: bool,
+    /// Describe this session of the label rather than the label itself.
+    pub session: Option<String>,
+    /// Create the label if it doesn't exist instead of failing.
+    pub create: bool,
+    /// Print the current description verbatim instead of changing it.
+    pub show: bool,
+    /// Treat the description as a template and expand its `{variables}`.
+    pub template: bool,
+    /// Print the label's earlier descriptions instead of changing it.
+    pub history: bool,
+    /// Zone for `history` timestamps, overriding the `time_zone` setting.
+    pub zone: Option<DisplayZone>,
+}
+
+/// `describe --history`: the label's recorded descriptions, oldest first.
+fn print_description_history(label_entry: &Label, label: &str, zone: DisplayZone) {
+    if label_entry.description_history.is_empty() {
+        println!("No description history for '{}'", label);
+        return;
+    }
+    for (at, text) in &label_entry.description_history {
+        let text = if text.is_empty() {
+            "(cleared)".dimmed().to_string()
+        } else {
+            text.clone()
+        };
+        println!(
+            "{}  {}",
+            format_ts(*at, zone, "%Y-%m-%d %H:%M").dimmed(),
+            text
+        );
+    }
+}
+
+/// The variables `describe --template` knows.
+const TEMPLATE_VARS: &[&str] = &["label", "date", "user", "count"];
+
+/// Expand a `describe --template` description for `label`, which holds
+/// `count` sessions. `user` comes from the environment and may be missing.
+fn expand_description(
+    template: &str,
+    label: &str,
+    count: usize,
+    today: &str,
+    user: Option<&str>,
+) -> Result<String> {
+    expand_template(template, |name| match name {
+        "label" => Ok(label.to_string()),
+        "date" => Ok(today.to_string()),
+        "count" => Ok(count.to_string()),
+        "user" => user
+            .map(str::to_string)
+            .context("{user} needs $USER (or $USERNAME) to be set"),
+        _ => bail!(
+            "Unknown template variable '{{{}}}' (known: {})",
+            name,
+            TEMPLATE_VARS
+                .iter()
+                .map(|v| format!("{{{}}}", v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    })
+}
+
+fn env_user() -> Option<String> {
+    env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .ok()
+        .filter(|u| !u.is_empty())
+}
+
+/// The description `describe --show` prints: the session's when `session_id`
+/// is given, otherwise the label's. A missing label or session is an error;
+/// an unset description is `None`.
+fn current_description<'a>(
+    store: &'a Store,
+    label: &str,
+    session_id: Option<&str>,
+) -> Result<Option<&'a str>> {
+    match session_id {
+        Some(sid) => Ok(find_session(store, label, sid)?.description.as_deref()),
+        None => Ok(store
+            .get_label(label)
+            .with_context(|| format!("Label '{}' not found", label))?
+            .description
+            .as_deref()),
+    }
+}
+
+pub fn describe(
+    storage: &Storage,
+    label: &str,
+    description: Option<String>,
+    options: &DescribeOptions,
+) -> Result<()> {
+    describe_with_editor(storage, label, description, options, editor::run_editor)
+}
+
+fn describe_with_editor<F>(
+    storage: &Storage,
+    label: &str,
+    description: Option<String>,
+    options: &DescribeOptions,
+    run_editor: F,
+) -> Result<()>
+where
+    F: FnOnce(&Path) -> Result<()>,
+{
+    let session_id = options.session.as_deref();
+
+    if options.show {
+        // Nothing at all (and success) when there is no description, so
+        // `$(claude-sessions describe X --show)` is simply empty
+        let store = storage.load()?;
+        if let Some(desc) = current_description(&store, label, session_id)? {
+            println!("{}", desc);
+        }
+        return Ok(());
+    }
+
+    if options.history {
+        let store = storage.load()?;
+        let label_entry = store
+            .get_label(label)
+            .with_context(|| format!("Label '{}' not found", label))?;
+        let zone = options
+            .zone
+            .unwrap_or_else(|| store.settings.display_zone());
+        print_description_history(label_entry, label, zone);
+        return Ok(());
+    }
+
+    let description = if options.edit {
+        let store = storage.load()?;
+        let current = match (session_id, store.get_label(label)) {
+            (Some(sid), _) => find_session(&store, label, sid)?.description.as_deref(),
+            (None, Some(label_entry)) => label_entry.description.as_deref(),
+            (None, None) if options.create => None,
+            (None, None) => bail!("Label '{}' not found", label),
+        };
+        editor::edit_text(current.unwrap_or(""), run_editor)?
+    } else {
+        description
+    };
+
+    let user = env_user();
+    let description = storage.update(|store| {
+        let today = format_ts(Utc::now(), store.settings.display_zone(), "%Y-%m-%d");
+        let name = store.resolve_label_name(label).to_string();
+        let label_entry = label_for_update(store, label, options.create)?;
+        let description = match description {
+            Some(ref text) if options.template => Some(expand_description(
+                text,
+                &name,
+                label_entry.sessions.len(),
+                &today,
+                user.as_deref(),
+            )?),
+            _ => description.clone(),
+        };
+        match session_id {
+            Some(sid) => {
+                let session = label_entry
+                    .find_session_mut(sid)
+                    .with_context(|| format!("Session '{}' not found in label '{}'", sid, label))?;
+                session.description = description.clone();
+            }
+            None => {
+                label_entry.set_description(description.clone(), Utc::now());
+            }
+        }
+        Ok(description)
+    })?;
+
+    let target = match session_id {
+        Some(sid) => format!("session '{}' in '{}'", sid, label.cyan()),
+        None => format!("'{}'", label.cyan()),
+    };
+    match description {
+        Some(desc) => println!(
+            "{} Updated description for {}: {}",
+            OutputMode::current().ok_mark(),
+            target,
+            desc
+        ),
+        None => println!(
+            "{} Cleared description for {}",
+            OutputMode::current().ok_mark(),
+            target
+        ),
+    }
+
+    Ok(())
+}
+
+pub fn meta(
+    storage: &Storage,
+    label: &str,
+    session_id: &str,
+    set: Vec<(String, String)>,
+    unset: Vec<String>,
+) -> Result<()> {
+    let mut store = storage.load()?;
+
+    let label_entry = label_for_update(&mut store, label, false)?;
+    let session = label_entry
+        .find_session_mut(session_id)
+        .with_context(|| format!("Session '{}' not found in label '{}'", session_id, label))?;
+
+    if set.is_empty() && unset.is_empty() {
+        if session.meta.is_empty() {
+            println!("No metadata for session '{}'", session_id);
+        } else {
+            let mut pairs: Vec<(&String, &String)> = session.meta.iter().collect();
+            pairs.sort();
+            for (key, value) in pairs {
+                println!("{}={}", key, value);
+            }
+        }
+        return Ok(());
+    }
+
+    for key in &unset {
+        session.meta.remove(key);
+    }
+    for (key, value) in set {
+        session.meta.insert(key, value);
+    }
+    let summary = format_meta(&session.meta);
+
+    storage.save(&store)?;
+
+    println!(
+        "{} Updated metadata for session '{}'",
+        OutputMode::current().ok_mark(),
+        session_id
+    );
+    if !summary.is_empty() {
+        println!("  Meta: {}", summary);
+    }
+
+    Ok(())
+}
+
+pub fn watch(storage: &Storage, label: &str, interval: u64) -> Result<()> {
+    let cwd = env::current_dir().context("Could not get current directory")?;
+    let dir = claude::project_dir(&cwd)?;
+
+    println!(
+        "{} Watching {} for new sessions (Ctrl-C to stop)",
+        OutputMode::current().arrow(),
+        dir.display()
+    );
+
+    let stop = StopFlag::on_ctrl_c()?;
+    let mut known = claude::session_ids(&dir)?;
+    let mut added = 0;
+    while !sleep_unless_stopped(Duration::from_secs(interval), &stop) {
+        let current = claude::session_ids(&dir)?;
+        let new_ids = claude::new_session_ids(&known, &current);
+        added += for_each_until_stopped(new_ids, &stop, |session_id| {
+            add(storage, label, &session_id, None, &AddOptions::default())
+        })?;
+        known = current;
+    }
+
+    println!(
+        "{} Stopped watching; added {} session{} to '{}'",
+        OutputMode::current().ok_mark(),
+        added,
+        if added == 1 { "" } else { "s" },
+        label
+    );
+    Ok(())
+}
+
+/// Split the session ids found on disk into those `label` doesn't record yet
+/// (to add, sorted) and the number already recorded (skipped).
+fn sync_plan(found: &BTreeSet<String>, label: Option<&Label>) -> (Vec<String>, usize) {
+    let recorded: HashSet<&str> = label
+        .map(|l| l.sessions.iter().map(|s| s.session_id.as_str()).collect())
+        .unwrap_or_default();
+    let (skipped, to_add): (Vec<&String>, Vec<&String>) =
+        found.iter().partition(|id| recorded.contains(id.as_str()));
+    (to_add.into_iter().cloned().collect(), skipped.len())
+}
+
+/// Add every Claude session recorded for the current directory that `label`
+/// doesn't have yet.
+pub fn sync(storage: &Storage, label: &str) -> Result<()> {
+    let cwd = env::current_dir().context("Could not get current directory")?;
+    sync_from_dir(storage, label, &claude::project_dir(&cwd)?, &cwd)
+}
+
+fn sync_from_dir(storage: &Storage, label: &str, project_dir: &Path, cwd: &Path) -> Result<()> {
+    let found = claude::session_ids(project_dir)?;
+    if found.is_empty() {
+        // Nothing to record, so don't leave an empty label behind either
+        bail!(
+            "No Claude sessions found in {}; nothing to sync",
+            project_dir.display()
+        );
+    }
+    let path = cwd.to_string_lossy().to_string();
+
+    let (added, skipped) = storage.update(|store| {
+        let (to_add, skipped) = sync_plan(&found, store.get_label(label));
+        let label_entry = store.get_or_create_label(label);
+        for session_id in &to_add {
+            // The transcript's modification time is the best guess at when it was used
+            let created_at = fs::metadata(project_dir.join(format!("{}.jsonl", session_id)))
+                .and_then(|m| m.modified())
+                .map(DateTime::<Utc>::from)
+                .unwrap_or_else(|_| Utc::now());
+            label_entry.add_session(Session {
+                session_id: session_id.clone(),
+                path: path.clone(),
+                created_at,
+                ..Default::default()
+            });
+        }
+        Ok((to_add, skipped))
+    })?;
+
+    for session_id in &added {
+        println!("  {} {}", "+".green(), session_id);
+    }
+    println!(
+        "{} Synced '{}' from {}: {} added, {} already recorded",
+        OutputMode::current().ok_mark(),
+        label.cyan(),
+        project_dir.display(),
+        added.len(),
+        skipped
+    );
+    Ok(())
+}
+
+/// Write the data file path to `out` if this run saved it
+/// (`--print-data-path`), e.g. so a wrapper script can commit it.
+pub fn print_data_path<W: Write>(out: &mut W, storage: &Storage) -> Result<()> {
+    if storage.was_saved() {
+        writeln!(out, "{}", storage.path().display())?;
+    }
+    Ok(())
+}
+
+pub fn version(verbose: bool, json: bool) -> Result<()> {
+    let info = build_info();
+
+    if json {
+        println!("{}", render_json(&info, false)?);
+        return Ok(());
+    }
+
+    println!("claude-sessions {}", info.version);
+    if verbose {
+        println!("  commit: {}", info.git_commit.unwrap_or("unknown"));
+        println!("  rustc:  {}", info.rustc.unwrap_or("unknown"));
+        println!("  target: {}", info.target.unwrap_or("unknown"));
+    }
+    Ok(())
+}
+
+/// Size and last modification of a file on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileInfo {
+    pub size: u64,
+    /// `None` where the platform doesn't record it.
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Stat `path`, or `None` when it doesn't exist (yet).
+fn file_info(path: &Path) -> Result<Option<FileInfo>> {
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(Some(FileInfo {
+            size: metadata.len(),
+            modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+        })),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("Could not read {}", path.display())),
+    }
+}
+
+pub fn config(storage: &Storage, json: bool) -> Result<()> {
+    let store = storage.load()?;
+    let info = file_info(storage.path())?;
+    let labels_dir = (store.settings.layout == Layout::Flat).then(|| storage.labels_dir());
+
+    if json {
+        let settings: BTreeMap<&str, String> = SETTING_KEYS
+            .iter()
+            .map(|key| (key.name, (key.get)(&store.settings)))
+            .collect();
+        let out = serde_json::json!({
+            "data_file": storage.path(),
+            "labels_dir": labels_dir,
+            "size": info.map(|i| i.size),
+            "modified": info.and_then(|i| i.modified).map(|at| at.to_rfc3339()),
+            "settings": settings,
+        });
+        println!("{}", render_json(&out, false)?);
+        return Ok(());
+    }
+
+    println!("{}", "Configuration".cyan().bold());
+    println!("  Data file: {}", storage.path().display());
+    match info {
+        Some(info) => {
+            println!("  Size: {} bytes", info.size);
+            if let Some(modified) = info.modified {
+                println!(
+                    "  Modified: {}",
+                    format_ts(modified, store.settings.display_zone(), "%Y-%m-%d %H:%M:%S")
+                );
+            }
+        }
+        None => println!("  Size: {}", "not created".dimmed()),
+    }
+    if let Some(dir) = labels_dir {
+        println!("  Labels dir: {}", dir.display());
+    }
+    println!();
+    println!("{}", "Settings".cyan().bold());
+    for key in SETTING_KEYS {
+        println!(
+            "  {} = {}  {}",
+            key.name,
+            (key.get)(&store.settings),
+            key.description.dimmed()
+        );
+    }
+    Ok(())
+}
+
+pub fn config_profiles(storage: &Storage) -> Result<()> {
+    let Some(dir) = storage.path().parent() else {
+        return Ok(());
+    };
+    let current = storage.path().file_name().and_then(|n| n.to_str());
+
+    for profile in list_profiles(dir)? {
+        if profile_file_name(&profile).ok().as_deref() == current {
+            println!("{} {}", "*".green(), profile.cyan());
+        } else {
+            println!("  {}", profile);
+        }
+    }
+    Ok(())
+}
+
+pub fn config_get(storage: &Storage, key: &str) -> Result<()> {
+    let key = settings::find_key(key)?;
+    let store = storage.load()?;
+
+    println!("{}", (key.get)(&store.settings));
+    Ok(())
+}
+
+pub fn config_set(storage: &Storage, key: &str, value: &str) -> Result<()> {
+    let key = settings::find_key(key)?;
+    let mut store = storage.load()?;
+
+    (key.set)(&mut store.settings, value)?;
+    storage.save(&store)?;
+
+    println!(
+        "{} Set {} = {}",
+        OutputMode::current().ok_mark(),
+        key.name.cyan(),
+        (key.get)(&store.settings)
+    );
+    Ok(())
+}
+
+/// Write just the settings (no labels) to `file` as JSON.
+pub fn config_export(storage: &Storage, file: &Path) -> Result<()> {
+    let store = storage.load()?;
+    let json =
+        serde_json::to_string_pretty(&store.settings).context("Could not serialize settings")?;
+    fs::write(file, json + "\n").with_context(|| format!("Could not write {}", file.display()))?;
+
+    println!(
+        "{} Exported settings to {}",
+        OutputMode::current().ok_mark(),
+        file.display()
+    );
+    Ok(())
+}
+
+/// Parse settings written by `config export`, checking values that would
+/// otherwise be silently ignored.
+fn parse_settings(content: &str) -> Result<settings::Settings> {
+    let settings: settings::Settings =
+        serde_json::from_str(content).context("Invalid settings file")?;
+    if let Some(ref zone) = settings.time_zone {
+        DisplayZone::parse(zone).context("Invalid settings file")?;
+    }
+    Ok(settings)
+}
+
+/// Replace the settings with those in `file`, leaving labels as they are.
+pub fn config_import(storage: &Storage, file: &Path) -> Result<()> {
+    let content =
+        fs::read_to_string(file).with_context(|| format!("Could not read {}", file.display()))?;
+    let settings = parse_settings(&content)?;
+
+    storage.update(|store| {
+        store.settings = settings.clone();
+        Ok(())
+    })?;
+
+    println!(
+        "{} Imported settings from {}",
+        OutputMode::current().ok_mark(),
+        file.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use output::StoreOut;
+    use std::ffi::OsStr;
 
     fn temp_path(name: &str) -> PathBuf {
         env::temp_dir().join(format!(
@@ -272,288 +3742,4706 @@ mod tests {
         ))
     }
 
-    fn cleanup(path: &PathBuf) {
-        let _ = fs::remove_file(path);
+    fn cleanup(path: &PathBuf) {
+        let _ = fs::remove_file(path);
+    }
+
+    fn create_test_storage(name: &str) -> (Storage, PathBuf) {
+        let path = temp_path(name);
+        cleanup(&path);
+        let storage = Storage::with_path(path.clone());
+        (storage, path)
+    }
+
+    fn create_test_session_with_time(
+        id: &str,
+        path: &str,
+        desc: Option<&str>,
+        year: i32,
+        month: u32,
+        day: u32,
+    ) -> Session {
+        Session {
+            session_id: id.to_string(),
+            path: path.to_string(),
+            description: desc.map(|s| s.to_string()),
+            created_at: Utc.with_ymd_and_hms(year, month, day, 12, 0, 0).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    // ==================== Session Id Tests ====================
+
+    #[test]
+    fn test_looks_like_session_id_accepts_uuids() {
+        assert!(looks_like_session_id(
+            "0f8fad5b-d9cb-469f-a165-70867728950e"
+        ));
+        assert!(looks_like_session_id(
+            "7C9E6679-7425-40DE-944B-E07FC1F90AE7"
+        ));
+    }
+
+    #[test]
+    fn test_looks_like_session_id_rejects_short_strings() {
+        assert!(!looks_like_session_id(""));
+        assert!(!looks_like_session_id("session-123"));
+        assert!(!looks_like_session_id("0f8fad5b"));
+        assert!(!looks_like_session_id("0f8fad5b-d9cb-469f-a165"));
+    }
+
+    #[test]
+    fn test_looks_like_session_id_rejects_malformed() {
+        // Right shape, wrong characters or group lengths
+        assert!(!looks_like_session_id(
+            "0f8fad5b-d9cb-469f-a165-70867728950g"
+        ));
+        assert!(!looks_like_session_id(
+            "0f8fad5bd-9cb-469f-a165-70867728950e"
+        ));
+        assert!(!looks_like_session_id(
+            "0f8fad5b-d9cb-469f-a165-70867728950e-"
+        ));
+        assert!(!looks_like_session_id("/home/user/project"));
+        assert!(!looks_like_session_id("セッション-123-émoji-🎉"));
+    }
+
+    #[test]
+    fn test_check_session_id_is_opt_in() {
+        assert!(check_session_id("my-session", false, false).is_ok());
+        assert!(check_session_id("my-session", true, false).is_ok());
+        let err = check_session_id("my-session", true, true).unwrap_err();
+        assert!(err.to_string().contains("expected a UUID"), "{}", err);
+        assert!(check_session_id("0f8fad5b-d9cb-469f-a165-70867728950e", false, true).is_ok());
+    }
+
+    #[test]
+    fn test_add_strict_id_rejects_non_uuid() {
+        let (storage, path) = create_test_storage("add-strict-id");
+
+        let options = AddOptions {
+            strict_id: true,
+            ..Default::default()
+        };
+        let result = add(&storage, "my-label", "/home/user/project", None, &options);
+
+        assert!(result.is_err());
+        assert!(storage.load().unwrap().labels.is_empty());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_strict_id_accepts_uuid() {
+        let (storage, path) = create_test_storage("add-strict-id-ok");
+
+        let options = AddOptions {
+            strict_id: true,
+            ..Default::default()
+        };
+        add(
+            &storage,
+            "my-label",
+            "0f8fad5b-d9cb-469f-a165-70867728950e",
+            None,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            storage
+                .load()
+                .unwrap()
+                .get_label("my-label")
+                .unwrap()
+                .sessions
+                .len(),
+            1
+        );
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_without_strict_id_keeps_non_uuid() {
+        let (storage, path) = create_test_storage("add-permissive-id");
+
+        add(
+            &storage,
+            "my-label",
+            "セッション-123-émoji-🎉",
+            None,
+            &AddOptions::default(),
+        )
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(
+            store.get_label("my-label").unwrap().sessions[0].session_id,
+            "セッション-123-émoji-🎉"
+        );
+        cleanup(&path);
+    }
+
+    // ==================== Add Command Tests ====================
+
+    #[test]
+    fn test_add_at_backdates_session() {
+        let (storage, path) = create_test_storage("add-at");
+        let at = Utc.with_ymd_and_hms(2023, 5, 1, 9, 30, 0).unwrap();
+
+        let options = AddOptions {
+            at: Some(at),
+            ..Default::default()
+        };
+        add(&storage, "my-label", "old-session", None, &options).unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(
+            store.get_label("my-label").unwrap().sessions[0].created_at,
+            at
+        );
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_without_at_uses_now() {
+        let (storage, path) = create_test_storage("add-at-now");
+
+        let before = Utc::now();
+        add(
+            &storage,
+            "my-label",
+            "new-session",
+            None,
+            &AddOptions::default(),
+        )
+        .unwrap();
+        let after = Utc::now();
+
+        let store = storage.load().unwrap();
+        let created_at = store.get_label("my-label").unwrap().sessions[0].created_at;
+        assert!(before <= created_at && created_at <= after);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_at_keeps_latest_by_created_at() {
+        let (storage, path) = create_test_storage("add-at-order");
+
+        add(
+            &storage,
+            "my-label",
+            "current",
+            None,
+            &AddOptions::default(),
+        )
+        .unwrap();
+        let options = AddOptions {
+            at: Some(Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap()),
+            ..Default::default()
+        };
+        add(&storage, "my-label", "backfilled", None, &options).unwrap();
+
+        let store = storage.load().unwrap();
+        let latest = store
+            .get_label("my-label")
+            .unwrap()
+            .latest_session()
+            .unwrap();
+        assert_eq!(latest.session_id, "current");
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_creates_new_label() {
+        let (storage, path) = create_test_storage("add-new-label");
+
+        let result = add(
+            &storage,
+            "my-label",
+            "session-123",
+            None,
+            &AddOptions::default(),
+        );
+        assert!(result.is_ok());
+
+        let store = storage.load().unwrap();
+        assert!(store.labels.contains_key("my-label"));
+
+        let label = store.get_label("my-label").unwrap();
+        assert_eq!(label.sessions.len(), 1);
+        assert_eq!(label.sessions[0].session_id, "session-123");
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_appends_to_existing_label() {
+        let (storage, path) = create_test_storage("add-append");
+
+        // Add first session
+        add(
+            &storage,
+            "my-label",
+            "session-1",
+            None,
+            &AddOptions::default(),
+        )
+        .unwrap();
+
+        // Add second session to same label
+        add(
+            &storage,
+            "my-label",
+            "session-2",
+            Some("Second session".to_string()),
+            &AddOptions::default(),
+        )
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        let label = store.get_label("my-label").unwrap();
+        assert_eq!(label.sessions.len(), 2);
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_with_description() {
+        let (storage, path) = create_test_storage("add-desc");
+
+        add(
+            &storage,
+            "my-label",
+            "session-123",
+            Some("Test description".to_string()),
+            &AddOptions::default(),
+        )
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        let label = store.get_label("my-label").unwrap();
+        assert_eq!(
+            label.sessions[0].description,
+            Some("Test description".to_string())
+        );
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_saves_current_directory() {
+        let (storage, path) = create_test_storage("add-path");
+
+        add(
+            &storage,
+            "my-label",
+            "session-123",
+            None,
+            &AddOptions::default(),
+        )
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        let label = store.get_label("my-label").unwrap();
+
+        // The path should be the current working directory
+        let current_dir = env::current_dir().unwrap().to_string_lossy().to_string();
+        assert_eq!(label.sessions[0].path, current_dir);
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_dry_run_leaves_store_unchanged() {
+        let (mut storage, path) = create_test_storage("add-dry-run");
+
+        add(&storage, "existing", "sess-1", None, &AddOptions::default()).unwrap();
+        storage.set_dry_run(true);
+
+        let result = add(
+            &storage,
+            "new-label",
+            "sess-2",
+            None,
+            &AddOptions::default(),
+        );
+        assert!(result.is_ok());
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.labels.len(), 1);
+        assert!(!store.labels.contains_key("new-label"));
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_resume_launches_new_session() {
+        let (storage, path) = create_test_storage("add-resume");
+
+        add(
+            &storage,
+            "my-label",
+            "older-session",
+            None,
+            &AddOptions::default(),
+        )
+        .unwrap();
+
+        let mut launched = None;
+        let options = AddOptions {
+            resume: true,
+            ..Default::default()
+        };
+        add_with_launcher(&storage, "my-label", "new-session", None, &options, |s| {
+            launched = Some(s.session_id.clone());
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(launched, Some("new-session".to_string()));
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_resume_refuses_running_session() {
+        let (storage, path) = create_test_storage("add-resume-locked");
+        let session_id = format!("add-resume-locked-{}", std::process::id());
+        let _running =
+            SessionLock::acquire(&session_lock::runtime_dir(), &session_id, false).unwrap();
+
+        let options = AddOptions {
+            resume: true,
+            ..Default::default()
+        };
+        let err = add(&storage, "my-label", &session_id, None, &options).unwrap_err();
+
+        assert!(err.to_string().contains("is already running"));
+        let store = storage.load().unwrap();
+        let session = store
+            .get_label("my-label")
+            .unwrap()
+            .find_session(&session_id)
+            .unwrap();
+        assert_eq!(session.resume_count, 0);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_without_resume_does_not_launch() {
+        let (storage, path) = create_test_storage("add-no-resume");
+
+        let mut launched = false;
+        add_with_launcher(
+            &storage,
+            "my-label",
+            "sess-1",
+            None,
+            &AddOptions::default(),
+            |_| {
+                launched = true;
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert!(!launched);
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_claude_command_resumes_session_in_its_path() {
+        let session = create_test_session_with_time("sess-123", "/tmp", None, 2024, 1, 1);
+
+        let command = claude_command(
+            &session,
+            Path::new(&session.path),
+            &LaunchOptions::default(),
+        );
+
+        assert_eq!(command.get_program(), "claude");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, vec!["--resume", "sess-123"]);
+        assert_eq!(command.get_current_dir(), Some(Path::new("/tmp")));
+    }
+
+    #[test]
+    fn test_session_command_defaults_to_claude() {
+        let session = create_test_session_with_time("sess-123", "/tmp", None, 2024, 1, 1);
+
+        let command = session_command(
+            &session,
+            Path::new(&session.path),
+            &LaunchOptions::default(),
+        );
+
+        assert_eq!(command.get_program(), "claude");
+    }
+
+    #[test]
+    fn test_session_command_shell_runs_shell_in_path() {
+        let session = create_test_session_with_time("sess-123", "/tmp", None, 2024, 1, 1);
+        let launch = LaunchOptions {
+            shell: true,
+            ..Default::default()
+        };
+
+        let command = session_command(&session, Path::new(&session.path), &launch);
+
+        assert_eq!(command.get_program(), shell_program(env::var_os("SHELL")));
+        assert_eq!(command.get_args().count(), 0);
+        assert_eq!(command.get_current_dir(), Some(Path::new("/tmp")));
+    }
+
+    #[test]
+    fn test_shell_program_uses_shell_var() {
+        assert_eq!(
+            shell_program(Some(OsString::from("/usr/bin/fish"))),
+            "/usr/bin/fish"
+        );
+    }
+
+    #[test]
+    fn test_shell_program_falls_back_to_sh() {
+        assert_eq!(shell_program(None), "/bin/sh");
+        assert_eq!(shell_program(Some(OsString::new())), "/bin/sh");
+    }
+
+    #[test]
+    fn test_claude_command_appends_prompt() {
+        let session = create_test_session_with_time("sess-123", "/tmp", None, 2024, 1, 1);
+        let launch = LaunchOptions {
+            prompt: Some("run the tests again".to_string()),
+            ..Default::default()
+        };
+
+        let command = claude_command(&session, Path::new(&session.path), &launch);
+
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            vec!["--resume", "sess-123", "--", "run the tests again"]
+        );
+    }
+
+    #[test]
+    fn test_claude_command_passes_prompt_after_separator() {
+        let session = create_test_session_with_time("sess-123", "/tmp", None, 2024, 1, 1);
+        let launch = LaunchOptions {
+            prompt: Some("--dangerously-skip-permissions".to_string()),
+            ..Default::default()
+        };
+
+        let command = claude_command(&session, Path::new(&session.path), &launch);
+
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            vec![
+                "--resume",
+                "sess-123",
+                "--",
+                "--dangerously-skip-permissions"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_claude_command_sets_env() {
+        let session = create_test_session_with_time("sess-123", "/tmp", None, 2024, 1, 1);
+        let launch = LaunchOptions {
+            env: vec![
+                (
+                    "ANTHROPIC_BASE_URL".to_string(),
+                    "http://localhost:8080".to_string(),
+                ),
+                ("ANTHROPIC_MODEL".to_string(), "opus".to_string()),
+            ],
+            ..Default::default()
+        };
+
+        let command = claude_command(&session, Path::new(&session.path), &launch);
+
+        let envs: HashMap<_, _> = command
+            .get_envs()
+            .map(|(k, v)| (k.to_owned(), v.map(|v| v.to_owned())))
+            .collect();
+        assert_eq!(envs.len(), 2);
+        assert_eq!(
+            envs[OsStr::new("ANTHROPIC_BASE_URL")].as_deref(),
+            Some(OsStr::new("http://localhost:8080"))
+        );
+        assert_eq!(
+            envs[OsStr::new("ANTHROPIC_MODEL")].as_deref(),
+            Some(OsStr::new("opus"))
+        );
+        // Arguments are untouched
+        assert_eq!(command.get_args().count(), 2);
+    }
+
+    #[test]
+    fn test_add_label_description_on_new_label() {
+        let (storage, path) = create_test_storage("add-label-desc-new");
+        let options = AddOptions {
+            label_description: Some("OAuth bug".to_string()),
+            ..Default::default()
+        };
+
+        add(&storage, "TICKET-1", "sess-1", None, &options).unwrap();
+
+        let store = storage.load().unwrap();
+        let label = store.get_label("TICKET-1").unwrap();
+        assert_eq!(label.description, Some("OAuth bug".to_string()));
+        assert_eq!(label.sessions.len(), 1);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_label_description_updates_existing_label() {
+        let (storage, path) = create_test_storage("add-label-desc-existing");
+        add(&storage, "TICKET-1", "sess-1", None, &AddOptions::default()).unwrap();
+        describe(
+            &storage,
+            "TICKET-1",
+            Some("Old".to_string()),
+            &DescribeOptions::default(),
+        )
+        .unwrap();
+
+        let options = AddOptions {
+            label_description: Some("New".to_string()),
+            ..Default::default()
+        };
+        add(&storage, "TICKET-1", "sess-2", None, &options).unwrap();
+
+        let store = storage.load().unwrap();
+        let label = store.get_label("TICKET-1").unwrap();
+        assert_eq!(label.description, Some("New".to_string()));
+        assert_eq!(label.sessions.len(), 2);
+
+        // Without the flag the label description is left alone
+        add(&storage, "TICKET-1", "sess-3", None, &AddOptions::default()).unwrap();
+        let store = storage.load().unwrap();
+        assert_eq!(
+            store.get_label("TICKET-1").unwrap().description,
+            Some("New".to_string())
+        );
+        cleanup(&path);
+    }
+
+    // ==================== Add Move Existing Tests ====================
+
+    fn create_move_store(storage: &Storage) {
+        storage
+            .update(|store| {
+                let mut old = Label::new(None);
+                let mut session = create_test_session_with_time("sess-1", "/old", None, 2024, 1, 1);
+                session.description = Some("Earlier work".to_string());
+                old.add_session(session);
+                store.labels.insert("old".to_string(), old);
+                store.labels.insert("new".to_string(), Label::new(None));
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_add_move_existing_relocates_session() {
+        let (storage, path) = create_test_storage("add-move");
+        create_move_store(&storage);
+        let options = AddOptions {
+            move_existing: true,
+            ..Default::default()
+        };
+
+        add(&storage, "new", "sess-1", None, &options).unwrap();
+
+        let store = storage.load().unwrap();
+        assert!(store.get_label("old").unwrap().sessions.is_empty());
+        let moved = &store.get_label("new").unwrap().sessions;
+        assert_eq!(moved.len(), 1);
+        // The new copy keeps its own path but inherits the old description
+        assert_ne!(moved[0].path, "/old");
+        assert_eq!(moved[0].description, Some("Earlier work".to_string()));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_without_move_existing_duplicates() {
+        let (storage, path) = create_test_storage("add-move-off");
+        create_move_store(&storage);
+
+        add(&storage, "new", "sess-1", None, &AddOptions::default()).unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.get_label("old").unwrap().sessions.len(), 1);
+        assert_eq!(store.get_label("new").unwrap().sessions.len(), 1);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_move_existing_not_found_elsewhere_just_adds() {
+        let (storage, path) = create_test_storage("add-move-none");
+        create_move_store(&storage);
+        let options = AddOptions {
+            move_existing: true,
+            ..Default::default()
+        };
+
+        add(&storage, "new", "sess-2", None, &options).unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.get_label("old").unwrap().sessions.len(), 1);
+        assert_eq!(
+            store.get_label("new").unwrap().sessions[0].session_id,
+            "sess-2"
+        );
+        cleanup(&path);
+    }
+
+    // ==================== Add Idempotent Tests ====================
+
+    #[test]
+    fn test_add_idempotent_skips_same_id_and_path() {
+        let (storage, path) = create_test_storage("add-idem-skip");
+        let options = AddOptions {
+            idempotent: true,
+            ..Default::default()
+        };
+
+        add(&storage, "work", "sess-1", None, &options).unwrap();
+        add(
+            &storage,
+            "work",
+            "sess-1",
+            Some("again".to_string()),
+            &options,
+        )
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        let sessions = &store.get_label("work").unwrap().sessions;
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].description, None);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_idempotent_inserts_when_path_differs() {
+        let (storage, path) = create_test_storage("add-idem-path");
+        storage
+            .update(|store| {
+                store
+                    .get_or_create_label("work")
+                    .add_session(create_test_session_with_time(
+                        "sess-1",
+                        "/elsewhere",
+                        None,
+                        2024,
+                        1,
+                        1,
+                    ));
+                Ok(())
+            })
+            .unwrap();
+        let options = AddOptions {
+            idempotent: true,
+            ..Default::default()
+        };
+
+        add(&storage, "work", "sess-1", None, &options).unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.get_label("work").unwrap().sessions.len(), 2);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_idempotent_concurrent_reruns_insert_once() {
+        let (storage, path) = create_test_storage("add-idem-concurrent");
+        storage.save(&Store::new()).unwrap();
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let options = AddOptions {
+                        idempotent: true,
+                        ..Default::default()
+                    };
+                    add(&Storage::with_path(path), "work", "sess-1", None, &options).unwrap();
+                })
+            })
+            .collect();
+        for handle in threads {
+            handle.join().unwrap();
+        }
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.get_label("work").unwrap().sessions.len(), 1);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_idempotent_skip_does_not_write() {
+        let (storage, path) = create_test_storage("add-idem-no-write");
+        let options = AddOptions {
+            idempotent: true,
+            ..Default::default()
+        };
+        add(&storage, "work", "sess-1", None, &options).unwrap();
+
+        let again = Storage::with_path(path.clone());
+        add(&again, "work", "sess-1", None, &options).unwrap();
+        assert!(!again.was_saved());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_without_idempotent_duplicates() {
+        let (storage, path) = create_test_storage("add-idem-off");
+
+        add(&storage, "work", "sess-1", None, &AddOptions::default()).unwrap();
+        add(&storage, "work", "sess-1", None, &AddOptions::default()).unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.get_label("work").unwrap().sessions.len(), 2);
+        cleanup(&path);
+    }
+
+    // ==================== Allowed Roots Tests ====================
+
+    fn storage_with_roots(name: &str, roots: &[&str]) -> (Storage, PathBuf) {
+        let (storage, path) = create_test_storage(name);
+        storage
+            .update(|store| {
+                store.settings.allowed_roots = roots.iter().map(|r| r.to_string()).collect();
+                Ok(())
+            })
+            .unwrap();
+        (storage, path)
+    }
+
+    #[test]
+    fn test_check_allowed_root() {
+        let roots = vec!["/work/mono".to_string(), "/work/tools".to_string()];
+
+        assert!(check_allowed_root(&[], "/anywhere").is_ok());
+        assert!(check_allowed_root(&roots, "/work/mono").is_ok());
+        assert!(check_allowed_root(&roots, "/work/tools/cli").is_ok());
+        assert!(check_allowed_root(&roots, "/work/mono-old").is_err());
+        assert!(check_allowed_root(&roots, "/home/me").is_err());
+    }
+
+    #[test]
+    fn test_add_inside_allowed_root() {
+        let cwd = env::current_dir().unwrap();
+        let root = cwd.parent().unwrap_or(&cwd).to_string_lossy().to_string();
+        let (storage, path) = storage_with_roots("add-root-ok", &[&root]);
+
+        add(&storage, "work", "sess-1", None, &AddOptions::default()).unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.get_label("work").unwrap().sessions.len(), 1);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_outside_allowed_roots_fails() {
+        let (storage, path) = storage_with_roots("add-root-bad", &["/no/such/monorepo"]);
+
+        let err = add(&storage, "work", "sess-1", None, &AddOptions::default()).unwrap_err();
+
+        assert!(
+            err.to_string().contains("not under any allowed root"),
+            "{}",
+            err
+        );
+        assert!(storage.load().unwrap().get_label("work").is_none());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_add_force_bypasses_allowed_roots() {
+        let (storage, path) = storage_with_roots("add-root-force", &["/no/such/monorepo"]);
+        let options = AddOptions {
+            force: true,
+            ..Default::default()
+        };
+
+        add(&storage, "work", "sess-1", None, &options).unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.get_label("work").unwrap().sessions.len(), 1);
+        cleanup(&path);
+    }
+
+    // ==================== Add Stdin JSON Tests ====================
+
+    #[test]
+    fn test_session_from_json_full_object() {
+        let json = r#"{
+            "session_id": "0f8fad5b-d9cb-469f-a165-70867728950e",
+            "path": "/projects/app",
+            "description": "Imported",
+            "created_at": "2024-06-15T14:30:00Z",
+            "meta": {"model": "opus"},
+            "resume_count": 3,
+            "last_resumed_at": "2024-06-20T08:00:00Z"
+        }"#;
+
+        let session = session_from_json(json, "/cwd", Utc::now()).unwrap();
+        assert_eq!(session.session_id, "0f8fad5b-d9cb-469f-a165-70867728950e");
+        assert_eq!(session.path, "/projects/app");
+        assert_eq!(session.description, Some("Imported".to_string()));
+        assert_eq!(
+            session.created_at,
+            Utc.with_ymd_and_hms(2024, 6, 15, 14, 30, 0).unwrap()
+        );
+        assert_eq!(session.meta.get("model"), Some(&"opus".to_string()));
+        assert_eq!(session.resume_count, 3);
+        assert_eq!(
+            session.last_resumed_at,
+            Some(Utc.with_ymd_and_hms(2024, 6, 20, 8, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_session_from_json_minimal_object_gets_defaults() {
+        let now = Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap();
+
+        let session = session_from_json(r#"{"session_id": "sess-1"}"#, "/cwd", now).unwrap();
+        assert_eq!(session.path, "/cwd");
+        assert_eq!(session.created_at, now);
+        assert_eq!(session.description, None);
+        assert!(session.meta.is_empty());
+        assert_eq!(session.resume_count, 0);
+    }
+
+    #[test]
+    fn test_session_from_json_malformed_errors() {
+        let err = session_from_json("{not json", "/cwd", Utc::now()).unwrap_err();
+        assert!(err.to_string().contains("Invalid session JSON"));
+
+        assert!(session_from_json(r#"{"path": "/x"}"#, "/cwd", Utc::now()).is_err());
+        assert!(session_from_json(r#"{"session_id": " "}"#, "/cwd", Utc::now()).is_err());
+    }
+
+    #[test]
+    fn test_add_json_inserts_session_and_merges_meta() {
+        let (storage, path) = create_test_storage("add-json");
+        let options = AddOptions {
+            meta: vec![("reviewer".to_string(), "alice".to_string())],
+            ..Default::default()
+        };
+
+        add_json(
+            &storage,
+            "my-label",
+            r#"{"session_id": "sess-1", "path": "/p", "meta": {"model": "opus"}}"#,
+            &options,
+        )
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        let session = &store.get_label("my-label").unwrap().sessions[0];
+        assert_eq!(session.path, "/p");
+        assert_eq!(session.meta.len(), 2);
+        cleanup(&path);
+    }
+
+    // ==================== List Command Tests ====================
+
+    fn render_list(store: &Store, label: Option<&str>, options: &ListOptions) -> String {
+        let mut out = Vec::new();
+        match label {
+            Some(name) => write_label_details(
+                &mut out,
+                name,
+                store.get_label(name).unwrap(),
+                options,
+                DisplayZone::Utc,
+            )
+            .unwrap(),
+            None => write_label_summaries(&mut out, store, options).unwrap(),
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_list_render_wraps_descriptions_by_rendered_width() {
+        let mut store = Store::new();
+        let mut label = Label::new(Some(
+            "Label notes with **quite a few** words to wrap".to_string(),
+        ));
+        label.add_session(create_test_session_with_time(
+            "s1",
+            "/work",
+            Some("Session notes that are *much* longer than the width allows"),
+            2024,
+            1,
+            1,
+        ));
+        store.labels.insert("auth".to_string(), label);
+        let options = ListOptions {
+            mode: OutputMode::Plain,
+            render: true,
+            width: Some(34),
+            ..Default::default()
+        };
+
+        let output = render_list(&store, Some("auth"), &options);
+
+        assert!(!output.contains('*'), "{}", output);
+        for line in output.lines() {
+            assert!(line.width() <= 34, "{:?}", line);
+        }
+        let lines: Vec<&str> = output.lines().collect();
+        let start = lines
+            .iter()
+            .position(|line| line.starts_with("    Description: "))
+            .unwrap();
+        assert!(
+            lines[start + 1].starts_with("                 "),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_list_non_tty_output_is_plain() {
+        let mut store = Store::new();
+        let mut label = Label::new(Some("Auth work".to_string()));
+        label.color = Some("magenta".to_string());
+        label.add_session(create_test_session_with_time(
+            "s1",
+            "/work/app",
+            Some("First pass"),
+            2024,
+            1,
+            1,
+        ));
+        label.add_session(create_test_session_with_time(
+            "s2",
+            "/work/app",
+            None,
+            2024,
+            2,
+            1,
+        ));
+        store.labels.insert("auth".to_string(), label);
+
+        // What `run` decides for piped stdout
+        let plain = ListOptions {
+            mode: OutputMode::detect(false, false),
+            heat: true,
+            page: Page {
+                offset: 0,
+                limit: Some(1),
+            },
+            ..Default::default()
+        };
+        let tree = ListOptions {
+            mode: OutputMode::Plain,
+            tree: true,
+            ..Default::default()
+        };
+        for output in [
+            render_list(&store, Some("auth"), &plain),
+            render_list(&store, None, &plain),
+            render_list(&store, None, &tree),
+            render_list(&Store::new(), None, &plain),
+        ] {
+            assert!(!output.contains('\x1b'), "{:?}", output);
+            assert!(output.is_ascii(), "{:?}", output);
+        }
+        let page = render_list(&store, Some("auth"), &plain);
+        assert!(page.contains("showing 1-1 of 2"), "{:?}", page);
+
+        let details = render_list(&store, Some("auth"), &plain);
+        assert!(details.contains("  - s2\n"));
+        assert!(render_list(&store, None, &plain).contains("auth [months]"));
+
+        let styled = ListOptions {
+            mode: OutputMode::detect(true, false),
+            ..Default::default()
+        };
+        assert!(render_list(&store, Some("auth"), &styled).contains("•"));
+    }
+
+    #[test]
+    fn test_list_empty_store() {
+        let (storage, path) = create_test_storage("list-empty");
+
+        // Should not error on empty store
+        let result = list(&storage, None, &ListOptions::default());
+        assert!(result.is_ok());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_list_all_labels() {
+        let (storage, path) = create_test_storage("list-all");
+
+        add(&storage, "label-1", "sess-1", None, &AddOptions::default()).unwrap();
+        add(&storage, "label-2", "sess-2", None, &AddOptions::default()).unwrap();
+
+        let result = list(&storage, None, &ListOptions::default());
+        assert!(result.is_ok());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_list_specific_label() {
+        let (storage, path) = create_test_storage("list-specific");
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+
+        let result = list(&storage, Some("my-label"), &ListOptions::default());
+        assert!(result.is_ok());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_list_nonexistent_label_returns_error() {
+        let (storage, path) = create_test_storage("list-nonexistent");
+
+        let result = list(&storage, Some("nonexistent"), &ListOptions::default());
+        assert!(result.is_err());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_list_json_output() {
+        let (storage, path) = create_test_storage("list-json");
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+
+        let options = ListOptions {
+            json: true,
+            ..Default::default()
+        };
+        assert!(list(&storage, None, &options).is_ok());
+        assert!(list(&storage, Some("my-label"), &options).is_ok());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_label_overview_one_entry_per_label() {
+        let mut store = Store::new();
+        let mut api = Label::new(Some("API work".to_string()));
+        api.add_session(create_test_session_with_time(
+            "a1", "/api", None, 2024, 1, 1,
+        ));
+        api.add_session(create_test_session_with_time(
+            "a2", "/api", None, 2024, 3, 1,
+        ));
+        store.labels.insert("api".to_string(), api);
+        store.labels.insert("empty".to_string(), Label::new(None));
+
+        let overview = label_overview(&store, &ListOptions::default());
+        let json = serde_json::to_value(&overview).unwrap();
+        let entries = json.as_array().unwrap();
+
+        assert_eq!(entries.len(), store.labels.len());
+        assert_eq!(entries[0]["name"], "api");
+        assert_eq!(entries[0]["description"], "API work");
+        assert_eq!(entries[0]["session_count"], 2);
+        assert_eq!(entries[0]["latest_created_at"], "2024-03-01T12:00:00Z");
+        assert_eq!(entries[1]["name"], "empty");
+        assert!(entries[1]["description"].is_null());
+        assert_eq!(entries[1]["session_count"], 0);
+        assert!(entries[1]["latest_created_at"].is_null());
+    }
+
+    #[test]
+    fn test_render_json_compact_is_single_line() {
+        let mut store = Store::new();
+        let mut label = Label::new(Some("Multi\nline description".to_string()));
+        label.add_session(create_test_session_with_time(
+            "sess-1",
+            "/path",
+            Some("First"),
+            2024,
+            1,
+            1,
+        ));
+        store.labels.insert("my-label".to_string(), label);
+
+        let compact = render_json(&store, true).unwrap();
+        assert!(!compact.contains('\n'));
+
+        let pretty = render_json(&store, false).unwrap();
+        assert!(pretty.contains('\n'));
+
+        let deserialized: Store = serde_json::from_str(&compact).unwrap();
+        let label = deserialized.get_label("my-label").unwrap();
+        assert_eq!(
+            label.description,
+            Some("Multi\nline description".to_string())
+        );
+        assert_eq!(label.sessions[0].session_id, "sess-1");
+    }
+
+    #[test]
+    fn test_render_json_indented() {
+        let mut store = Store::new();
+        store
+            .labels
+            .insert("my-label".to_string(), Label::new(Some("Desc".to_string())));
+        let out = StoreOut::from(&store);
+
+        let four = render_json_indented(&out, 4).unwrap();
+        assert!(four.contains("\n    \"labels\""));
+        assert!(four.contains("\n        \"my-label\""));
+
+        let flat = render_json_indented(&out, 0).unwrap();
+        assert!(!flat.contains('\n'));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&four).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&flat).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_list_json_indent_defaults() {
+        let mut options = ListOptions::default();
+        assert_eq!(options.json_indent(), 2);
+        options.compact = true;
+        assert_eq!(options.json_indent(), 0);
+        options.indent = Some(4);
+        assert_eq!(options.json_indent(), 4);
+    }
+
+    #[test]
+    fn test_list_json_and_porcelain_conflict() {
+        let (storage, path) = create_test_storage("list-json-porcelain");
+
+        let options = ListOptions {
+            json: true,
+            porcelain: true,
+            ..Default::default()
+        };
+        assert!(list(&storage, None, &options).is_err());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_list_with_age_and_recent_sort() {
+        let (storage, path) = create_test_storage("list-age");
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+
+        let options = ListOptions {
+            age: true,
+            sort: LabelSort::Recent,
+            reverse: true,
+            ..Default::default()
+        };
+        assert!(list(&storage, None, &options).is_ok());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_list_compact_empty_hides_empty_labels_but_keeps_them() {
+        let (mut storage, path) = create_test_storage("list-compact-empty");
+        add(&storage, "busy", "sess-1", None, &AddOptions::default()).unwrap();
+        storage
+            .update(|store| {
+                store.labels.insert("empty".to_string(), Label::new(None));
+                Ok(())
+            })
+            .unwrap();
+        storage.set_compact_empty(true);
+
+        let options = ListOptions {
+            porcelain: true,
+            ..Default::default()
+        };
+        let listed = |label: Option<&str>| {
+            let mut out = Vec::new();
+            list_to(&mut out, &storage, label, &options).map(|()| String::from_utf8(out).unwrap())
+        };
+        let labels: Vec<String> = listed(None)
+            .unwrap()
+            .lines()
+            .map(|line| line.split('\t').next().unwrap().to_string())
+            .collect();
+        assert_eq!(labels, vec!["busy"]);
+        assert!(listed(Some("empty")).is_err());
+        let sessions = listed(Some("busy")).unwrap();
+        assert_eq!(sessions.lines().count(), 1, "{}", sessions);
+        assert!(sessions.contains("sess-1"), "{}", sessions);
+
+        assert!(storage.load().unwrap().labels.contains_key("empty"));
+        cleanup(&path);
+    }
+
+    // ==================== Label Age Tests ====================
+
+    fn create_age_store() -> Store {
+        let mut store = Store::new();
+
+        let mut old = Label::new(None);
+        old.add_session(create_test_session_with_time("o1", "/p", None, 2023, 1, 1));
+
+        let mut fresh = Label::new(None);
+        fresh.add_session(create_test_session_with_time("f1", "/p", None, 2023, 1, 1));
+        fresh.add_session(create_test_session_with_time("f2", "/p", None, 2024, 6, 1));
+
+        store.labels.insert("old".to_string(), old);
+        store.labels.insert("fresh".to_string(), fresh);
+        store.labels.insert("empty".to_string(), Label::new(None));
+        store
+    }
+
+    fn sorted_names(store: &Store, sort: LabelSort, reverse: bool) -> Vec<String> {
+        let mut labels: Vec<(&String, &Label)> = store.labels.iter().collect();
+        sort_labels(&mut labels, sort, reverse);
+        labels.iter().map(|(name, _)| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_sort_labels_by_name() {
+        let store = create_age_store();
+
+        assert_eq!(
+            sorted_names(&store, LabelSort::Name, false),
+            vec!["empty", "fresh", "old"]
+        );
+    }
+
+    #[test]
+    fn test_sort_labels_by_recent_activity() {
+        let store = create_age_store();
+
+        assert_eq!(
+            sorted_names(&store, LabelSort::Recent, false),
+            vec!["fresh", "old", "empty"]
+        );
+        assert_eq!(
+            sorted_names(&store, LabelSort::Recent, true),
+            vec!["empty", "old", "fresh"]
+        );
+    }
+
+    #[test]
+    fn test_format_last_activity() {
+        let store = create_age_store();
+        let now = Utc.with_ymd_and_hms(2024, 6, 4, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            format_last_activity(store.get_label("fresh").unwrap(), now),
+            "3d ago"
+        );
+        assert_eq!(
+            format_last_activity(store.get_label("empty").unwrap(), now),
+            "never"
+        );
+    }
+
+    // ==================== Active Since Tests ====================
+
+    #[test]
+    fn test_active_since_keeps_labels_at_cutoff() {
+        let mut store = Store::new();
+        let mut at_cutoff = Label::new(None);
+        at_cutoff.add_session(create_test_session_with_time("s1", "/p", None, 2024, 6, 1));
+        let mut stale = Label::new(None);
+        stale.add_session(create_test_session_with_time("s2", "/p", None, 2024, 5, 1));
+        stale.add_session(create_test_session_with_time("s3", "/p", None, 2024, 5, 31));
+        store.labels.insert("at-cutoff".to_string(), at_cutoff);
+        store.labels.insert("stale".to_string(), stale);
+
+        let cutoff = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        retain_labels_active_since(&mut store, cutoff);
+
+        assert_eq!(sorted_label_names(&store), vec!["at-cutoff"]);
+    }
+
+    #[test]
+    fn test_active_since_drops_empty_labels() {
+        let mut store = Store::new();
+        store.labels.insert("empty".to_string(), Label::new(None));
+
+        retain_labels_active_since(
+            &mut store,
+            Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap(),
+        );
+
+        assert!(store.labels.is_empty());
+    }
+
+    #[test]
+    fn test_list_active_since_out_of_range_is_error() {
+        let (storage, path) = create_test_storage("active-since-range");
+        add(&storage, "work", "sess-1", None, &AddOptions::default()).unwrap();
+
+        let options = ListOptions {
+            active_since: Some(chrono::Duration::weeks(20_000_000)),
+            ..ListOptions::default()
+        };
+        let mut out = Vec::new();
+
+        assert!(list_to(&mut out, &storage, None, &options).is_err());
+        assert!(out.is_empty());
+        cleanup(&path);
+    }
+
+    // ==================== Path Filter Tests ====================
+
+    fn path_filter_store(present: &Path, absent: &Path) -> Store {
+        let present = present.to_string_lossy();
+        let absent = absent.to_string_lossy();
+
+        let mut store = Store::new();
+        let mut mixed = Label::new(None);
+        mixed.add_session(create_test_session_with_time(
+            "here", &present, None, 2024, 1, 1,
+        ));
+        mixed.add_session(create_test_session_with_time(
+            "gone", &absent, None, 2024, 1, 2,
+        ));
+        let mut stale = Label::new(None);
+        stale.add_session(create_test_session_with_time(
+            "old", &absent, None, 2024, 1, 3,
+        ));
+        store.labels.insert("mixed".to_string(), mixed);
+        store.labels.insert("stale".to_string(), stale);
+        store
+    }
+
+    fn session_ids_of(store: &Store, label: &str) -> Vec<String> {
+        store
+            .get_label(label)
+            .unwrap()
+            .sessions
+            .iter()
+            .map(|s| s.session_id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_sessions_path_missing() {
+        let present = temp_path("path-filter-present");
+        let absent = temp_path("path-filter-absent");
+        fs::create_dir_all(&present).unwrap();
+        let _ = fs::remove_dir_all(&absent);
+
+        let mut store = path_filter_store(&present, &absent);
+        filter_sessions_by_path(&mut store, PathFilter::Missing, Path::is_dir, true);
+
+        assert_eq!(session_ids_of(&store, "mixed"), vec!["gone"]);
+        assert_eq!(session_ids_of(&store, "stale"), vec!["old"]);
+        fs::remove_dir_all(&present).unwrap();
+    }
+
+    #[test]
+    fn test_filter_sessions_path_exists_drops_empty_labels() {
+        let present = temp_path("path-filter-exists-present");
+        let absent = temp_path("path-filter-exists-absent");
+        fs::create_dir_all(&present).unwrap();
+        let _ = fs::remove_dir_all(&absent);
+
+        let mut store = path_filter_store(&present, &absent);
+        filter_sessions_by_path(&mut store, PathFilter::Exists, Path::is_dir, true);
+
+        assert_eq!(session_ids_of(&store, "mixed"), vec!["here"]);
+        assert!(store.get_label("stale").is_none());
+        fs::remove_dir_all(&present).unwrap();
+    }
+
+    #[test]
+    fn test_filter_sessions_keeps_empty_label_when_asked() {
+        let mut store = path_filter_store(Path::new("/present"), Path::new("/absent"));
+        filter_sessions_by_path(
+            &mut store,
+            PathFilter::Exists,
+            |p| p == Path::new("/present"),
+            false,
+        );
+
+        assert_eq!(session_ids_of(&store, "mixed"), vec!["here"]);
+        assert!(session_ids_of(&store, "stale").is_empty());
+    }
+
+    // ==================== Resume Filter Tests ====================
+
+    fn resumed_session(id: &str, resumed: Option<(i32, u32, u32)>) -> Session {
+        let mut session = create_test_session_with_time(id, "/p", None, 2024, 1, 1);
+        session.last_resumed_at =
+            resumed.map(|(y, m, d)| Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap());
+        session
+    }
+
+    fn kept_ids(sessions: &[Session], filter: &ResumeFilter) -> Vec<String> {
+        sessions
+            .iter()
+            .filter(|s| filter.keeps(s))
+            .map(|s| s.session_id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_resume_filter_predicates() {
+        let sessions = vec![
+            resumed_session("never", None),
+            resumed_session("long-ago", Some((2024, 1, 5))),
+            resumed_session("recent", Some((2024, 6, 1))),
+        ];
+        let cutoff = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+
+        let never = ResumeFilter {
+            never_resumed: true,
+            ..Default::default()
+        };
+        assert_eq!(kept_ids(&sessions, &never), vec!["never"]);
+
+        let before = ResumeFilter {
+            resumed_before: Some(cutoff),
+            ..Default::default()
+        };
+        assert_eq!(kept_ids(&sessions, &before), vec!["long-ago"]);
+
+        let either = ResumeFilter {
+            never_resumed: true,
+            resumed_before: Some(cutoff),
+        };
+        assert_eq!(kept_ids(&sessions, &either), vec!["never", "long-ago"]);
+
+        assert!(!ResumeFilter::default().is_active());
+        assert!(either.is_active());
+    }
+
+    #[test]
+    fn test_resume_filter_legacy_session_is_never_resumed() {
+        let session: Session = serde_json::from_str(
+            r#"{"session_id":"legacy","path":"/p","created_at":"2023-01-01T00:00:00Z"}"#,
+        )
+        .unwrap();
+
+        assert!(is_never_resumed(&session));
+        assert!(!was_resumed_before(&session, Utc::now()));
+    }
+
+    // ==================== Label Tree Tests ====================
+
+    fn label_with_sessions(count: usize) -> Label {
+        let mut label = Label::new(None);
+        for i in 0..count {
+            label.add_session(create_test_session_with_time(
+                &format!("s{}", i),
+                "/p",
+                None,
+                2024,
+                1,
+                1,
+            ));
+        }
+        label
+    }
+
+    fn tree_line(depth: usize, name: &str, count: usize, is_label: bool) -> TreeLine {
+        TreeLine {
+            depth,
+            name: name.to_string(),
+            count,
+            is_label,
+        }
+    }
+
+    #[test]
+    fn test_label_tree_aggregates_counts() {
+        let mut store = Store::new();
+        store
+            .labels
+            .insert("team/web/TICKET-1".to_string(), label_with_sessions(2));
+        store
+            .labels
+            .insert("team/web/TICKET-2".to_string(), label_with_sessions(1));
+        store
+            .labels
+            .insert("team/api".to_string(), label_with_sessions(3));
+        store
+            .labels
+            .insert("team".to_string(), label_with_sessions(1));
+        store
+            .labels
+            .insert("scratch".to_string(), label_with_sessions(0));
+
+        let tree = build_label_tree(&store);
+        assert_eq!(tree.count, 7);
+
+        assert_eq!(
+            flatten_label_tree(&tree),
+            vec![
+                tree_line(0, "scratch", 0, true),
+                tree_line(0, "team", 7, true),
+                tree_line(1, "api", 3, true),
+                tree_line(1, "web", 3, false),
+                tree_line(2, "TICKET-1", 2, true),
+                tree_line(2, "TICKET-2", 1, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_label_tree_flat_names_at_root() {
+        let mut store = Store::new();
+        store
+            .labels
+            .insert("beta".to_string(), label_with_sessions(1));
+        store
+            .labels
+            .insert("alpha".to_string(), label_with_sessions(2));
+
+        assert_eq!(
+            flatten_label_tree(&build_label_tree(&store)),
+            vec![
+                tree_line(0, "alpha", 2, true),
+                tree_line(0, "beta", 1, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_label_tree_empty_store() {
+        let tree = build_label_tree(&Store::new());
+
+        assert_eq!(tree.count, 0);
+        assert!(flatten_label_tree(&tree).is_empty());
+    }
+
+    // ==================== Pagination Tests ====================
+
+    #[test]
+    fn test_paginate_inactive_shows_everything_without_footer() {
+        let (shown, footer) = paginate(vec![1, 2, 3], Page::default(), OutputMode::Styled);
+        assert_eq!(shown, vec![1, 2, 3]);
+        assert_eq!(footer, None);
+    }
+
+    #[test]
+    fn test_paginate_offset_and_limit() {
+        let items: Vec<usize> = (1..=134).collect();
+        let (shown, footer) = paginate(
+            items,
+            Page {
+                offset: 20,
+                limit: Some(20),
+            },
+            OutputMode::Styled,
+        );
+        assert_eq!(shown, (21..=40).collect::<Vec<_>>());
+        assert_eq!(footer.as_deref(), Some("showing 21–40 of 134"));
+    }
+
+    #[test]
+    fn test_paginate_plain_footer_is_ascii() {
+        let (_, footer) = paginate(
+            vec!["a", "b", "c"],
+            Page {
+                offset: 1,
+                limit: Some(1),
+            },
+            OutputMode::Plain,
+        );
+        assert_eq!(footer.as_deref(), Some("showing 2-2 of 3"));
+    }
+
+    #[test]
+    fn test_paginate_limit_past_end_is_clamped() {
+        let (shown, footer) = paginate(
+            vec!["a", "b", "c"],
+            Page {
+                offset: 1,
+                limit: Some(10),
+            },
+            OutputMode::Styled,
+        );
+        assert_eq!(shown, vec!["b", "c"]);
+        assert_eq!(footer.as_deref(), Some("showing 2–3 of 3"));
+    }
+
+    #[test]
+    fn test_paginate_offset_only() {
+        let (shown, footer) = paginate(
+            vec!["a", "b", "c"],
+            Page {
+                offset: 2,
+                limit: None,
+            },
+            OutputMode::Styled,
+        );
+        assert_eq!(shown, vec!["c"]);
+        assert_eq!(footer.as_deref(), Some("showing 3–3 of 3"));
+    }
+
+    #[test]
+    fn test_paginate_offset_past_end_is_empty() {
+        let (shown, footer) = paginate(
+            vec!["a", "b"],
+            Page {
+                offset: 5,
+                limit: Some(usize::MAX),
+            },
+            OutputMode::Styled,
+        );
+        assert!(shown.is_empty());
+        assert_eq!(footer.as_deref(), Some("showing none of 2"));
+    }
+
+    #[test]
+    fn test_paginate_zero_limit() {
+        let (shown, footer) = paginate(
+            vec!["a"],
+            Page {
+                offset: 0,
+                limit: Some(0),
+            },
+            OutputMode::Styled,
+        );
+        assert!(shown.is_empty());
+        assert_eq!(footer.as_deref(), Some("showing none of 1"));
+    }
+
+    // ==================== Null Output Tests ====================
+
+    #[test]
+    fn test_write_records_null_keeps_newlines_in_names() {
+        let mut out = Vec::new();
+        write_records(&mut out, ["plain", "two\nlines", "功能 🚀"], true).unwrap();
+
+        assert_eq!(out, "plain\0two\nlines\0功能 🚀\0".as_bytes());
+        let records: Vec<&[u8]> = out.split(|&b| b == 0).collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[1], b"two\nlines");
+        assert!(records[3].is_empty());
+    }
+
+    #[test]
+    fn test_write_records_newline_terminated_by_default() {
+        let mut out = Vec::new();
+        write_records(&mut out, ["a", "b"], false).unwrap();
+
+        assert_eq!(out, b"a\nb\n");
+    }
+
+    #[test]
+    fn test_write_records_empty() {
+        let mut out = Vec::new();
+        write_records(&mut out, Vec::<String>::new(), true).unwrap();
+
+        assert!(out.is_empty());
+    }
+
+    // ==================== Porcelain Tests ====================
+
+    #[test]
+    fn test_porcelain_labels_columns() {
+        let mut store = Store::new();
+        let mut label = Label::new(Some("Auth work".to_string()));
+        label.add_session(create_test_session_with_time("s1", "/p", None, 2024, 1, 1));
+        label.add_session(create_test_session_with_time("s2", "/p", None, 2024, 1, 2));
+        store.labels.insert("beta".to_string(), label);
+        store.labels.insert("alpha".to_string(), Label::new(None));
+
+        let records = porcelain_labels(&store);
+
+        assert_eq!(records, vec!["alpha\t0\t", "beta\t2\tAuth work"]);
+    }
+
+    #[test]
+    fn test_porcelain_sessions_columns() {
+        let mut label = Label::new(None);
+        label.add_session(create_test_session_with_time(
+            "old",
+            "/path/old",
+            None,
+            2024,
+            1,
+            1,
+        ));
+        label.add_session(create_test_session_with_time(
+            "new",
+            "/path/new",
+            Some("Fix"),
+            2024,
+            6,
+            1,
+        ));
+
+        let records = porcelain_sessions("my-label", &label, TimeFormat::Rfc3339);
+
+        assert_eq!(
+            records,
+            vec![
+                "my-label\tnew\t2024-06-01T12:00:00+00:00\t/path/new\tFix",
+                "my-label\told\t2024-01-01T12:00:00+00:00\t/path/old\t",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_porcelain_escapes_special_characters() {
+        let mut label = Label::new(None);
+        label.add_session(create_test_session_with_time(
+            "s1",
+            "/path\twith\ttabs",
+            Some("Line1\nLine2\r \\backslash"),
+            2024,
+            1,
+            1,
+        ));
+
+        let records = porcelain_sessions("label\tname", &label, TimeFormat::Rfc3339);
+
+        assert_eq!(records.len(), 1);
+        assert!(!records[0].contains('\n'));
+        assert_eq!(records[0].matches('\t').count(), 4);
+        assert_eq!(
+            records[0],
+            "label\\tname\ts1\t2024-01-01T12:00:00+00:00\t/path\\twith\\ttabs\tLine1\\nLine2\\r \\\\backslash"
+        );
+    }
+
+    // ==================== Label Picker Tests ====================
+
+    #[test]
+    fn test_label_candidates_match_store_labels() {
+        let mut store = Store::new();
+        for name in ["zeta", "alpha", "team/api", "Beta"] {
+            store.labels.insert(name.to_string(), Label::new(None));
+        }
+        store
+            .settings
+            .aliases
+            .insert("a".to_string(), "alpha".to_string());
+
+        // Aliases are not offered; labels come in name order
+        assert_eq!(
+            label_candidates(&store),
+            vec!["Beta", "alpha", "team/api", "zeta"]
+        );
+        assert!(label_candidates(&Store::new()).is_empty());
+    }
+
+    // ==================== Labels Command Tests ====================
+
+    fn render_labels(storage: &Storage, count: bool, null: bool) -> String {
+        let mut out = Vec::new();
+        labels_to(&mut out, storage, count, null).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_labels_empty_store() {
+        let (storage, path) = create_test_storage("labels-empty");
+
+        assert_eq!(render_labels(&storage, true, false), "0\n");
+        assert_eq!(render_labels(&storage, false, false), "");
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_labels_count_and_sorted_names() {
+        let (storage, path) = create_test_storage("labels-names");
+
+        add(&storage, "zeta", "sess-1", None, &AddOptions::default()).unwrap();
+        add(&storage, "alpha", "sess-2", None, &AddOptions::default()).unwrap();
+        add(&storage, "mid", "sess-3", None, &AddOptions::default()).unwrap();
+
+        assert_eq!(render_labels(&storage, true, false), "3\n");
+        assert_eq!(render_labels(&storage, false, false), "alpha\nmid\nzeta\n");
+        assert_eq!(render_labels(&storage, false, true), "alpha\0mid\0zeta\0");
+
+        cleanup(&path);
+    }
+
+    // ==================== Recent Command Tests ====================
+
+    fn create_recent_store() -> Store {
+        let mut store = Store::new();
+
+        let mut label_a = Label::new(None);
+        label_a.add_session(create_test_session_with_time(
+            "a-jan", "/a", None, 2024, 1, 10,
+        ));
+        label_a.add_session(create_test_session_with_time(
+            "a-jun", "/a", None, 2024, 6, 10,
+        ));
+
+        let mut label_b = Label::new(None);
+        label_b.add_session(create_test_session_with_time(
+            "b-mar", "/b", None, 2024, 3, 10,
+        ));
+        label_b.add_session(create_test_session_with_time(
+            "b-jun", "/b", None, 2024, 6, 12,
+        ));
+        label_b.add_session(create_test_session_with_time(
+            "b-jun-2", "/b", None, 2024, 6, 14,
+        ));
+
+        store.labels.insert("label-a".to_string(), label_a);
+        store.labels.insert("label-b".to_string(), label_b);
+        store
+    }
+
+    fn recent_ids(sessions: &[(&String, &Session)]) -> Vec<String> {
+        sessions.iter().map(|(_, s)| s.session_id.clone()).collect()
+    }
+
+    #[test]
+    fn test_recent_sessions_newest_first_capped_by_count() {
+        let store = create_recent_store();
+
+        let sessions = recent_sessions(&store, None, None, 2);
+
+        assert_eq!(recent_ids(&sessions), vec!["b-jun-2", "b-jun"]);
+        assert_eq!(sessions[0].0, "label-b");
+    }
+
+    #[test]
+    fn test_recent_sessions_since_duration() {
+        let store = create_recent_store();
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+        let since = parse_time_bound("7d", now).unwrap();
+
+        let sessions = recent_sessions(&store, Some(since), None, 10);
+
+        assert_eq!(recent_ids(&sessions), vec!["b-jun-2", "b-jun", "a-jun"]);
+    }
+
+    #[test]
+    fn test_recent_sessions_absolute_window() {
+        let store = create_recent_store();
+        let now = Utc::now();
+        let since = parse_time_bound("2024-02-01", now).unwrap();
+        let until = parse_time_bound("2024-06-11", now).unwrap();
+
+        let sessions = recent_sessions(&store, Some(since), Some(until), 10);
+
+        assert_eq!(recent_ids(&sessions), vec!["a-jun", "b-mar"]);
+    }
+
+    #[test]
+    fn test_recent_sessions_until_date_includes_that_day() {
+        let store = create_recent_store();
+        let until = parse_time_upper_bound("2024-06-10", Utc::now()).unwrap();
+
+        let sessions = recent_sessions(&store, None, Some(until), 10);
+
+        // a-jun was created at 12:00 on June 10
+        assert_eq!(recent_ids(&sessions), vec!["a-jun", "b-mar", "a-jan"]);
+    }
+
+    #[test]
+    fn test_recent_sessions_window_with_count_cap() {
+        let store = create_recent_store();
+        let since = parse_time_bound("2024-03-01", Utc::now()).unwrap();
+
+        let sessions = recent_sessions(&store, Some(since), None, 1);
+
+        assert_eq!(recent_ids(&sessions), vec!["b-jun-2"]);
+    }
+
+    #[test]
+    fn test_recent_invalid_since_returns_error() {
+        let (storage, path) = create_test_storage("recent-invalid");
+
+        let result = recent(&storage, 10, Some("not-a-date"), None, None);
+        assert!(result.is_err());
+
+        cleanup(&path);
+    }
+
+    // ==================== Clone Command Tests ====================
+
+    #[test]
+    fn test_clone_description_only() {
+        let (storage, path) = create_test_storage("clone-desc-only");
+
+        add(&storage, "sprint-7", "sess-1", None, &AddOptions::default()).unwrap();
+        describe(
+            &storage,
+            "sprint-7",
+            Some("Sprint work".to_string()),
+            &DescribeOptions::default(),
+        )
+        .unwrap();
+
+        clone_label(&storage, "sprint-7", "sprint-8", false).unwrap();
+
+        let store = storage.load().unwrap();
+        let cloned = store.get_label("sprint-8").unwrap();
+        assert_eq!(cloned.description, Some("Sprint work".to_string()));
+        assert!(cloned.sessions.is_empty());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_clone_with_sessions() {
+        let (storage, path) = create_test_storage("clone-with-sessions");
+
+        add(&storage, "sprint-7", "sess-1", None, &AddOptions::default()).unwrap();
+        add(&storage, "sprint-7", "sess-2", None, &AddOptions::default()).unwrap();
+
+        clone_label(&storage, "sprint-7", "sprint-8", true).unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.get_label("sprint-8").unwrap().sessions.len(), 2);
+        assert_eq!(store.get_label("sprint-7").unwrap().sessions.len(), 2);
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_clone_existing_dest_returns_error() {
+        let (storage, path) = create_test_storage("clone-existing-dest");
+
+        add(&storage, "sprint-7", "sess-1", None, &AddOptions::default()).unwrap();
+        add(&storage, "sprint-8", "sess-2", None, &AddOptions::default()).unwrap();
+
+        let result = clone_label(&storage, "sprint-7", "sprint-8", true);
+        assert!(result.is_err());
+
+        let store = storage.load().unwrap();
+        let dest = store.get_label("sprint-8").unwrap();
+        assert_eq!(dest.sessions.len(), 1);
+        assert_eq!(dest.sessions[0].session_id, "sess-2");
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_clone_missing_source_returns_error() {
+        let (storage, path) = create_test_storage("clone-missing-source");
+
+        let result = clone_label(&storage, "missing", "new", false);
+        assert!(result.is_err());
+
+        cleanup(&path);
+    }
+
+    // ==================== Import Command Tests ====================
+
+    fn write_import_file(name: &str, store: &Store) -> PathBuf {
+        let path = temp_path(name);
+        Storage::with_path(path.clone()).save(store).unwrap();
+        path
+    }
+
+    fn create_import_store() -> Store {
+        let mut store = Store::new();
+        let mut label = Label::new(Some("Theirs".to_string()));
+        label.add_session(create_test_session_with_time("t1", "/t", None, 2024, 1, 1));
+        store.labels.insert("auth".to_string(), label);
+        store.labels.insert("billing".to_string(), Label::new(None));
+        store
+    }
+
+    #[test]
+    fn test_import_adds_labels() {
+        let (storage, path) = create_test_storage("import-adds");
+        let file = write_import_file("import-adds-src", &create_import_store());
+
+        import(&storage, &file, false, false, None, KeepOnConflict::Newer).unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.labels.len(), 2);
+        assert_eq!(store.get_label("auth").unwrap().sessions.len(), 1);
+
+        cleanup(&path);
+        cleanup(&file);
+    }
+
+    /// A directory of flat-layout label files, as `(file name, label)` pairs.
+    fn write_label_dir(name: &str, files: &[(&str, Label)]) -> PathBuf {
+        let dir = temp_path(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file_name, label) in files {
+            fs::write(dir.join(file_name), serde_json::to_string(label).unwrap()).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_import_from_label_directory() {
+        let (storage, path) = create_test_storage("import-dir");
+        add(&storage, "auth", "mine", None, &AddOptions::default()).unwrap();
+
+        let mut auth = Label::new(Some("Theirs".to_string()));
+        auth.add_session(create_test_session_with_time("t1", "/t", None, 2024, 1, 1));
+        let mut encoded = Label::new(None);
+        encoded.add_session(create_test_session_with_time("t2", "/t", None, 2024, 1, 2));
+        let dir = write_label_dir(
+            "import-dir-src",
+            &[
+                ("auth.json", auth),
+                // "alice/fix #1" with `/`, space and `#` percent-encoded
+                ("alice%2Ffix%20%231.json", encoded),
+            ],
+        );
+        fs::write(dir.join("README.txt"), "not a label").unwrap();
+
+        import(&storage, &dir, false, false, None, KeepOnConflict::Newer).unwrap();
+
+        let store = storage.load().unwrap();
+        let mut names: Vec<&String> = store.labels.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["alice/fix #1", "auth"]);
+        // The existing label is merged into, as with a file import
+        assert_eq!(session_ids_of(&store, "auth"), vec!["mine", "t1"]);
+        assert_eq!(
+            store.get_label("alice/fix #1").unwrap().sessions[0].session_id,
+            "t2"
+        );
+
+        cleanup(&path);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_from_directory_overwrite_and_empty() {
+        let (storage, path) = create_test_storage("import-dir-overwrite");
+        add(&storage, "auth", "mine", None, &AddOptions::default()).unwrap();
+
+        let mut auth = Label::new(None);
+        auth.add_session(create_test_session_with_time("t1", "/t", None, 2024, 1, 1));
+        let dir = write_label_dir("import-dir-overwrite-src", &[("auth.json", auth)]);
+        import(&storage, &dir, true, false, None, KeepOnConflict::Newer).unwrap();
+        assert_eq!(session_ids_of(&storage.load().unwrap(), "auth"), vec!["t1"]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let empty = write_label_dir("import-dir-empty-src", &[]);
+        assert!(import(&storage, &empty, false, false, None, KeepOnConflict::Newer).is_err());
+        fs::remove_dir_all(&empty).unwrap();
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_import_missing_file_returns_error() {
+        let (storage, path) = create_test_storage("import-missing");
+
+        let result = import(
+            &storage,
+            &temp_path("import-missing-src"),
+            false,
+            false,
+            None,
+            KeepOnConflict::Newer,
+        );
+        assert!(result.is_err());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_import_with_prefix() {
+        let (storage, path) = create_test_storage("import-prefix");
+        let file = write_import_file("import-prefix-src", &create_import_store());
+
+        add(&storage, "auth", "mine", None, &AddOptions::default()).unwrap();
+        import(
+            &storage,
+            &file,
+            false,
+            false,
+            Some("alice/"),
+            KeepOnConflict::Newer,
+        )
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        let mut names: Vec<&String> = store.labels.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["alice/auth", "alice/billing", "auth"]);
+        assert_eq!(store.get_label("auth").unwrap().sessions.len(), 1);
+        assert_eq!(
+            store.get_label("auth").unwrap().sessions[0].session_id,
+            "mine"
+        );
+
+        cleanup(&path);
+        cleanup(&file);
+    }
+
+    #[test]
+    fn test_import_with_prefix_collision_merges_or_overwrites() {
+        let (storage, path) = create_test_storage("import-prefix-collision");
+        let file = write_import_file("import-prefix-collision-src", &create_import_store());
+
+        add(&storage, "alice/auth", "mine", None, &AddOptions::default()).unwrap();
+
+        import(
+            &storage,
+            &file,
+            false,
+            false,
+            Some("alice/"),
+            KeepOnConflict::Newer,
+        )
+        .unwrap();
+        let store = storage.load().unwrap();
+        assert_eq!(store.get_label("alice/auth").unwrap().sessions.len(), 2);
+
+        import(
+            &storage,
+            &file,
+            true,
+            false,
+            Some("alice/"),
+            KeepOnConflict::Newer,
+        )
+        .unwrap();
+        let store = storage.load().unwrap();
+        let label = store.get_label("alice/auth").unwrap();
+        assert_eq!(label.sessions.len(), 1);
+        assert_eq!(label.sessions[0].session_id, "t1");
+
+        cleanup(&path);
+        cleanup(&file);
+    }
+
+    #[test]
+    fn test_prefix_labels_renames_all() {
+        let prefixed = prefix_labels(create_import_store(), "team/");
+
+        let mut names: Vec<&String> = prefixed.labels.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["team/auth", "team/billing"]);
+    }
+
+    // ==================== Export Command Tests ====================
+
+    #[test]
+    fn test_export_json_reimports() {
+        let (storage, path) = create_test_storage("export-json");
+        let file = temp_path("export-json-out");
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+        config_set(&storage, "checksum", "true").unwrap();
+        export(&storage, Some(&file), ExportFormat::Json, None, false).unwrap();
+
+        let exported = Storage::with_path(file.clone()).load().unwrap();
+        assert_eq!(exported.get_label("my-label").unwrap().sessions.len(), 1);
+        assert!(!exported.settings.checksum);
+
+        cleanup(&path);
+        cleanup(&file);
+    }
+
+    #[test]
+    fn test_export_single_label_reimports() {
+        let (storage, path) = create_test_storage("export-label");
+        let file = temp_path("export-label-out");
+        add(&storage, "shared", "sess-1", None, &AddOptions::default()).unwrap();
+        add(&storage, "shared", "sess-2", None, &AddOptions::default()).unwrap();
+        add(&storage, "private", "sess-3", None, &AddOptions::default()).unwrap();
+        alias_add(&storage, "sh", "shared", false).unwrap();
+
+        export(&storage, Some(&file), ExportFormat::Json, Some("sh"), false).unwrap();
+
+        let exported = Storage::with_path(file.clone()).load().unwrap();
+        assert_eq!(exported.labels.len(), 1);
+        assert_eq!(exported.labels["shared"].sessions.len(), 2);
+        assert!(exported.settings.aliases.is_empty());
+
+        let (other, other_path) = create_test_storage("export-label-dest");
+        import(&other, &file, false, false, None, KeepOnConflict::Newer).unwrap();
+        let store = other.load().unwrap();
+        assert_eq!(store.labels.len(), 1);
+        assert_eq!(store.get_label("shared").unwrap().sessions.len(), 2);
+
+        cleanup(&path);
+        cleanup(&other_path);
+        cleanup(&file);
+    }
+
+    #[test]
+    fn test_export_missing_label_errors() {
+        let (storage, path) = create_test_storage("export-label-missing");
+        let file = temp_path("export-label-missing-out");
+
+        assert!(export(
+            &storage,
+            Some(&file),
+            ExportFormat::Json,
+            Some("nope"),
+            false
+        )
+        .is_err());
+        assert!(!file.exists());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_export_csv_escapes_special_characters() {
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.add_session(create_test_session_with_time(
+            "sess-1",
+            "/path, with comma",
+            Some("Line1\nLine2 \"quoted\", 'single'"),
+            2024,
+            1,
+            1,
+        ));
+        label.add_session(create_test_session_with_time(
+            "sess-2", "/plain", None, 2024, 2, 1,
+        ));
+        store.labels.insert("label,one".to_string(), label);
+
+        let output = render_export(&store, ExportFormat::Csv).unwrap();
+
+        let mut reader = csv::Reader::from_reader(output.as_bytes());
+        assert_eq!(
+            reader.headers().unwrap(),
+            vec!["label", "session_id", "path", "created_at", "description"]
+        );
+        let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][0], "label,one");
+        assert_eq!(&rows[0][1], "sess-1");
+        assert_eq!(&rows[0][2], "/path, with comma");
+        assert_eq!(&rows[0][3], "2024-01-01T12:00:00+00:00");
+        assert_eq!(&rows[0][4], "Line1\nLine2 \"quoted\", 'single'");
+        assert_eq!(&rows[1][1], "sess-2");
+        assert_eq!(&rows[1][4], "");
+    }
+
+    #[test]
+    fn test_anonymize_store_scrubs_paths_and_descriptions() {
+        let mut store = Store::new();
+        let mut label = Label::new(Some("Client project".to_string()));
+        let mut first = create_test_session_with_time(
+            "sess-1",
+            "/home/me/acme-secret",
+            Some("Fix the payroll export"),
+            2024,
+            1,
+            1,
+        );
+        first
+            .meta
+            .insert("ticket".to_string(), "ACME-42".to_string());
+        label.add_session(first);
+        label.add_session(create_test_session_with_time(
+            "sess-2",
+            "/home/me/acme-secret",
+            None,
+            2024,
+            2,
+            1,
+        ));
+        label.add_session(create_test_session_with_time(
+            "sess-3",
+            "/srv/other",
+            None,
+            2024,
+            3,
+            1,
+        ));
+        store.labels.insert("acme".to_string(), label);
+        store.labels.insert("empty".to_string(), Label::new(None));
+
+        let anonymized = anonymize_store(&store);
+
+        assert_eq!(anonymized.labels.len(), 2);
+        let label = &anonymized.labels["acme"];
+        assert_eq!(label.description, None);
+        assert_eq!(label.sessions.len(), 3);
+        for (before, after) in store.labels["acme"].sessions.iter().zip(&label.sessions) {
+            assert_eq!(after.session_id, before.session_id);
+            assert_eq!(after.created_at, before.created_at);
+            assert_eq!(after.description, None);
+            assert!(after.path.starts_with("/anonymized/"));
+            assert!(!after.path.contains("acme"));
+        }
+        // Same directory, same placeholder; different directory, different one
+        assert_eq!(label.sessions[0].path, label.sessions[1].path);
+        assert_ne!(label.sessions[0].path, label.sessions[2].path);
+        assert_eq!(label.sessions[0].meta["ticket"], "");
+        // The original is untouched
+        assert_eq!(
+            store.labels["acme"].sessions[0].path,
+            "/home/me/acme-secret"
+        );
+    }
+
+    #[test]
+    fn test_export_csv_empty_store_has_header_only() {
+        let output = render_export(&Store::new(), ExportFormat::Csv).unwrap();
+
+        assert_eq!(output, "label,session_id,path,created_at,description\n");
+    }
+
+    // ==================== Search Command Tests ====================
+
+    fn create_search_store() -> Store {
+        let mut store = Store::new();
+
+        let mut auth = Label::new(Some("OAuth login bugs".to_string()));
+        auth.add_session(create_test_session_with_time(
+            "a1",
+            "/work/app",
+            Some("Investigate token refresh"),
+            2024,
+            1,
+            1,
+        ));
+        auth.add_session(create_test_session_with_time(
+            "a2",
+            "/work/app",
+            None,
+            2024,
+            2,
+            1,
+        ));
+
+        let mut billing = Label::new(None);
+        billing.add_session(create_test_session_with_time(
+            "b1",
+            "/work/billing",
+            Some("Stripe webhooks, unrelated to AUTH header parsing"),
+            2024,
+            3,
+            1,
+        ));
+        billing.add_session(create_test_session_with_time(
+            "auth-fix-7",
+            "/work/billing",
+            None,
+            2024,
+            4,
+            1,
+        ));
+
+        store.labels.insert("auth".to_string(), auth);
+        store.labels.insert("billing".to_string(), billing);
+        store
+    }
+
+    fn hit_ids(hits: &[(&String, &Session)]) -> Vec<String> {
+        hits.iter().map(|(_, s)| s.session_id.clone()).collect()
+    }
+
+    #[test]
+    fn test_search_full_mode_matches_descriptions_and_paths() {
+        let store = create_search_store();
+
+        let hits = search_sessions(&store, "auth", &SearchOptions::default());
+        assert_eq!(hit_ids(&hits), vec!["a2", "a1", "auth-fix-7", "b1"]);
+
+        let hits = search_sessions(&store, "TOKEN", &SearchOptions::default());
+        assert_eq!(hit_ids(&hits), vec!["a1"]);
+
+        let hits = search_sessions(&store, "/work/billing", &SearchOptions::default());
+        assert_eq!(hit_ids(&hits), vec!["auth-fix-7", "b1"]);
+    }
+
+    #[test]
+    fn test_search_names_only_skips_description_hits() {
+        let store = create_search_store();
+        let options = SearchOptions {
+            names_only: true,
+            ..Default::default()
+        };
+
+        let hits = search_sessions(&store, "auth", &options);
+        assert_eq!(hit_ids(&hits), vec!["a2", "a1", "auth-fix-7"]);
+
+        assert!(search_sessions(&store, "token", &options).is_empty());
+        assert!(search_sessions(&store, "/work", &options).is_empty());
+        assert_eq!(
+            hit_ids(&search_sessions(&store, "token", &SearchOptions::default())),
+            vec!["a1"]
+        );
+    }
+
+    #[test]
+    fn test_search_max_desc_len_limits_scanned_text() {
+        let store = create_search_store();
+        let options = SearchOptions {
+            max_desc_len: Some(10),
+            ..Default::default()
+        };
+
+        assert!(search_sessions(&store, "webhooks", &options).is_empty());
+        assert_eq!(
+            hit_ids(&search_sessions(&store, "stripe", &options)),
+            vec!["b1"]
+        );
+    }
+
+    #[test]
+    fn test_search_in_restricts_to_matching_labels() {
+        let store = create_search_store();
+
+        // "auth" hits sessions in both labels without a scope
+        let options = SearchOptions {
+            labels: vec!["billing".to_string()],
+            ..Default::default()
+        };
+        let hits = search_sessions(&store, "auth", &options);
+        assert_eq!(hit_ids(&hits), vec!["auth-fix-7", "b1"]);
+        assert!(hits.iter().all(|(label, _)| *label == "billing"));
+
+        let options = SearchOptions {
+            labels: vec!["au*".to_string(), "nope".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            hit_ids(&search_sessions(&store, "auth", &options)),
+            vec!["a2", "a1"]
+        );
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("auth", "auth"));
+        assert!(!glob_match("auth", "auth-2"));
+        assert!(glob_match("auth*", "auth-2"));
+        assert!(glob_match("*-2", "auth-2"));
+        assert!(glob_match("a*h*2", "auth-2"));
+        assert!(glob_match("TICKET-???", "TICKET-123"));
+        assert!(!glob_match("TICKET-???", "TICKET-12"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn test_search_command_returns_ok() {
+        let (storage, path) = create_test_storage("search");
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+
+        assert!(search(&storage, "sess", &SearchOptions::default()).is_ok());
+        assert!(search(&storage, "nothing", &SearchOptions::default()).is_ok());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_search_count_only_matches_detailed_hits() {
+        let (storage, path) = create_test_storage("search-count");
+        storage.save(&create_search_store()).unwrap();
+
+        let options = SearchOptions {
+            count_only: true,
+            ..Default::default()
+        };
+        assert!(search(&storage, "auth", &options).is_ok());
+
+        // --count-only prints the length of the same scan the listing uses
+        let store = storage.load().unwrap();
+        let detailed = search_sessions(&store, "auth", &SearchOptions::default());
+        let counted = search_sessions(&store, "auth", &options);
+        assert_eq!(counted.len(), detailed.len());
+        assert_eq!(counted.len(), 4);
+
+        cleanup(&path);
+    }
+
+    // ==================== Dedup Command Tests ====================
+
+    fn create_duplicate_store(storage: &Storage) {
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.add_session(create_test_session_with_time(
+            "dup",
+            "/old",
+            Some("Old notes"),
+            2024,
+            1,
+            1,
+        ));
+        label.add_session(create_test_session_with_time(
+            "dup", "/new", None, 2024, 6, 1,
+        ));
+        store.labels.insert("my-label".to_string(), label);
+        storage.save(&store).unwrap();
+    }
+
+    #[test]
+    fn test_dedup_keeps_newest() {
+        let (storage, path) = create_test_storage("dedup-newest");
+        create_duplicate_store(&storage);
+
+        dedup(&storage, None, false).unwrap();
+
+        let store = storage.load().unwrap();
+        let sessions = &store.get_label("my-label").unwrap().sessions;
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].path, "/new");
+        assert_eq!(sessions[0].description, None);
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_dedup_merge_keeps_description() {
+        let (storage, path) = create_test_storage("dedup-merge");
+        create_duplicate_store(&storage);
+
+        dedup(&storage, Some("my-label"), true).unwrap();
+
+        let store = storage.load().unwrap();
+        let sessions = &store.get_label("my-label").unwrap().sessions;
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].path, "/new");
+        assert_eq!(sessions[0].description, Some("Old notes".to_string()));
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_dedup_nonexistent_label_returns_error() {
+        let (storage, path) = create_test_storage("dedup-missing");
+
+        assert!(dedup(&storage, Some("nonexistent"), false).is_err());
+
+        cleanup(&path);
+    }
+
+    // ==================== Browse Tests ====================
+
+    fn create_browse_store() -> Store {
+        let mut store = Store::new();
+        let mut work = Label::new(None);
+        work.add_session(create_test_session_with_time(
+            "old", "/work", None, 2024, 1, 1,
+        ));
+        work.add_session(create_test_session_with_time(
+            "new",
+            "/work",
+            Some("Latest"),
+            2024,
+            2,
+            1,
+        ));
+        store.labels.insert("work".to_string(), work);
+        store.labels.insert("empty".to_string(), Label::new(None));
+        store
+    }
+
+    fn sessions_screen(label: &str) -> BrowseScreen {
+        BrowseScreen::Sessions {
+            label: label.to_string(),
+        }
+    }
+
+    fn session_screen(label: &str, session_id: &str) -> BrowseScreen {
+        BrowseScreen::Session {
+            label: label.to_string(),
+            session_id: session_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_browse_labels_menu() {
+        let store = create_browse_store();
+
+        assert_eq!(
+            browse_menu(&store, &BrowseScreen::Labels, DisplayZone::Utc),
+            vec![
+                BrowseItem::Label {
+                    name: "empty".to_string(),
+                    sessions: 0
+                },
+                BrowseItem::Label {
+                    name: "work".to_string(),
+                    sessions: 2
+                },
+                BrowseItem::Quit,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_browse_sessions_menu_newest_first() {
+        let store = create_browse_store();
+
+        let items = browse_menu(&store, &sessions_screen("work"), DisplayZone::Utc);
+        let ids: Vec<&str> = items
+            .iter()
+            .filter_map(|item| match item {
+                BrowseItem::Session { id, .. } => Some(id.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ids, vec!["new", "old"]);
+        assert_eq!(items.last(), Some(&BrowseItem::Back));
+
+        assert_eq!(
+            browse_menu(&store, &sessions_screen("empty"), DisplayZone::Utc),
+            vec![BrowseItem::Back]
+        );
+    }
+
+    #[test]
+    fn test_browse_sessions_menu_uses_zone() {
+        let store = create_browse_store();
+        let zone = DisplayZone::parse("Asia/Tokyo").unwrap();
+
+        let items = browse_menu(&store, &sessions_screen("work"), zone);
+
+        assert_eq!(
+            items[0],
+            BrowseItem::Session {
+                id: "new".to_string(),
+                summary: "(2024-02-01 21:00) - Latest".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_browse_session_menu() {
+        let store = create_browse_store();
+
+        assert_eq!(
+            browse_menu(&store, &session_screen("work", "old"), DisplayZone::Utc),
+            vec![
+                BrowseItem::Resume,
+                BrowseItem::Details,
+                BrowseItem::Remove,
+                BrowseItem::Back,
+            ]
+        );
+        // A session removed in the meantime only offers Back
+        assert_eq!(
+            browse_menu(&store, &session_screen("work", "gone"), DisplayZone::Utc),
+            vec![BrowseItem::Back]
+        );
+        assert_eq!(
+            browse_menu(&store, &sessions_screen("missing"), DisplayZone::Utc),
+            vec![BrowseItem::Back]
+        );
+    }
+
+    #[test]
+    fn test_browse_transitions_forward() {
+        let store = create_browse_store();
+
+        let labels = browse_menu(&store, &BrowseScreen::Labels, DisplayZone::Utc);
+        assert_eq!(
+            browse_next(&BrowseScreen::Labels, &labels[1]),
+            BrowseStep::Show(sessions_screen("work"))
+        );
+
+        let sessions = browse_menu(&store, &sessions_screen("work"), DisplayZone::Utc);
+        assert_eq!(
+            browse_next(&sessions_screen("work"), &sessions[0]),
+            BrowseStep::Show(session_screen("work", "new"))
+        );
+
+        let screen = session_screen("work", "new");
+        assert_eq!(
+            browse_next(&screen, &BrowseItem::Resume),
+            BrowseStep::Resume {
+                label: "work".to_string(),
+                session_id: "new".to_string()
+            }
+        );
+        assert_eq!(
+            browse_next(&screen, &BrowseItem::Details),
+            BrowseStep::Details {
+                label: "work".to_string(),
+                session_id: "new".to_string()
+            }
+        );
+        assert_eq!(
+            browse_next(&screen, &BrowseItem::Remove),
+            BrowseStep::Remove {
+                label: "work".to_string(),
+                session_id: "new".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_browse_transitions_back_and_quit() {
+        assert_eq!(
+            browse_next(&session_screen("work", "new"), &BrowseItem::Back),
+            BrowseStep::Show(sessions_screen("work"))
+        );
+        assert_eq!(
+            browse_next(&sessions_screen("work"), &BrowseItem::Back),
+            BrowseStep::Show(BrowseScreen::Labels)
+        );
+        assert_eq!(
+            browse_next(&BrowseScreen::Labels, &BrowseItem::Back),
+            BrowseStep::Quit
+        );
+        assert_eq!(
+            browse_next(&BrowseScreen::Labels, &BrowseItem::Quit),
+            BrowseStep::Quit
+        );
+    }
+
+    #[test]
+    fn test_browse_item_text() {
+        assert_eq!(
+            BrowseItem::Label {
+                name: "work".to_string(),
+                sessions: 1
+            }
+            .text(),
+            "work (1 session)"
+        );
+        assert_eq!(BrowseItem::Back.text(), "← Back");
+    }
+
+    // ==================== Stats Tests ====================
+
+    #[test]
+    fn test_label_stats_figures() {
+        let mut label = Label::new(Some("Fix the 🚀 launcher".to_string()));
+        label.add_session(create_test_session_with_time("b", "/p", None, 2024, 3, 1));
+        label.add_session(create_test_session_with_time("a", "/p", None, 2024, 1, 15));
+        label.add_session(create_test_session_with_time("c", "/p", None, 2024, 2, 1));
+
+        let stats = label_stats(&label);
+
+        assert_eq!(stats.sessions, 3);
+        assert_eq!(
+            stats.oldest,
+            Some(Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap())
+        );
+        assert_eq!(
+            stats.newest,
+            Some(Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap())
+        );
+        assert_eq!(stats.description_length, 18);
+    }
+
+    #[test]
+    fn test_label_stats_empty_label() {
+        let stats = label_stats(&Label::new(None));
+
+        assert_eq!(
+            stats,
+            LabelStats {
+                sessions: 0,
+                oldest: None,
+                newest: None,
+                resumes: 0,
+                description_length: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_label_stats_json_shape() {
+        let json = serde_json::to_value(label_stats(&Label::new(None))).unwrap();
+
+        assert_eq!(json["sessions"], 0);
+        assert!(json["oldest"].is_null());
+        assert_eq!(json["description_length"], 0);
+    }
+
+    #[test]
+    fn test_store_stats_spans_labels() {
+        let mut store = create_browse_store();
+        store
+            .labels
+            .insert("also-empty".to_string(), Label::new(None));
+
+        let stats = store_stats(&store);
+
+        assert_eq!(stats.labels, 3);
+        assert_eq!(stats.empty_labels, 2);
+        assert_eq!(stats.sessions, 2);
+        assert_eq!(
+            stats.oldest,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap())
+        );
+        assert_eq!(
+            stats.newest,
+            Some(Utc.with_ymd_and_hms(2024, 2, 1, 12, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_format_stats_time_uses_zone() {
+        let at = Utc.with_ymd_and_hms(2024, 2, 1, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            format_stats_time(Some(at), DisplayZone::Utc),
+            "2024-02-01 12:00:00"
+        );
+        assert_eq!(
+            format_stats_time(Some(at), DisplayZone::parse("Asia/Tokyo").unwrap()),
+            "2024-02-01 21:00:00"
+        );
+        assert_eq!(format_stats_time(None, DisplayZone::Utc), "-");
+    }
+
+    #[test]
+    fn test_stats_command_missing_label_returns_error() {
+        let (storage, path) = create_test_storage("stats-missing");
+
+        assert!(stats(&storage, Some("nope"), false, false, None).is_err());
+        assert!(stats(&storage, None, false, false, None).is_ok());
+        cleanup(&path);
+    }
+
+    // ==================== Path Stats Tests ====================
+
+    #[test]
+    fn test_path_stats_counts_sessions_and_labels() {
+        let mut store = Store::new();
+        let mut api = Label::new(None);
+        api.add_session(create_test_session_with_time(
+            "a1", "/shared", None, 2024, 1, 1,
+        ));
+        api.add_session(create_test_session_with_time(
+            "a2", "/shared", None, 2024, 1, 2,
+        ));
+        api.add_session(create_test_session_with_time(
+            "a3", "/api", None, 2024, 1, 3,
+        ));
+        store.labels.insert("api".to_string(), api);
+        let mut web = Label::new(None);
+        web.add_session(create_test_session_with_time(
+            "w1", "/shared", None, 2024, 2, 1,
+        ));
+        store.labels.insert("web".to_string(), web);
+
+        let stats = path_stats(&store);
+
+        assert_eq!(
+            stats["/shared"],
+            PathStats {
+                sessions: 3,
+                labels: 2
+            }
+        );
+        assert_eq!(
+            stats["/api"],
+            PathStats {
+                sessions: 1,
+                labels: 1
+            }
+        );
+        let order: Vec<&str> = paths_by_sessions(&stats)
+            .into_iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        assert_eq!(order, vec!["/shared", "/api"]);
+    }
+
+    #[test]
+    fn test_path_stats_empty_store() {
+        assert!(path_stats(&Store::new()).is_empty());
+    }
+
+    // ==================== Count Tests ====================
+
+    fn create_count_store() -> Store {
+        let mut store = Store::new();
+        let mut api = Label::new(None);
+        api.add_session(create_test_session_with_time(
+            "a", "/srv/api", None, 2024, 1, 5,
+        ));
+        api.add_session(create_test_session_with_time(
+            "b", "/srv/web", None, 2024, 1, 20,
+        ));
+        api.add_session(create_test_session_with_time(
+            "c", "/srv/api", None, 2024, 3, 2,
+        ));
+        let mut web = Label::new(None);
+        web.add_session(create_test_session_with_time(
+            "d", "/srv/web", None, 2023, 12, 31,
+        ));
+        store.labels.insert("api".to_string(), api);
+        store.labels.insert("web".to_string(), web);
+        store.labels.insert("empty".to_string(), Label::new(None));
+        store
+    }
+
+    fn counts(pairs: &[(&str, usize)]) -> Vec<(String, usize)> {
+        pairs.iter().map(|(k, n)| (k.to_string(), *n)).collect()
+    }
+
+    #[test]
+    fn test_count_by_label() {
+        let result: Vec<_> = count_sessions(&create_count_store(), CountBy::Label)
+            .into_iter()
+            .collect();
+
+        // Empty labels have no sessions to count, so they don't appear
+        assert_eq!(result, counts(&[("api", 3), ("web", 1)]));
+    }
+
+    #[test]
+    fn test_count_by_path() {
+        let result: Vec<_> = count_sessions(&create_count_store(), CountBy::Path)
+            .into_iter()
+            .collect();
+
+        assert_eq!(result, counts(&[("/srv/api", 2), ("/srv/web", 2)]));
+    }
+
+    #[test]
+    fn test_count_by_month() {
+        let result: Vec<_> = count_sessions(&create_count_store(), CountBy::Month)
+            .into_iter()
+            .collect();
+
+        assert_eq!(
+            result,
+            counts(&[("2023-12", 1), ("2024-01", 2), ("2024-03", 1)])
+        );
+    }
+
+    #[test]
+    fn test_count_empty_store() {
+        assert!(count_sessions(&Store::new(), CountBy::Label).is_empty());
+    }
+
+    // ==================== Resume Tracking Tests ====================
+
+    #[test]
+    fn test_record_resume_bumps_counter() {
+        let (storage, path) = create_test_storage("record-resume");
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+
+        record_resume(&storage, "my-label", "sess-1").unwrap();
+        record_resume(&storage, "my-label", "sess-1").unwrap();
+
+        let store = storage.load().unwrap();
+        let label = store.get_label("my-label").unwrap();
+        assert_eq!(label.sessions[0].resume_count, 2);
+        assert!(label.sessions[0].last_resumed_at.is_some());
+        assert_eq!(label_stats(label).resumes, 2);
+        cleanup(&path);
+    }
+
+    // ==================== Alias Command Tests ====================
+
+    #[test]
+    fn test_alias_add_and_use() {
+        let (storage, path) = create_test_storage("alias-add");
+        add(
+            &storage,
+            "2024-Q3-platform-migration",
+            "s1",
+            None,
+            &AddOptions::default(),
+        )
+        .unwrap();
+
+        alias_add(&storage, "mig", "2024-Q3-platform-migration", false).unwrap();
+        add(&storage, "mig", "s2", None, &AddOptions::default()).unwrap();
+        describe(
+            &storage,
+            "mig",
+            Some("Migration".to_string()),
+            &DescribeOptions::default(),
+        )
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.labels.len(), 1);
+        let label = store.get_label("2024-Q3-platform-migration").unwrap();
+        assert_eq!(label.sessions.len(), 2);
+        assert_eq!(label.description, Some("Migration".to_string()));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_alias_add_chains_to_real_label() {
+        let (storage, path) = create_test_storage("alias-chain");
+        add(&storage, "long-name", "s1", None, &AddOptions::default()).unwrap();
+
+        alias_add(&storage, "ln", "long-name", false).unwrap();
+        alias_add(&storage, "l", "ln", false).unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.settings.aliases["l"], "long-name");
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_alias_add_missing_label_returns_error() {
+        let (storage, path) = create_test_storage("alias-missing");
+
+        assert!(alias_add(&storage, "mig", "nope", false).is_err());
+        assert!(storage.load().unwrap().settings.aliases.is_empty());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_alias_remove() {
+        let (storage, path) = create_test_storage("alias-remove");
+        add(&storage, "long-name", "s1", None, &AddOptions::default()).unwrap();
+        alias_add(&storage, "ln", "long-name", false).unwrap();
+
+        alias_remove(&storage, "ln").unwrap();
+
+        assert!(storage.load().unwrap().settings.aliases.is_empty());
+        assert!(alias_remove(&storage, "ln").is_err());
+        assert!(alias_list(&storage).is_ok());
+        cleanup(&path);
+    }
+
+    // ==================== Sweep Tests ====================
+
+    fn create_sweep_store() -> Store {
+        let mut store = Store::new();
+        let mut mixed = Label::new(None);
+        mixed.add_session(create_test_session_with_time(
+            "m-old", "/p", None, 2023, 1, 1,
+        ));
+        mixed.add_session(create_test_session_with_time(
+            "m-new", "/p", None, 2024, 6, 1,
+        ));
+        let mut stale = Label::new(None);
+        stale.add_session(create_test_session_with_time(
+            "s-old1", "/p", None, 2022, 1, 1,
+        ));
+        stale.add_session(create_test_session_with_time(
+            "s-old2", "/p", None, 2023, 3, 1,
+        ));
+        let mut fresh = Label::new(None);
+        fresh.add_session(create_test_session_with_time(
+            "f-new", "/p", None, 2024, 5, 1,
+        ));
+        store.labels.insert("mixed".to_string(), mixed);
+        store.labels.insert("stale".to_string(), stale);
+        store.labels.insert("fresh".to_string(), fresh);
+        store.labels.insert("empty".to_string(), Label::new(None));
+        store
+    }
+
+    #[test]
+    fn test_sweep_sessions_across_labels() {
+        let mut store = create_sweep_store();
+        let cutoff = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        let report = sweep_sessions(&mut store, cutoff, false);
+
+        assert_eq!(
+            report.removed,
+            vec![("mixed".to_string(), 1), ("stale".to_string(), 2)]
+        );
+        assert!(report.pruned.is_empty());
+        assert_eq!(store.labels.len(), 4);
+        assert_eq!(
+            store.get_label("mixed").unwrap().sessions[0].session_id,
+            "m-new"
+        );
+        assert!(store.get_label("stale").unwrap().sessions.is_empty());
+        assert_eq!(store.get_label("fresh").unwrap().sessions.len(), 1);
+    }
+
+    #[test]
+    fn test_sweep_sessions_prune_empty() {
+        let mut store = create_sweep_store();
+        let cutoff = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        let report = sweep_sessions(&mut store, cutoff, true);
+
+        assert_eq!(report.pruned, vec!["stale".to_string()]);
+        assert!(store.get_label("stale").is_none());
+        // Labels that were already empty are not swept away
+        assert!(store.get_label("empty").is_some());
+        assert!(store.get_label("mixed").is_some());
+    }
+
+    #[test]
+    fn test_sweep_dry_run_changes_nothing() {
+        let (mut storage, path) = create_test_storage("sweep-dry-run");
+        storage.save(&create_sweep_store()).unwrap();
+
+        storage.set_dry_run(true);
+        sweep(&storage, chrono::Duration::days(1), true, None).unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.labels.len(), 4);
+        assert_eq!(store.get_label("stale").unwrap().sessions.len(), 2);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_sweep_out_of_range_duration_is_error() {
+        let (storage, path) = create_test_storage("sweep-out-of-range");
+        storage.save(&create_sweep_store()).unwrap();
+
+        assert!(sweep(&storage, chrono::Duration::weeks(20_000_000), true, None).is_err());
+
+        assert_eq!(storage.load().unwrap().labels.len(), 4);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_sweep_command_removes_old_sessions() {
+        let (storage, path) = create_test_storage("sweep");
+        storage.save(&create_sweep_store()).unwrap();
+
+        sweep(&storage, chrono::Duration::days(1), true, None).unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.labels.len(), 1);
+        assert!(store.get_label("empty").is_some());
+        cleanup(&path);
+    }
+
+    // ==================== History Command Tests ====================
+
+    #[test]
+    fn test_history_command_reads_log() {
+        let dir = temp_path("history-cmd");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let storage = Storage::with_path(dir.join("data.json"));
+
+        assert!(history(&storage, None, 10, false, None).is_ok());
+
+        history::append(
+            &storage.history_path(),
+            &HistoryEntry {
+                at: Utc::now(),
+                label: "work".to_string(),
+                session_id: "sess-1".to_string(),
+                dir: "/work".to_string(),
+                exit_code: Some(0),
+            },
+        )
+        .unwrap();
+
+        assert!(history(&storage, Some("work"), 10, false, None).is_ok());
+        assert!(history(&storage, None, 10, true, None).is_ok());
+        assert_eq!(history::read(&storage.history_path()).unwrap().len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_log_resume_skips_dry_run() {
+        let dir = temp_path("history-dry-run");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut storage = Storage::with_path(dir.join("data.json"));
+        let entry = HistoryEntry {
+            at: Utc::now(),
+            label: "work".to_string(),
+            session_id: "sess-1".to_string(),
+            dir: "/work".to_string(),
+            exit_code: Some(0),
+        };
+
+        storage.set_dry_run(true);
+        log_resume(&storage, &entry);
+        assert!(!storage.history_path().exists());
+
+        storage.set_dry_run(false);
+        log_resume(&storage, &entry);
+        assert_eq!(history::read(&storage.history_path()).unwrap().len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // ==================== Validate Import Tests ====================
+
+    fn write_export(name: &str, store: &Store) -> PathBuf {
+        let file = temp_path(name);
+        fs::write(&file, serde_json::to_string(store).unwrap()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_validate_import_clean_export_passes() {
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.add_session(create_test_session_with_time(
+            "s1",
+            "/work/app",
+            None,
+            2024,
+            1,
+            1,
+        ));
+        store.labels.insert("ok".to_string(), label);
+        let file = write_export("validate-clean", &store);
+
+        assert!(validate_import_with(&file, false, |_| true).is_ok());
+        cleanup(&file);
+    }
+
+    #[test]
+    fn test_validate_import_dirty_export_fails() {
+        let mut store = Store::new();
+        store.labels.insert("empty".to_string(), Label::new(None));
+        let mut label = Label::new(None);
+        label.add_session(create_test_session_with_time(
+            "s1",
+            "/work/app",
+            None,
+            2024,
+            1,
+            1,
+        ));
+        label.add_session(create_test_session_with_time(
+            "s1",
+            "/work/app",
+            None,
+            2024,
+            1,
+            1,
+        ));
+        store.labels.insert("dupes".to_string(), label);
+        let file = write_export("validate-dirty", &store);
+
+        let err = validate_import_with(&file, false, |_| true).unwrap_err();
+        assert_eq!(err.to_string(), "2 problems found");
+        cleanup(&file);
+    }
+
+    #[test]
+    fn test_validate_import_unparseable_export_fails() {
+        let file = temp_path("validate-garbage");
+        fs::write(&file, "{ not json").unwrap();
+
+        assert!(validate_import_with(&file, false, |_| true).is_err());
+        cleanup(&file);
+    }
+
+    #[test]
+    fn test_validate_import_strict_rejects_unknown_fields() {
+        let file = temp_path("validate-strict.json");
+        fs::write(
+            &file,
+            r#"{"labels":{"work":{"descriptn":"typo","sessions":[{
+                "session_id":"s1","path":"/work/app","created_at":"2024-01-01T00:00:00Z"
+            }]}}}"#,
+        )
+        .unwrap();
+
+        assert!(validate_import_with(&file, false, |_| true).is_ok());
+        assert!(validate_import_with(&file, true, |_| true).is_err());
+        cleanup(&file);
+    }
+
+    #[test]
+    fn test_validate_import_leaves_store_untouched() {
+        let (storage, path) = create_test_storage("validate-untouched");
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.add_session(create_test_session_with_time(
+            "s1",
+            "/work/app",
+            None,
+            2024,
+            1,
+            1,
+        ));
+        store.labels.insert("incoming".to_string(), label);
+        let file = write_export("validate-untouched-src", &store);
+
+        validate_import_with(&file, false, |_| true).unwrap();
+        assert!(storage.load().unwrap().labels.is_empty());
+        cleanup(&file);
+        cleanup(&path);
+    }
+
+    // ==================== Sync Tests ====================
+
+    fn id_set(ids: &[&str]) -> BTreeSet<String> {
+        ids.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_sync_plan_skips_recorded_sessions() {
+        let mut label = Label::new(None);
+        label.add_session(create_test_session_with_time("b", "/p", None, 2024, 1, 1));
+
+        let (to_add, skipped) = sync_plan(&id_set(&["c", "a", "b"]), Some(&label));
+        assert_eq!(to_add, vec!["a", "c"]);
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn test_sync_plan_missing_label_adds_everything() {
+        let (to_add, skipped) = sync_plan(&id_set(&["a", "b"]), None);
+        assert_eq!(to_add, vec!["a", "b"]);
+        assert_eq!(skipped, 0);
+    }
+
+    #[test]
+    fn test_sync_from_dir_adds_new_sessions_once() {
+        let (storage, path) = create_test_storage("sync");
+        let project_dir = temp_path("sync-project");
+        let _ = fs::remove_dir_all(&project_dir);
+        fs::create_dir_all(&project_dir).unwrap();
+        fs::write(project_dir.join("sess-1.jsonl"), "").unwrap();
+        fs::write(project_dir.join("sess-2.jsonl"), "").unwrap();
+        fs::write(project_dir.join("notes.txt"), "").unwrap();
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+
+        let cwd = Path::new("/work/app");
+        sync_from_dir(&storage, "my-label", &project_dir, cwd).unwrap();
+        sync_from_dir(&storage, "my-label", &project_dir, cwd).unwrap();
+
+        let store = storage.load().unwrap();
+        let sessions = &store.get_label("my-label").unwrap().sessions;
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[1].session_id, "sess-2");
+        assert_eq!(sessions[1].path, "/work/app");
+        fs::remove_dir_all(&project_dir).unwrap();
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_sync_from_dir_without_sessions_creates_no_label() {
+        let (storage, path) = create_test_storage("sync-empty");
+        let project_dir = temp_path("sync-empty-project");
+        let _ = fs::remove_dir_all(&project_dir);
+
+        let err =
+            sync_from_dir(&storage, "my-label", &project_dir, Path::new("/work")).unwrap_err();
+
+        assert!(
+            err.to_string().contains("No Claude sessions found"),
+            "{}",
+            err
+        );
+        assert!(storage.load().unwrap().get_label("my-label").is_none());
+        assert!(!path.exists());
+        cleanup(&path);
+    }
+
+    // ==================== Remove Command Tests ====================
+
+    fn scratch_storage(name: &str) -> (Storage, PathBuf) {
+        let (storage, path) = create_test_storage(name);
+        for (sid, desc) in [
+            ("s1", "scratch one"),
+            ("s2", "keep me"),
+            ("s3", "scratch two"),
+        ] {
+            add(
+                &storage,
+                "my-label",
+                sid,
+                Some(desc.to_string()),
+                &AddOptions::default(),
+            )
+            .unwrap();
+        }
+        (storage, path)
+    }
+
+    fn remaining_ids(storage: &Storage) -> Vec<String> {
+        storage
+            .load()
+            .unwrap()
+            .get_label("my-label")
+            .unwrap()
+            .sessions
+            .iter()
+            .map(|s| s.session_id.clone())
+            .collect()
+    }
+
+    fn ids(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_remove_listed_reports_found_and_missing() {
+        let mut label = Label::new(None);
+        label.add_session(create_test_session_with_time("s1", "/p", None, 2024, 1, 1));
+        label.add_session(create_test_session_with_time("s2", "/p", None, 2024, 1, 2));
+
+        let (removed, missing) = remove_listed(&mut label, &ids(&["s2", "nope", "s1", "gone"]));
+
+        assert_eq!(removed, ids(&["s2", "s1"]));
+        assert_eq!(missing, ids(&["nope", "gone"]));
+        assert!(label.sessions.is_empty());
+    }
+
+    #[test]
+    fn test_remove_several_sessions_with_some_missing() {
+        let (storage, path) = scratch_storage("remove-several");
+        let options = RemoveOptions {
+            sessions: ids(&["s1", "missing", "s3"]),
+            ..Default::default()
+        };
+
+        remove(&storage, "my-label", None, &options).unwrap();
+
+        assert_eq!(remaining_ids(&storage), ids(&["s2"]));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_several_sessions_all_missing_is_error() {
+        let (storage, path) = scratch_storage("remove-several-none");
+        let options = RemoveOptions {
+            sessions: ids(&["x", "y"]),
+            ..Default::default()
+        };
+
+        let err = remove(&storage, "my-label", None, &options).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "None of the sessions were found in label 'my-label': x, y"
+        );
+        assert_eq!(remaining_ids(&storage), ids(&["s1", "s2", "s3"]));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_match_desc_requires_yes_for_several() {
+        let (storage, path) = scratch_storage("remove-match-desc-yes");
+        let options = RemoveOptions {
+            match_desc: Some("scratch".to_string()),
+            ..Default::default()
+        };
+
+        assert!(remove(&storage, "my-label", None, &options).is_err());
+        assert_eq!(
+            storage
+                .load()
+                .unwrap()
+                .get_label("my-label")
+                .unwrap()
+                .sessions
+                .len(),
+            3
+        );
+
+        let options = RemoveOptions {
+            yes: true,
+            ..options
+        };
+        remove(&storage, "my-label", None, &options).unwrap();
+        let store = storage.load().unwrap();
+        let sessions = &store.get_label("my-label").unwrap().sessions;
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_id, "s2");
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_keep_drops_the_rest() {
+        let (storage, path) = scratch_storage("remove-keep");
+        let options = RemoveOptions {
+            keep: Some("s2".to_string()),
+            ..Default::default()
+        };
+
+        assert!(remove(&storage, "my-label", None, &options).is_err());
+
+        let options = RemoveOptions {
+            yes: true,
+            ..options
+        };
+        remove(&storage, "my-label", None, &options).unwrap();
+        let store = storage.load().unwrap();
+        let sessions = &store.get_label("my-label").unwrap().sessions;
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_id, "s2");
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_keep_unknown_session_errors() {
+        let (storage, path) = scratch_storage("remove-keep-missing");
+        let options = RemoveOptions {
+            keep: Some("nope".to_string()),
+            yes: true,
+            ..Default::default()
+        };
+
+        assert!(remove(&storage, "my-label", None, &options).is_err());
+        assert_eq!(
+            storage
+                .load()
+                .unwrap()
+                .get_label("my-label")
+                .unwrap()
+                .sessions
+                .len(),
+            3
+        );
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_match_desc_single_without_yes() {
+        let (storage, path) = scratch_storage("remove-match-desc-single");
+        let options = RemoveOptions {
+            match_desc: Some("keep".to_string()),
+            ..Default::default()
+        };
+
+        remove(&storage, "my-label", None, &options).unwrap();
+        assert_eq!(
+            storage
+                .load()
+                .unwrap()
+                .get_label("my-label")
+                .unwrap()
+                .sessions
+                .len(),
+            2
+        );
+        cleanup(&path);
+    }
+
+    fn planned_ids(
+        storage: &Storage,
+        session_id: Option<&str>,
+        options: &RemoveOptions,
+    ) -> (bool, Vec<String>) {
+        let store = storage.load().unwrap();
+        let plan = removal_plan(&store, "my-label", session_id, options).unwrap();
+        let ids = plan.sessions.iter().map(|s| s.session_id.clone()).collect();
+        (plan.label, ids)
+    }
+
+    #[test]
+    fn test_removal_plan_covers_every_remove_form() {
+        let (storage, path) = scratch_storage("removal-plan");
+
+        assert_eq!(
+            planned_ids(&storage, None, &RemoveOptions::default()),
+            (true, vec!["s1".into(), "s2".into(), "s3".into()])
+        );
+        assert_eq!(
+            planned_ids(&storage, Some("s2"), &RemoveOptions::default()),
+            (false, vec!["s2".into()])
+        );
+        let all = RemoveOptions {
+            all_sessions: true,
+            ..Default::default()
+        };
+        assert_eq!(planned_ids(&storage, None, &all).1.len(), 3);
+        let listed = RemoveOptions {
+            sessions: vec!["s3".into(), "nope".into(), "s1".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            planned_ids(&storage, None, &listed),
+            (false, vec!["s3".into(), "s1".into()])
+        );
+        let matching = RemoveOptions {
+            match_desc: Some("scratch".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            planned_ids(&storage, None, &matching).1,
+            vec!["s1".to_string(), "s3".to_string()]
+        );
+        let keep = RemoveOptions {
+            keep: Some("s1".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            planned_ids(&storage, None, &keep).1,
+            vec!["s2".to_string(), "s3".to_string()]
+        );
+
+        let store = storage.load().unwrap();
+        assert!(removal_plan(&store, "my-label", Some("nope"), &RemoveOptions::default()).is_err());
+        assert!(removal_plan(&store, "missing", None, &RemoveOptions::default()).is_err());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_dry_run_leaves_store_unchanged_for_every_form() {
+        let (mut storage, path) = scratch_storage("remove-dry-run-forms");
+        storage.set_dry_run(true);
+
+        let forms = [
+            (None, RemoveOptions::default()),
+            (Some("s2"), RemoveOptions::default()),
+            (
+                None,
+                RemoveOptions {
+                    all_sessions: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                None,
+                RemoveOptions {
+                    sessions: vec!["s1".into(), "s3".into()],
+                    ..Default::default()
+                },
+            ),
+            (
+                None,
+                RemoveOptions {
+                    keep: Some("s1".into()),
+                    ..Default::default()
+                },
+            ),
+        ];
+        for (session_id, options) in &forms {
+            remove(&storage, "my-label", *session_id, options).unwrap();
+            assert_eq!(remaining_ids(&storage), vec!["s1", "s2", "s3"]);
+        }
+        // Dry runs still fail on targets that don't exist
+        assert!(remove(
+            &storage,
+            "my-label",
+            Some("nope"),
+            &RemoveOptions::default()
+        )
+        .is_err());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_match_desc_dry_run_needs_no_yes() {
+        let (mut storage, path) = scratch_storage("remove-match-desc-dry-run");
+        storage.set_dry_run(true);
+        let options = RemoveOptions {
+            match_desc: Some("scratch".to_string()),
+            ..Default::default()
+        };
+
+        remove(&storage, "my-label", None, &options).unwrap();
+        assert_eq!(
+            storage
+                .load()
+                .unwrap()
+                .get_label("my-label")
+                .unwrap()
+                .sessions
+                .len(),
+            3
+        );
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_entire_label() {
+        let (storage, path) = create_test_storage("remove-label");
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+
+        let result = remove(&storage, "my-label", None, &RemoveOptions::default());
+        assert!(result.is_ok());
+
+        let store = storage.load().unwrap();
+        assert!(!store.labels.contains_key("my-label"));
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_specific_session() {
+        let (storage, path) = create_test_storage("remove-session");
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+        add(&storage, "my-label", "sess-2", None, &AddOptions::default()).unwrap();
+
+        let result = remove(
+            &storage,
+            "my-label",
+            Some("sess-1"),
+            &RemoveOptions::default(),
+        );
+        assert!(result.is_ok());
+
+        let store = storage.load().unwrap();
+        let label = store.get_label("my-label").unwrap();
+        assert_eq!(label.sessions.len(), 1);
+        assert_eq!(label.sessions[0].session_id, "sess-2");
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_all_sessions_keeps_label() {
+        let (storage, path) = create_test_storage("remove-all-sessions");
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+        add(&storage, "my-label", "sess-2", None, &AddOptions::default()).unwrap();
+        describe(
+            &storage,
+            "my-label",
+            Some("Keep me".to_string()),
+            &DescribeOptions::default(),
+        )
+        .unwrap();
+
+        let options = RemoveOptions {
+            all_sessions: true,
+            ..Default::default()
+        };
+        let result = remove(&storage, "my-label", None, &options);
+        assert!(result.is_ok());
+
+        let store = storage.load().unwrap();
+        let label = store.get_label("my-label").unwrap();
+        assert!(label.sessions.is_empty());
+        assert_eq!(label.description, Some("Keep me".to_string()));
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_all_sessions_nonexistent_label_returns_error() {
+        let (storage, path) = create_test_storage("remove-all-sessions-missing");
+
+        let options = RemoveOptions {
+            all_sessions: true,
+            ..Default::default()
+        };
+        assert!(remove(&storage, "nonexistent", None, &options).is_err());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_nonexistent_label_returns_error() {
+        let (storage, path) = create_test_storage("remove-nonexistent-label");
+
+        let result = remove(&storage, "nonexistent", None, &RemoveOptions::default());
+        assert!(result.is_err());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_nonexistent_session_returns_error() {
+        let (storage, path) = create_test_storage("remove-nonexistent-session");
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+
+        let result = remove(
+            &storage,
+            "my-label",
+            Some("nonexistent"),
+            &RemoveOptions::default(),
+        );
+        assert!(result.is_err());
+
+        cleanup(&path);
+    }
+
+    // ==================== Describe Command Tests ====================
+
+    #[test]
+    fn test_describe_set_description() {
+        let (storage, path) = create_test_storage("describe-set");
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+
+        let result = describe(
+            &storage,
+            "my-label",
+            Some("New description".to_string()),
+            &DescribeOptions::default(),
+        );
+        assert!(result.is_ok());
+
+        let store = storage.load().unwrap();
+        let label = store.get_label("my-label").unwrap();
+        assert_eq!(label.description, Some("New description".to_string()));
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_describe_update_description() {
+        let (storage, path) = create_test_storage("describe-update");
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+        describe(
+            &storage,
+            "my-label",
+            Some("First".to_string()),
+            &DescribeOptions::default(),
+        )
+        .unwrap();
+
+        let result = describe(
+            &storage,
+            "my-label",
+            Some("Updated".to_string()),
+            &DescribeOptions::default(),
+        );
+        assert!(result.is_ok());
+
+        let store = storage.load().unwrap();
+        let label = store.get_label("my-label").unwrap();
+        assert_eq!(label.description, Some("Updated".to_string()));
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_describe_clear_description() {
+        let (storage, path) = create_test_storage("describe-clear");
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+        describe(
+            &storage,
+            "my-label",
+            Some("Has description".to_string()),
+            &DescribeOptions::default(),
+        )
+        .unwrap();
+
+        let result = describe(&storage, "my-label", None, &DescribeOptions::default());
+        assert!(result.is_ok());
+
+        let store = storage.load().unwrap();
+        let label = store.get_label("my-label").unwrap();
+        assert_eq!(label.description, None);
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_describe_records_history_of_label_descriptions() {
+        let (storage, path) = create_test_storage("describe-history");
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+        for text in ["First", "First", "Second"] {
+            describe(
+                &storage,
+                "my-label",
+                Some(text.to_string()),
+                &DescribeOptions::default(),
+            )
+            .unwrap();
+        }
+        // Session descriptions aren't part of the label's history
+        let session_options = DescribeOptions {
+            session: Some("sess-1".to_string()),
+            ..Default::default()
+        };
+        describe(
+            &storage,
+            "my-label",
+            Some("S".to_string()),
+            &session_options,
+        )
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        let history: Vec<&str> = store
+            .get_label("my-label")
+            .unwrap()
+            .description_history
+            .iter()
+            .map(|(_, text)| text.as_str())
+            .collect();
+        assert_eq!(history, vec!["First", "Second"]);
+
+        let history_options = DescribeOptions {
+            history: true,
+            ..Default::default()
+        };
+        assert!(describe(&storage, "my-label", None, &history_options).is_ok());
+        assert!(describe(&storage, "missing", None, &history_options).is_err());
+        // Printing the history changes nothing
+        assert_eq!(
+            storage
+                .load()
+                .unwrap()
+                .get_label("my-label")
+                .unwrap()
+                .description,
+            Some("Second".to_string())
+        );
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_describe_nonexistent_label_returns_error() {
+        let (storage, path) = create_test_storage("describe-nonexistent");
+
+        let result = describe(
+            &storage,
+            "nonexistent",
+            Some("Description".to_string()),
+            &DescribeOptions::default(),
+        );
+        assert!(result.is_err());
+
+        cleanup(&path);
+    }
+
+    // ==================== Describe Template Tests ====================
+
+    fn expand(template: &str) -> Result<String> {
+        expand_description(template, "TICKET-1", 3, "2024-06-01", Some("petros"))
+    }
+
+    #[test]
+    fn test_expand_description_label() {
+        assert_eq!(expand("Ticket {label}").unwrap(), "Ticket TICKET-1");
+    }
+
+    #[test]
+    fn test_expand_description_date() {
+        assert_eq!(expand("Opened {date}").unwrap(), "Opened 2024-06-01");
+    }
+
+    #[test]
+    fn test_expand_description_user() {
+        assert_eq!(expand("Owned by {user}").unwrap(), "Owned by petros");
+    }
+
+    #[test]
+    fn test_expand_description_missing_user_fails() {
+        let err = expand_description("{user}", "l", 0, "2024-06-01", None).unwrap_err();
+        assert!(err.to_string().contains("$USER"), "{}", err);
+    }
+
+    #[test]
+    fn test_expand_description_count() {
+        assert_eq!(expand("{count} sessions").unwrap(), "3 sessions");
+    }
+
+    #[test]
+    fn test_expand_description_unknown_variable_fails() {
+        let err = expand("Ticket {owner}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown template variable '{owner}' (known: {label}, {date}, {user}, {count})"
+        );
+    }
+
+    #[test]
+    fn test_describe_template_stores_expanded_text() {
+        let (storage, path) = create_test_storage("describe-template");
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+        let options = DescribeOptions {
+            template: true,
+            ..Default::default()
+        };
+
+        describe(
+            &storage,
+            "my-label",
+            Some("{label} has {count}".to_string()),
+            &options,
+        )
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(
+            store.get_label("my-label").unwrap().description.as_deref(),
+            Some("my-label has 1")
+        );
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_describe_template_error_leaves_description() {
+        let (storage, path) = create_test_storage("describe-template-bad");
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+        let options = DescribeOptions {
+            template: true,
+            ..Default::default()
+        };
+
+        assert!(describe(&storage, "my-label", Some("{nope}".to_string()), &options).is_err());
+
+        let store = storage.load().unwrap();
+        assert_eq!(store.get_label("my-label").unwrap().description, None);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_describe_without_template_keeps_braces() {
+        let (storage, path) = create_test_storage("describe-template-off");
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+
+        describe(
+            &storage,
+            "my-label",
+            Some("{label}".to_string()),
+            &DescribeOptions::default(),
+        )
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(
+            store.get_label("my-label").unwrap().description.as_deref(),
+            Some("{label}")
+        );
+        cleanup(&path);
+    }
+
+    // ==================== Print Data Path Tests ====================
+
+    #[test]
+    fn test_print_data_path_after_add() {
+        let (storage, path) = create_test_storage("print-data-path");
+        let mut out = Vec::new();
+
+        print_data_path(&mut out, &storage).unwrap();
+        assert!(out.is_empty());
+
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+        print_data_path(&mut out, &storage).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{}\n", path.display())
+        );
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_print_data_path_silent_for_read_only_commands() {
+        let (storage, path) = create_test_storage("print-data-path-ro");
+        let mut out = Vec::new();
+
+        stats(&storage, None, false, false, None).unwrap();
+        print_data_path(&mut out, &storage).unwrap();
+
+        assert!(out.is_empty());
+        cleanup(&path);
+    }
+
+    // ==================== Config Command Tests ====================
+
+    #[test]
+    fn test_config_returns_ok() {
+        let (storage, path) = create_test_storage("config");
+
+        assert!(config(&storage, false).is_ok());
+        assert!(config(&storage, true).is_ok());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_file_info_existing_file() {
+        let path = temp_path("file-info");
+        fs::write(&path, "12345").unwrap();
+        let before = Utc::now() - chrono::Duration::minutes(1);
+
+        let info = file_info(&path).unwrap().unwrap();
+
+        assert_eq!(info.size, 5);
+        assert!(info.modified.is_some_and(|at| at > before));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_file_info_missing_file_is_none() {
+        let path = temp_path("file-info-missing");
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(file_info(&path).unwrap(), None);
+    }
+
+    // ==================== Config Export Tests ====================
+
+    #[test]
+    fn test_config_export_import_roundtrip_keeps_labels() {
+        let (source, source_path) = create_test_storage("config-export-src");
+        source
+            .update(|store| {
+                store.settings.checksum = true;
+                store.settings.time_zone = Some("Europe/Berlin".to_string());
+                store
+                    .settings
+                    .aliases
+                    .insert("w".to_string(), "work".to_string());
+                store.get_or_create_label("source-only");
+                Ok(())
+            })
+            .unwrap();
+        let file = temp_path("config-export.json");
+        config_export(&source, &file).unwrap();
+
+        let (target, target_path) = create_test_storage("config-export-dst");
+        add(&target, "mine", "sess-1", None, &AddOptions::default()).unwrap();
+        target
+            .update(|store| {
+                store.settings.sort_sessions_on_save = true;
+                Ok(())
+            })
+            .unwrap();
+
+        config_import(&target, &file).unwrap();
+
+        let store = target.load().unwrap();
+        assert_eq!(store.settings, source.load().unwrap().settings);
+        assert!(!store.settings.sort_sessions_on_save);
+        assert_eq!(store.labels.keys().collect::<Vec<_>>(), vec!["mine"]);
+        assert_eq!(store.get_label("mine").unwrap().sessions.len(), 1);
+
+        let exported = fs::read_to_string(&file).unwrap();
+        assert!(!exported.contains("source-only"));
+        cleanup(&file);
+        cleanup(&source_path);
+        cleanup(&target_path);
+    }
+
+    #[test]
+    fn test_parse_settings_rejects_bad_values() {
+        assert!(parse_settings(r#"{"layout": "nested"}"#).is_err());
+        assert!(parse_settings(r#"{"time_zone": "Nowhere/Special"}"#).is_err());
+        assert!(parse_settings("not json").is_err());
+        assert!(parse_settings("{}").unwrap().is_default());
+    }
+
+    // ==================== Describe Edit Tests ====================
+
+    #[test]
+    fn test_describe_edit_label_prefills_current_text() {
+        let (storage, path) = create_test_storage("describe-edit");
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+        describe(
+            &storage,
+            "my-label",
+            Some("Old".to_string()),
+            &DescribeOptions::default(),
+        )
+        .unwrap();
+
+        let options = DescribeOptions {
+            edit: true,
+            ..Default::default()
+        };
+        describe_with_editor(&storage, "my-label", None, &options, |file| {
+            assert_eq!(fs::read_to_string(file).unwrap(), "Old");
+            fs::write(file, "Old\nplus a second line\n").unwrap();
+            Ok(())
+        })
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(
+            store.get_label("my-label").unwrap().description,
+            Some("Old\nplus a second line".to_string())
+        );
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_describe_edit_session_and_clear() {
+        let (storage, path) = create_test_storage("describe-edit-session");
+        add(
+            &storage,
+            "my-label",
+            "sess-1",
+            Some("Session note".to_string()),
+            &AddOptions::default(),
+        )
+        .unwrap();
+
+        let options = DescribeOptions {
+            edit: true,
+            session: Some("sess-1".to_string()),
+            ..Default::default()
+        };
+        describe_with_editor(&storage, "my-label", None, &options, |file| {
+            assert_eq!(fs::read_to_string(file).unwrap(), "Session note");
+            fs::write(file, "").unwrap();
+            Ok(())
+        })
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        let label = store.get_label("my-label").unwrap();
+        assert_eq!(label.sessions[0].description, None);
+        assert_eq!(label.description, None);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_describe_session_missing_returns_error() {
+        let (storage, path) = create_test_storage("describe-session-missing");
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+
+        let options = DescribeOptions {
+            session: Some("nope".to_string()),
+            ..Default::default()
+        };
+        assert!(describe(&storage, "my-label", Some("x".to_string()), &options).is_err());
+        cleanup(&path);
+    }
+
+    // ==================== Color Command Tests ====================
+
+    #[test]
+    fn test_color_sets_and_clears() {
+        let (storage, path) = create_test_storage("color");
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
+
+        color(&storage, "my-label", Some("Bright-Red")).unwrap();
+        assert_eq!(
+            storage.load().unwrap().get_label("my-label").unwrap().color,
+            Some("bright_red".to_string())
+        );
+
+        assert!(color(&storage, "my-label", Some("chartreuse")).is_err());
+        assert!(color(&storage, "missing", Some("red")).is_err());
+
+        color(&storage, "my-label", None).unwrap();
+        assert_eq!(
+            storage.load().unwrap().get_label("my-label").unwrap().color,
+            None
+        );
+        cleanup(&path);
+    }
+
+    // ==================== Description Map Tests ====================
+
+    fn create_map_store() -> Store {
+        let mut store = Store::new();
+        store
+            .labels
+            .insert("api".to_string(), Label::new(Some("Old".to_string())));
+        store.labels.insert("web".to_string(), Label::new(None));
+        store
     }
 
-    fn create_test_storage(name: &str) -> (Storage, PathBuf) {
-        let path = temp_path(name);
-        cleanup(&path);
-        let storage = Storage::with_path(path.clone());
-        (storage, path)
+    fn description_map(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
     }
 
-    fn create_test_session_with_time(
-        id: &str,
-        path: &str,
-        desc: Option<&str>,
-        year: i32,
-        month: u32,
-        day: u32,
-    ) -> Session {
-        Session {
-            session_id: id.to_string(),
-            path: path.to_string(),
-            description: desc.map(|s| s.to_string()),
-            created_at: Utc.with_ymd_and_hms(year, month, day, 12, 0, 0).unwrap(),
-        }
+    #[test]
+    fn test_parse_description_map_json_and_toml() {
+        let expected = description_map(&[("api", "Backend"), ("team/web", "Frontend")]);
+
+        let json = r#"{"api": "Backend", "team/web": "Frontend"}"#;
+        assert_eq!(parse_description_map(json, false).unwrap(), expected);
+
+        let toml = "api = \"Backend\"\n\"team/web\" = \"Frontend\"\n";
+        assert_eq!(parse_description_map(toml, true).unwrap(), expected);
     }
 
-    // ==================== Add Command Tests ====================
+    #[test]
+    fn test_parse_description_map_malformed_errors() {
+        assert!(parse_description_map("{not json", false).is_err());
+        assert!(parse_description_map(r#"{"api": 3}"#, false).is_err());
+        assert!(parse_description_map(r#"["api"]"#, false).is_err());
+        assert!(parse_description_map("api = [1, 2]", true).is_err());
+    }
 
     #[test]
-    fn test_add_creates_new_label() {
-        let (storage, path) = create_test_storage("add-new-label");
+    fn test_apply_description_map_sets_descriptions() {
+        let mut store = create_map_store();
+        let map = description_map(&[("api", "Backend"), ("web", "Frontend")]);
 
-        let result = add(&storage, "my-label", "session-123", None);
-        assert!(result.is_ok());
+        let skipped = apply_description_map(&mut store, &map, false).unwrap();
 
-        let store = storage.load().unwrap();
-        assert!(store.labels.contains_key("my-label"));
+        assert!(skipped.is_empty());
+        assert_eq!(store.labels["api"].description, Some("Backend".to_string()));
+        assert_eq!(
+            store.labels["web"].description,
+            Some("Frontend".to_string())
+        );
+    }
 
-        let label = store.get_label("my-label").unwrap();
-        assert_eq!(label.sessions.len(), 1);
-        assert_eq!(label.sessions[0].session_id, "session-123");
+    #[test]
+    fn test_apply_description_map_unknown_label_errors_without_changes() {
+        let mut store = create_map_store();
+        let map = description_map(&[("api", "Backend"), ("nope", "Who?")]);
 
-        cleanup(&path);
+        let err = apply_description_map(&mut store, &map, false).unwrap_err();
+
+        assert!(err.to_string().contains("nope"));
+        assert_eq!(store.labels["api"].description, Some("Old".to_string()));
     }
 
     #[test]
-    fn test_add_appends_to_existing_label() {
-        let (storage, path) = create_test_storage("add-append");
+    fn test_apply_description_map_skip_unknown() {
+        let mut store = create_map_store();
+        let map = description_map(&[("api", "Backend"), ("nope", "Who?")]);
 
-        // Add first session
-        add(&storage, "my-label", "session-1", None).unwrap();
+        let skipped = apply_description_map(&mut store, &map, true).unwrap();
 
-        // Add second session to same label
-        add(
-            &storage,
-            "my-label",
-            "session-2",
-            Some("Second session".to_string()),
-        )
-        .unwrap();
+        assert_eq!(skipped, vec!["nope"]);
+        assert_eq!(store.labels["api"].description, Some("Backend".to_string()));
+        assert!(!store.labels.contains_key("nope"));
+    }
 
-        let store = storage.load().unwrap();
-        let label = store.get_label("my-label").unwrap();
-        assert_eq!(label.sessions.len(), 2);
+    #[test]
+    fn test_describe_from_map_reads_toml_file() {
+        let (storage, path) = create_test_storage("describe-map");
+        storage
+            .update(|store| {
+                *store = create_map_store();
+                Ok(())
+            })
+            .unwrap();
+        let file = temp_path("describe-map-src").with_extension("toml");
+        fs::write(&file, "web = \"Frontend\"\n").unwrap();
+
+        describe_from_map(&storage, &file, false).unwrap();
 
+        let store = storage.load().unwrap();
+        assert_eq!(
+            store.get_label("web").unwrap().description,
+            Some("Frontend".to_string())
+        );
         cleanup(&path);
+        cleanup(&file);
     }
 
-    #[test]
-    fn test_add_with_description() {
-        let (storage, path) = create_test_storage("add-desc");
+    // ==================== Rename Session Tests ====================
 
+    #[test]
+    fn test_rename_session_command() {
+        let (storage, path) = create_test_storage("rename-session");
         add(
             &storage,
             "my-label",
-            "session-123",
-            Some("Test description".to_string()),
+            "sess-1",
+            Some("Notes".to_string()),
+            &AddOptions::default(),
         )
         .unwrap();
+        add(&storage, "my-label", "sess-2", None, &AddOptions::default()).unwrap();
+
+        rename_session(&storage, "my-label", "sess-1", "sess-9").unwrap();
+        assert!(rename_session(&storage, "my-label", "sess-1", "sess-3").is_err());
+        assert!(rename_session(&storage, "my-label", "sess-9", "sess-2").is_err());
+        assert!(rename_session(&storage, "missing", "sess-9", "sess-3").is_err());
 
         let store = storage.load().unwrap();
         let label = store.get_label("my-label").unwrap();
-        assert_eq!(
-            label.sessions[0].description,
-            Some("Test description".to_string())
-        );
-
+        let renamed = label.find_session("sess-9").unwrap();
+        assert_eq!(renamed.description, Some("Notes".to_string()));
+        assert!(label.find_session("sess-2").is_some());
         cleanup(&path);
     }
 
-    #[test]
-    fn test_add_saves_current_directory() {
-        let (storage, path) = create_test_storage("add-path");
+    // ==================== Touch Tests ====================
 
-        add(&storage, "my-label", "session-123", None).unwrap();
+    #[test]
+    fn test_touch_all_bumps_every_session() {
+        let (storage, path) = create_test_storage("touch-all");
+        storage
+            .update(|store| {
+                let label = store.get_or_create_label("work");
+                label.add_session(create_test_session_with_time("s1", "/p", None, 2023, 1, 1));
+                label.add_session(create_test_session_with_time("s2", "/p", None, 2024, 1, 1));
+                Ok(())
+            })
+            .unwrap();
+        let before = Utc::now() - chrono::Duration::minutes(1);
+
+        touch(&storage, "work", None, true).unwrap();
 
         let store = storage.load().unwrap();
-        let label = store.get_label("my-label").unwrap();
+        let label = store.get_label("work").unwrap();
+        assert!(label.sessions.iter().all(|s| s.created_at > before));
+        assert_eq!(label.latest_session().unwrap().session_id, "s2");
+        cleanup(&path);
+    }
 
-        // The path should be the current working directory
-        let current_dir = env::current_dir().unwrap().to_string_lossy().to_string();
-        assert_eq!(label.sessions[0].path, current_dir);
+    #[test]
+    fn test_touch_single_session_and_missing() {
+        let (storage, path) = create_test_storage("touch-one");
+        storage
+            .update(|store| {
+                let label = store.get_or_create_label("work");
+                label.add_session(create_test_session_with_time("s1", "/p", None, 2023, 1, 1));
+                label.add_session(create_test_session_with_time("s2", "/p", None, 2024, 1, 1));
+                Ok(())
+            })
+            .unwrap();
+
+        touch(&storage, "work", Some("s1"), false).unwrap();
+        assert!(touch(&storage, "work", Some("nope"), false).is_err());
+        assert!(touch(&storage, "missing", None, true).is_err());
 
+        let store = storage.load().unwrap();
+        let label = store.get_label("work").unwrap();
+        assert_eq!(label.latest_session().unwrap().session_id, "s1");
         cleanup(&path);
     }
 
-    // ==================== List Command Tests ====================
+    // ==================== Create Flag Tests ====================
 
     #[test]
-    fn test_list_empty_store() {
-        let (storage, path) = create_test_storage("list-empty");
+    fn test_describe_missing_label_errors_without_create() {
+        let (storage, path) = create_test_storage("describe-no-create");
 
-        // Should not error on empty store
-        let result = list(&storage, None);
-        assert!(result.is_ok());
+        let result = describe(
+            &storage,
+            "new-label",
+            Some("Desc".to_string()),
+            &DescribeOptions::default(),
+        );
 
+        assert!(result.is_err());
+        assert!(storage.load().unwrap().labels.is_empty());
         cleanup(&path);
     }
 
     #[test]
-    fn test_list_all_labels() {
-        let (storage, path) = create_test_storage("list-all");
-
-        add(&storage, "label-1", "sess-1", None).unwrap();
-        add(&storage, "label-2", "sess-2", None).unwrap();
+    fn test_describe_create_adds_missing_label() {
+        let (storage, path) = create_test_storage("describe-create");
+        let options = DescribeOptions {
+            create: true,
+            ..Default::default()
+        };
 
-        let result = list(&storage, None);
-        assert!(result.is_ok());
+        describe(&storage, "new-label", Some("Desc".to_string()), &options).unwrap();
 
+        let store = storage.load().unwrap();
+        let label = store.get_label("new-label").unwrap();
+        assert_eq!(label.description, Some("Desc".to_string()));
+        assert!(label.sessions.is_empty());
         cleanup(&path);
     }
 
     #[test]
-    fn test_list_specific_label() {
-        let (storage, path) = create_test_storage("list-specific");
+    fn test_current_description_present() {
+        let mut store = Store::new();
+        let mut label = Label::new(Some("Runbook:\n1. restart".to_string()));
+        label.add_session(create_test_session_with_time(
+            "sess-1",
+            "/p",
+            Some("Session notes"),
+            2024,
+            1,
+            1,
+        ));
+        store.labels.insert("ops".to_string(), label);
 
-        add(&storage, "my-label", "sess-1", None).unwrap();
+        assert_eq!(
+            current_description(&store, "ops", None).unwrap(),
+            Some("Runbook:\n1. restart")
+        );
+        assert_eq!(
+            current_description(&store, "ops", Some("sess-1")).unwrap(),
+            Some("Session notes")
+        );
+    }
 
-        let result = list(&storage, Some("my-label"));
-        assert!(result.is_ok());
+    #[test]
+    fn test_current_description_absent() {
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.add_session(create_test_session_with_time(
+            "sess-1", "/p", None, 2024, 1, 1,
+        ));
+        store.labels.insert("ops".to_string(), label);
 
-        cleanup(&path);
+        assert_eq!(current_description(&store, "ops", None).unwrap(), None);
+        assert_eq!(
+            current_description(&store, "ops", Some("sess-1")).unwrap(),
+            None
+        );
+        assert!(current_description(&store, "missing", None).is_err());
+        assert!(current_description(&store, "ops", Some("nope")).is_err());
     }
 
     #[test]
-    fn test_list_nonexistent_label_returns_error() {
-        let (storage, path) = create_test_storage("list-nonexistent");
-
-        let result = list(&storage, Some("nonexistent"));
-        assert!(result.is_err());
+    fn test_describe_show_leaves_description_unchanged() {
+        let (storage, path) = create_test_storage("describe-show");
+        storage
+            .update(|store| {
+                store
+                    .labels
+                    .insert("ops".to_string(), Label::new(Some("Keep me".to_string())));
+                Ok(())
+            })
+            .unwrap();
+        let options = DescribeOptions {
+            show: true,
+            ..Default::default()
+        };
+
+        describe(&storage, "ops", None, &options).unwrap();
+        assert!(describe(&storage, "missing", None, &options).is_err());
 
+        let store = storage.load().unwrap();
+        assert_eq!(
+            store.get_label("ops").unwrap().description,
+            Some("Keep me".to_string())
+        );
         cleanup(&path);
     }
 
-    // ==================== Remove Command Tests ====================
-
     #[test]
-    fn test_remove_entire_label() {
-        let (storage, path) = create_test_storage("remove-label");
-
-        add(&storage, "my-label", "sess-1", None).unwrap();
+    fn test_alias_add_create_adds_missing_label() {
+        let (storage, path) = create_test_storage("alias-create");
 
-        let result = remove(&storage, "my-label", None);
-        assert!(result.is_ok());
+        assert!(alias_add(&storage, "short", "long-label-name", false).is_err());
+        alias_add(&storage, "short", "long-label-name", true).unwrap();
 
         let store = storage.load().unwrap();
-        assert!(!store.labels.contains_key("my-label"));
-
+        assert!(store.labels.contains_key("long-label-name"));
+        assert!(store.get_label("short").is_some());
         cleanup(&path);
     }
 
-    #[test]
-    fn test_remove_specific_session() {
-        let (storage, path) = create_test_storage("remove-session");
-
-        add(&storage, "my-label", "sess-1", None).unwrap();
-        add(&storage, "my-label", "sess-2", None).unwrap();
+    // ==================== Meta Command Tests ====================
 
-        let result = remove(&storage, "my-label", Some("sess-1"));
-        assert!(result.is_ok());
+    #[test]
+    fn test_add_with_meta() {
+        let (storage, path) = create_test_storage("add-meta");
+
+        let options = AddOptions {
+            meta: vec![
+                ("model".to_string(), "opus".to_string()),
+                ("reviewer".to_string(), "alice".to_string()),
+            ],
+            ..Default::default()
+        };
+        add(&storage, "my-label", "sess-1", None, &options).unwrap();
 
         let store = storage.load().unwrap();
-        let label = store.get_label("my-label").unwrap();
-        assert_eq!(label.sessions.len(), 1);
-        assert_eq!(label.sessions[0].session_id, "sess-2");
+        let session = &store.get_label("my-label").unwrap().sessions[0];
+        assert_eq!(session.meta.get("model"), Some(&"opus".to_string()));
+        assert_eq!(session.meta.get("reviewer"), Some(&"alice".to_string()));
 
         cleanup(&path);
     }
 
     #[test]
-    fn test_remove_nonexistent_label_returns_error() {
-        let (storage, path) = create_test_storage("remove-nonexistent-label");
-
-        let result = remove(&storage, "nonexistent", None);
-        assert!(result.is_err());
+    fn test_captured_env_meta() {
+        let meta = captured_env_meta(Some(OsString::from("/bin/zsh")), true);
+        assert_eq!(
+            meta,
+            vec![
+                ("shell".to_string(), "/bin/zsh".to_string()),
+                ("tty".to_string(), "true".to_string()),
+            ]
+        );
 
-        cleanup(&path);
+        // An unset or empty $SHELL is left out rather than recorded blank
+        for shell in [None, Some(OsString::new())] {
+            assert_eq!(
+                captured_env_meta(shell, false),
+                vec![("tty".to_string(), "false".to_string())]
+            );
+        }
     }
 
     #[test]
-    fn test_remove_nonexistent_session_returns_error() {
-        let (storage, path) = create_test_storage("remove-nonexistent-session");
+    fn test_add_capture_env_records_meta_under_explicit_values() {
+        let (storage, path) = create_test_storage("add-capture-env");
 
-        add(&storage, "my-label", "sess-1", None).unwrap();
+        let options = AddOptions {
+            capture_env: true,
+            meta: vec![("tty".to_string(), "manual".to_string())],
+            ..Default::default()
+        };
+        add(&storage, "my-label", "sess-1", None, &options).unwrap();
+        add(&storage, "my-label", "sess-2", None, &AddOptions::default()).unwrap();
 
-        let result = remove(&storage, "my-label", Some("nonexistent"));
-        assert!(result.is_err());
+        let store = storage.load().unwrap();
+        let label = store.get_label("my-label").unwrap();
+        let captured = label.find_session("sess-1").unwrap();
+        assert_eq!(captured.meta.get("tty"), Some(&"manual".to_string()));
+        assert!(label.find_session("sess-2").unwrap().meta.is_empty());
 
         cleanup(&path);
     }
 
-    // ==================== Describe Command Tests ====================
-
     #[test]
-    fn test_describe_set_description() {
-        let (storage, path) = create_test_storage("describe-set");
+    fn test_meta_set_and_unset() {
+        let (storage, path) = create_test_storage("meta-set-unset");
 
-        add(&storage, "my-label", "sess-1", None).unwrap();
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
 
-        let result = describe(&storage, "my-label", Some("New description".to_string()));
-        assert!(result.is_ok());
+        meta(
+            &storage,
+            "my-label",
+            "sess-1",
+            vec![
+                ("model".to_string(), "opus".to_string()),
+                ("reviewer".to_string(), "alice".to_string()),
+            ],
+            vec![],
+        )
+        .unwrap();
+        meta(
+            &storage,
+            "my-label",
+            "sess-1",
+            vec![("model".to_string(), "sonnet".to_string())],
+            vec!["reviewer".to_string()],
+        )
+        .unwrap();
 
         let store = storage.load().unwrap();
-        let label = store.get_label("my-label").unwrap();
-        assert_eq!(label.description, Some("New description".to_string()));
+        let session = &store.get_label("my-label").unwrap().sessions[0];
+        assert_eq!(session.meta.len(), 1);
+        assert_eq!(session.meta.get("model"), Some(&"sonnet".to_string()));
 
         cleanup(&path);
     }
 
     #[test]
-    fn test_describe_update_description() {
-        let (storage, path) = create_test_storage("describe-update");
+    fn test_meta_nonexistent_session_returns_error() {
+        let (storage, path) = create_test_storage("meta-nonexistent");
 
-        add(&storage, "my-label", "sess-1", None).unwrap();
-        describe(&storage, "my-label", Some("First".to_string())).unwrap();
-
-        let result = describe(&storage, "my-label", Some("Updated".to_string()));
-        assert!(result.is_ok());
+        add(&storage, "my-label", "sess-1", None, &AddOptions::default()).unwrap();
 
-        let store = storage.load().unwrap();
-        let label = store.get_label("my-label").unwrap();
-        assert_eq!(label.description, Some("Updated".to_string()));
+        let result = meta(&storage, "my-label", "missing", vec![], vec![]);
+        assert!(result.is_err());
 
         cleanup(&path);
     }
 
     #[test]
-    fn test_describe_clear_description() {
-        let (storage, path) = create_test_storage("describe-clear");
+    fn test_format_meta_is_sorted() {
+        let mut meta = HashMap::new();
+        meta.insert("zeta".to_string(), "1".to_string());
+        meta.insert("alpha".to_string(), "2".to_string());
 
-        add(&storage, "my-label", "sess-1", None).unwrap();
-        describe(&storage, "my-label", Some("Has description".to_string())).unwrap();
+        assert_eq!(format_meta(&meta), "alpha=2, zeta=1");
+    }
 
-        let result = describe(&storage, "my-label", None);
-        assert!(result.is_ok());
+    #[test]
+    fn test_config_set_get_roundtrip() {
+        let (storage, path) = create_test_storage("config-set-get");
 
-        let store = storage.load().unwrap();
-        let label = store.get_label("my-label").unwrap();
-        assert_eq!(label.description, None);
+        config_set(&storage, "checksum", "true").unwrap();
+        assert!(storage.load().unwrap().settings.checksum);
+        assert!(config_get(&storage, "checksum").is_ok());
+
+        config_set(&storage, "checksum", "false").unwrap();
+        assert!(!storage.load().unwrap().settings.checksum);
 
         cleanup(&path);
     }
 
     #[test]
-    fn test_describe_nonexistent_label_returns_error() {
-        let (storage, path) = create_test_storage("describe-nonexistent");
+    fn test_config_set_unknown_key_returns_error() {
+        let (storage, path) = create_test_storage("config-unknown-key");
 
-        let result = describe(&storage, "nonexistent", Some("Description".to_string()));
-        assert!(result.is_err());
+        assert!(config_set(&storage, "nope", "true").is_err());
+        assert!(config_get(&storage, "nope").is_err());
 
         cleanup(&path);
     }
 
-    // ==================== Config Command Tests ====================
-
     #[test]
-    fn test_config_returns_ok() {
-        let (storage, path) = create_test_storage("config");
+    fn test_config_set_bad_value_returns_error() {
+        let (storage, path) = create_test_storage("config-bad-value");
 
-        let result = config(&storage);
-        assert!(result.is_ok());
+        assert!(config_set(&storage, "checksum", "sometimes").is_err());
+        assert!(!storage.load().unwrap().settings.checksum);
 
         cleanup(&path);
     }
@@ -566,7 +8454,7 @@ mod tests {
     fn test_resume_nonexistent_label_returns_error() {
         let (storage, path) = create_test_storage("resume-nonexistent");
 
-        let result = resume(&storage, "nonexistent", false);
+        let result = resume(&storage, "nonexistent", &ResumeOptions::default());
         assert!(result.is_err());
 
         cleanup(&path);
@@ -583,16 +8471,136 @@ mod tests {
             .insert("empty-label".to_string(), Label::new(None));
         storage.save(&store).unwrap();
 
-        let result = resume(&storage, "empty-label", false);
+        let result = resume(&storage, "empty-label", &ResumeOptions::default());
+        assert!(result.is_err());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_resume_cd_escape_returns_error() {
+        let (storage, path) = create_test_storage("resume-cd-escape");
+
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.add_session(create_test_session_with_time(
+            "sess-1",
+            &env::temp_dir().to_string_lossy(),
+            None,
+            2024,
+            1,
+            1,
+        ));
+        store.labels.insert("my-label".to_string(), label);
+        storage.save(&store).unwrap();
+
+        let options = ResumeOptions {
+            cd: Some("..".to_string()),
+            ..Default::default()
+        };
+        let result = resume(&storage, "my-label", &options);
         assert!(result.is_err());
 
         cleanup(&path);
     }
 
+    // ==================== Fallback Resolution Tests ====================
+
+    fn create_fallback_store() -> Store {
+        let mut store = Store::new();
+        let mut primary = Label::new(None);
+        primary.add_session(create_test_session_with_time("p1", "/p", None, 2024, 1, 1));
+        let mut fallback = Label::new(None);
+        fallback.add_session(create_test_session_with_time("f1", "/f", None, 2024, 1, 1));
+        store.labels.insert("primary".to_string(), primary);
+        store.labels.insert("fallback".to_string(), fallback);
+        store.labels.insert("empty".to_string(), Label::new(None));
+        store
+    }
+
+    #[test]
+    fn test_resolve_resume_label_uses_primary() {
+        let store = create_fallback_store();
+
+        let (name, label) = resolve_resume_label(&store, "primary", Some("fallback")).unwrap();
+
+        assert_eq!(name, "primary");
+        assert_eq!(label.sessions[0].session_id, "p1");
+    }
+
+    #[test]
+    fn test_resolve_resume_label_uses_fallback() {
+        let store = create_fallback_store();
+
+        let (name, _) = resolve_resume_label(&store, "empty", Some("fallback")).unwrap();
+        assert_eq!(name, "fallback");
+
+        let (name, label) = resolve_resume_label(&store, "missing", Some("fallback")).unwrap();
+        assert_eq!(name, "fallback");
+        assert_eq!(label.sessions[0].session_id, "f1");
+    }
+
+    #[test]
+    fn test_resolve_resume_label_both_unusable() {
+        let store = create_fallback_store();
+
+        assert!(resolve_resume_label(&store, "empty", Some("missing")).is_err());
+        assert!(resolve_resume_label(&store, "missing", Some("empty")).is_err());
+        assert!(resolve_resume_label(&store, "empty", None).is_err());
+    }
+
     // ==================== Pick Session Tests ====================
     // Note: pick_session() is interactive and can't be easily unit tested.
     // We test the supporting logic through the Label's latest_session() method.
 
+    #[test]
+    fn test_picker_default_index_per_preference() {
+        let sessions = [
+            create_test_session_with_time("mid", "/p", None, 2024, 2, 1),
+            create_test_session_with_time("old", "/p", None, 2024, 1, 1),
+            create_test_session_with_time("new", "/p", None, 2024, 3, 1),
+        ];
+        let mut sorted: Vec<&Session> = sessions.iter().collect();
+        sorted.sort_by(|a, b| newest_first(a, b));
+
+        let latest = picker_default_index(&sorted, PickerDefault::Latest);
+        let oldest = picker_default_index(&sorted, PickerDefault::Oldest);
+
+        assert_eq!(sorted[latest].session_id, "new");
+        assert_eq!(sorted[oldest].session_id, "old");
+        assert_eq!((latest, oldest), (0, 2));
+    }
+
+    #[test]
+    fn test_picker_default_index_empty_list() {
+        assert_eq!(picker_default_index(&[], PickerDefault::Oldest), 0);
+    }
+
+    #[test]
+    fn test_picker_items_truncate_ids_by_default() {
+        let session = create_test_session_with_time(
+            "0f8fad5b-d9cb-469f-a165-70867728950e",
+            "/path",
+            Some("First"),
+            2024,
+            1,
+            1,
+        );
+
+        let items = picker_items(&[&session], Some(PICKER_ID_LEN), DisplayZone::Utc);
+        assert_eq!(items, vec!["0f8fad5b (2024-01-01 12:00) - First"]);
+    }
+
+    #[test]
+    fn test_picker_items_full_ids_without_truncation() {
+        let a = create_test_session_with_time("0f8fad5b-aaaa", "/path", None, 2024, 1, 1);
+        let b = create_test_session_with_time("0f8fad5b-bbbb", "/path", None, 2024, 1, 2);
+
+        let items = picker_items(&[&a, &b], None, DisplayZone::Utc);
+        assert!(items[0].starts_with("0f8fad5b-aaaa "));
+        assert!(items[1].starts_with("0f8fad5b-bbbb "));
+    }
+
     #[test]
     fn test_latest_session_is_selected_by_default() {
         let (storage, path) = create_test_storage("latest-session");
@@ -0,0 +1,525 @@
+//! JSON output shapes for `--json`.
+//!
+//! These structs are the wire format scripts depend on. They are mapped from
+//! the storage types so `Session`/`Label`/`Store` can change without changing
+//! what `--json` prints. Every field is always present (`null` when unset).
+
+use chrono::{DateTime, Utc};
+use serde::ser::{SerializeStruct, Serializer as _};
+use serde::Serialize;
+use serde_json::ser::{Formatter, PrettyFormatter};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::data::{Label, Session, Store};
+
+/// How timestamps are written in machine output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// RFC 3339 strings, e.g. `2024-01-01T12:00:00Z`.
+    #[default]
+    Rfc3339,
+    /// Integer Unix seconds.
+    Epoch,
+}
+
+impl TimeFormat {
+    pub fn timestamp(self, at: DateTime<Utc>) -> Timestamp {
+        match self {
+            TimeFormat::Rfc3339 => Timestamp::Rfc3339(at),
+            TimeFormat::Epoch => Timestamp::Epoch(at.timestamp()),
+        }
+    }
+
+    /// The timestamp as a porcelain field.
+    pub fn format(self, at: DateTime<Utc>) -> String {
+        match self {
+            TimeFormat::Rfc3339 => at.to_rfc3339(),
+            TimeFormat::Epoch => at.timestamp().to_string(),
+        }
+    }
+}
+
+/// A timestamp serialized as an RFC 3339 string or as Unix seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Timestamp {
+    Rfc3339(DateTime<Utc>),
+    Epoch(i64),
+}
+
+/// One session: `session_id`, `path`, `description`, `created_at`, `meta`,
+/// `resume_count`, `last_resumed_at`.
+#[derive(Debug, Serialize)]
+pub struct SessionOut<'a> {
+    pub session_id: &'a str,
+    pub path: &'a str,
+    pub description: Option<&'a str>,
+    pub created_at: Timestamp,
+    pub meta: BTreeMap<&'a str, &'a str>,
+    pub resume_count: u32,
+    pub last_resumed_at: Option<Timestamp>,
+}
+
+impl<'a> SessionOut<'a> {
+    pub fn new(session: &'a Session, time: TimeFormat) -> Self {
+        Self {
+            session_id: &session.session_id,
+            path: &session.path,
+            description: session.description.as_deref(),
+            created_at: time.timestamp(session.created_at),
+            meta: session
+                .meta
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect(),
+            resume_count: session.resume_count,
+            last_resumed_at: session.last_resumed_at.map(|at| time.timestamp(at)),
+        }
+    }
+}
+
+impl<'a> From<&'a Session> for SessionOut<'a> {
+    fn from(session: &'a Session) -> Self {
+        Self::new(session, TimeFormat::default())
+    }
+}
+
+/// One label: `description`, `session_count`, `latest_session_id`,
+/// `latest_created_at`, `sessions`. The counts and latest fields are computed
+/// here for convenience; they are not stored.
+#[derive(Debug, Serialize)]
+pub struct LabelOut<'a> {
+    pub description: Option<&'a str>,
+    pub session_count: usize,
+    pub latest_session_id: Option<&'a str>,
+    pub latest_created_at: Option<Timestamp>,
+    pub sessions: Vec<SessionOut<'a>>,
+}
+
+impl<'a> LabelOut<'a> {
+    pub fn new(label: &'a Label, time: TimeFormat) -> Self {
+        let latest = label.latest_session();
+        Self {
+            description: label.description.as_deref(),
+            session_count: label.sessions.len(),
+            latest_session_id: latest.map(|s| s.session_id.as_str()),
+            latest_created_at: latest.map(|s| time.timestamp(s.created_at)),
+            sessions: label
+                .sessions
+                .iter()
+                .map(|s| SessionOut::new(s, time))
+                .collect(),
+        }
+    }
+}
+
+impl<'a> From<&'a Label> for LabelOut<'a> {
+    fn from(label: &'a Label) -> Self {
+        Self::new(label, TimeFormat::default())
+    }
+}
+
+/// One line of the `list --json --overview` array: `name`, `description`,
+/// `session_count`, `latest_created_at`. Labels can't be archived or pinned
+/// yet, so there is no `archived` or `pinned_count`; add them with those
+/// features.
+#[derive(Debug, Serialize)]
+pub struct LabelSummaryOut<'a> {
+    pub name: &'a str,
+    pub description: Option<&'a str>,
+    pub session_count: usize,
+    pub latest_created_at: Option<Timestamp>,
+}
+
+impl<'a> LabelSummaryOut<'a> {
+    pub fn new(name: &'a str, label: &'a Label, time: TimeFormat) -> Self {
+        Self {
+            name,
+            description: label.description.as_deref(),
+            session_count: label.sessions.len(),
+            latest_created_at: label.last_activity().map(|at| time.timestamp(at)),
+        }
+    }
+}
+
+/// Every label keyed by name, in name order: `labels`.
+///
+/// `list --json` streams this shape with `write_store_json` rather than
+/// building it; the struct stays as the reference the stream is tested against.
+#[cfg(test)]
+#[derive(Debug, Serialize)]
+pub struct StoreOut<'a> {
+    pub labels: BTreeMap<&'a str, LabelOut<'a>>,
+}
+
+#[cfg(test)]
+impl<'a> StoreOut<'a> {
+    pub fn new(store: &'a Store, time: TimeFormat) -> Self {
+        Self {
+            labels: store
+                .labels
+                .iter()
+                .map(|(name, label)| (name.as_str(), LabelOut::new(label, time)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl<'a> From<&'a Store> for StoreOut<'a> {
+    fn from(store: &'a Store) -> Self {
+        Self::new(store, TimeFormat::default())
+    }
+}
+
+/// Write the same JSON as `StoreOut` to `writer`, one label and session at a
+/// time, so a large store is never held in memory twice. `indent` works as in
+/// `--indent`: spaces per level, 0 for a single line.
+pub fn write_store_json<W: Write>(
+    writer: W,
+    store: &Store,
+    time: TimeFormat,
+    indent: usize,
+) -> serde_json::Result<()> {
+    if indent == 0 {
+        return stream_store(&mut serde_json::Serializer::new(writer), store, time);
+    }
+    let indent = " ".repeat(indent);
+    let formatter = PrettyFormatter::with_indent(indent.as_bytes());
+    stream_store(
+        &mut serde_json::Serializer::with_formatter(writer, formatter),
+        store,
+        time,
+    )
+}
+
+fn stream_store<W: Write, F: Formatter>(
+    serializer: &mut serde_json::Serializer<W, F>,
+    store: &Store,
+    time: TimeFormat,
+) -> serde_json::Result<()> {
+    let mut names: Vec<&String> = store.labels.keys().collect();
+    names.sort();
+    let labels = names.into_iter().map(|name| {
+        let label = LabelStream {
+            label: &store.labels[name],
+            time,
+        };
+        (name, label)
+    });
+
+    let mut out = serializer.serialize_struct("StoreOut", 1)?;
+    out.serialize_field("labels", &MapStream::new(labels))?;
+    out.end()
+}
+
+/// A label serialized like `LabelOut`, but with its sessions streamed.
+struct LabelStream<'a> {
+    label: &'a Label,
+    time: TimeFormat,
+}
+
+impl Serialize for LabelStream<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let sessions = self
+            .label
+            .sessions
+            .iter()
+            .map(|s| SessionOut::new(s, self.time));
+
+        let latest = self.label.latest_session();
+
+        let mut out = serializer.serialize_struct("LabelOut", 5)?;
+        out.serialize_field("description", &self.label.description)?;
+        out.serialize_field("session_count", &self.label.sessions.len())?;
+        out.serialize_field("latest_session_id", &latest.map(|s| &s.session_id))?;
+        out.serialize_field(
+            "latest_created_at",
+            &latest.map(|s| self.time.timestamp(s.created_at)),
+        )?;
+        out.serialize_field("sessions", &SeqStream::new(sessions))?;
+        out.end()
+    }
+}
+
+/// Serializes an iterator as a JSON array without collecting it first.
+struct SeqStream<I>(std::cell::RefCell<Option<I>>);
+
+impl<I> SeqStream<I> {
+    fn new(iter: I) -> Self {
+        Self(std::cell::RefCell::new(Some(iter)))
+    }
+}
+
+impl<I> Serialize for SeqStream<I>
+where
+    I: Iterator,
+    I::Item: Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let iter = self.0.borrow_mut().take().expect("streamed twice");
+        serializer.collect_seq(iter)
+    }
+}
+
+/// Serializes an iterator of pairs as a JSON object without collecting it.
+struct MapStream<I>(std::cell::RefCell<Option<I>>);
+
+impl<I> MapStream<I> {
+    fn new(iter: I) -> Self {
+        Self(std::cell::RefCell::new(Some(iter)))
+    }
+}
+
+impl<I, K, V> Serialize for MapStream<I>
+where
+    I: Iterator<Item = (K, V)>,
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let iter = self.0.borrow_mut().take().expect("streamed twice");
+        serializer.collect_map(iter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::Value;
+
+    fn keys(value: &Value) -> Vec<&str> {
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    fn create_store() -> Store {
+        let mut store = Store::new();
+        store.settings.checksum = true;
+        let mut label = Label::new(None);
+        label.add_session(Session {
+            session_id: "sess-1".to_string(),
+            path: "/work".to_string(),
+            created_at: Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap(),
+            ..Default::default()
+        });
+        store.labels.insert("my-label".to_string(), label);
+        store
+    }
+
+    // ==================== Output Shape Tests ====================
+
+    #[test]
+    fn test_session_out_field_names() {
+        let store = create_store();
+        let session = &store.get_label("my-label").unwrap().sessions[0];
+        let json = serde_json::to_value(SessionOut::from(session)).unwrap();
+
+        assert_eq!(
+            keys(&json),
+            vec![
+                "created_at",
+                "description",
+                "last_resumed_at",
+                "meta",
+                "path",
+                "resume_count",
+                "session_id",
+            ]
+        );
+        // Unset fields are present rather than skipped
+        assert!(json["description"].is_null());
+        assert!(json["last_resumed_at"].is_null());
+        assert_eq!(json["meta"], serde_json::json!({}));
+        assert_eq!(json["resume_count"], 0);
+        assert_eq!(json["created_at"], "2024-01-01T12:00:00Z");
+    }
+
+    #[test]
+    fn test_label_out_field_names() {
+        let store = create_store();
+        let json =
+            serde_json::to_value(LabelOut::from(store.get_label("my-label").unwrap())).unwrap();
+
+        assert_eq!(
+            keys(&json),
+            vec![
+                "description",
+                "latest_created_at",
+                "latest_session_id",
+                "session_count",
+                "sessions",
+            ]
+        );
+        assert_eq!(json["sessions"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_label_out_computed_fields_match_sessions() {
+        let mut label = Label::new(None);
+        for (id, day) in [("old", 1), ("newest", 20), ("mid", 10)] {
+            label.add_session(Session {
+                session_id: id.to_string(),
+                created_at: Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).unwrap(),
+                ..Default::default()
+            });
+        }
+
+        let json = serde_json::to_value(LabelOut::new(&label, TimeFormat::Epoch)).unwrap();
+
+        assert_eq!(json["session_count"], 3);
+        assert_eq!(json["latest_session_id"], "newest");
+        assert_eq!(
+            json["latest_created_at"],
+            Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0)
+                .unwrap()
+                .timestamp()
+        );
+    }
+
+    #[test]
+    fn test_label_out_computed_fields_null_when_empty() {
+        let json = serde_json::to_value(LabelOut::from(&Label::new(None))).unwrap();
+
+        assert_eq!(json["session_count"], 0);
+        assert!(json["latest_session_id"].is_null());
+        assert!(json["latest_created_at"].is_null());
+    }
+
+    #[test]
+    fn test_label_summary_out_fields() {
+        let store = create_store();
+        let label = store.get_label("my-label").unwrap();
+        let json =
+            serde_json::to_value(LabelSummaryOut::new("my-label", label, TimeFormat::Rfc3339))
+                .unwrap();
+
+        assert_eq!(
+            keys(&json),
+            vec!["description", "latest_created_at", "name", "session_count"]
+        );
+        assert_eq!(json["name"], "my-label");
+        assert!(json["description"].is_null());
+        assert_eq!(json["session_count"], 1);
+        assert_eq!(json["latest_created_at"], "2024-01-01T12:00:00Z");
+    }
+
+    #[test]
+    fn test_store_out_omits_settings() {
+        let store = create_store();
+        let json = serde_json::to_value(StoreOut::from(&store)).unwrap();
+
+        assert_eq!(keys(&json), vec!["labels"]);
+        assert_eq!(keys(&json["labels"]), vec!["my-label"]);
+        assert_eq!(
+            json["labels"]["my-label"]["sessions"][0]["session_id"],
+            "sess-1"
+        );
+    }
+
+    #[test]
+    fn test_session_out_meta_is_sorted() {
+        let mut session = Session::default();
+        session.meta.insert("b".to_string(), "2".to_string());
+        session.meta.insert("a".to_string(), "1".to_string());
+
+        let json = serde_json::to_string(&SessionOut::from(&session)).unwrap();
+
+        assert!(json.contains(r#""meta":{"a":"1","b":"2"}"#));
+    }
+
+    #[test]
+    fn test_epoch_timestamps_are_integers() {
+        let mut session = Session {
+            created_at: Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap(),
+            ..Default::default()
+        };
+        session.last_resumed_at = Some(Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap());
+
+        let json = serde_json::to_value(SessionOut::new(&session, TimeFormat::Epoch)).unwrap();
+
+        assert_eq!(json["created_at"], 1_704_110_400);
+        assert_eq!(json["last_resumed_at"], 1_704_153_600);
+        assert_eq!(TimeFormat::Epoch.format(session.created_at), "1704110400");
+    }
+
+    // ==================== Streaming Tests ====================
+
+    fn create_large_store() -> Store {
+        let mut store = Store::new();
+        for i in 0..100 {
+            let mut label = Label::new(Some(format!("Label {}", i)));
+            for j in 0..10 {
+                let mut session = Session {
+                    session_id: format!("sess-{}-{}", i, j),
+                    path: format!("/work/{}", i),
+                    created_at: Utc.with_ymd_and_hms(2024, 1, 1 + j, 12, 0, 0).unwrap(),
+                    ..Default::default()
+                };
+                if j % 2 == 0 {
+                    session.description = Some(format!("Session {}", j));
+                    session.meta.insert("ticket".to_string(), i.to_string());
+                }
+                label.add_session(session);
+            }
+            store.labels.insert(format!("label-{}", i), label);
+        }
+        store
+    }
+
+    #[test]
+    fn test_streamed_store_parses_back_to_equal_store() {
+        let store = create_large_store();
+        let mut buf = Vec::new();
+
+        write_store_json(&mut buf, &store, TimeFormat::Rfc3339, 2).unwrap();
+
+        let parsed: Store = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(parsed.labels.len(), 100);
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(&store).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_streamed_store_matches_store_out() {
+        let store = create_large_store();
+        for indent in [0, 2, 4] {
+            let mut buf = Vec::new();
+            write_store_json(&mut buf, &store, TimeFormat::Epoch, indent).unwrap();
+
+            let expected = if indent == 0 {
+                serde_json::to_string(&StoreOut::new(&store, TimeFormat::Epoch)).unwrap()
+            } else {
+                let indent = " ".repeat(indent);
+                let mut expected = Vec::new();
+                let formatter = PrettyFormatter::with_indent(indent.as_bytes());
+                let mut serializer =
+                    serde_json::Serializer::with_formatter(&mut expected, formatter);
+                StoreOut::new(&store, TimeFormat::Epoch)
+                    .serialize(&mut serializer)
+                    .unwrap();
+                String::from_utf8(expected).unwrap()
+            };
+            assert_eq!(String::from_utf8(buf).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_streamed_empty_store() {
+        let mut buf = Vec::new();
+
+        write_store_json(&mut buf, &Store::new(), TimeFormat::Rfc3339, 0).unwrap();
+
+        assert_eq!(String::from_utf8(buf).unwrap(), r#"{"labels":{}}"#);
+    }
+}
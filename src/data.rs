@@ -1,14 +1,52 @@
-use chrono::{DateTime, Utc};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::settings::Settings;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Session {
     pub session_id: String,
     pub path: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub meta: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub resume_count: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_resumed_at: Option<DateTime<Utc>>,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+impl Session {
+    /// Fold a duplicate of this session into it. Values already set on `self`
+    /// win; empty fields are filled from `other`, metadata is unioned, and the
+    /// newer `created_at` is kept.
+    pub fn merge_from(&mut self, other: &Session) {
+        if self.path.is_empty() {
+            self.path = other.path.clone();
+        }
+        if self.description.as_deref().is_none_or(str::is_empty) {
+            if let Some(ref desc) = other.description {
+                if !desc.is_empty() {
+                    self.description = Some(desc.clone());
+                }
+            }
+        }
+        for (key, value) in &other.meta {
+            self.meta
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        self.created_at = self.created_at.max(other.created_at);
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,22 +54,78 @@ pub struct Label {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub sessions: Vec<Session>,
+    /// Color name used for the label in human output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Each description set through `set_description`, oldest first, the
+    /// current one included. A cleared description is recorded as an empty
+    /// string.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub description_history: Vec<(DateTime<Utc>, String)>,
 }
 
+/// How many entries `Label::description_history` keeps; older ones are dropped.
+pub const DESCRIPTION_HISTORY_LIMIT: usize = 20;
+
 impl Label {
     pub fn new(description: Option<String>) -> Self {
         Self {
             description,
             sessions: Vec::new(),
+            color: None,
+            description_history: Vec::new(),
+        }
+    }
+
+    /// Change the description, recording the new value in the history unless
+    /// it is the same as the current one or the last one recorded. Returns
+    /// whether the description changed.
+    pub fn set_description(&mut self, description: Option<String>, now: DateTime<Utc>) -> bool {
+        if description == self.description {
+            return false;
         }
+        let text = description.clone().unwrap_or_default();
+        if self.description_history.last().map(|(_, last)| last) != Some(&text) {
+            self.description_history.push((now, text));
+            let excess = self
+                .description_history
+                .len()
+                .saturating_sub(DESCRIPTION_HISTORY_LIMIT);
+            self.description_history.drain(..excess);
+        }
+        self.description = description;
+        true
     }
 
     pub fn add_session(&mut self, session: Session) {
         self.sessions.push(session);
     }
 
+    /// The session listings show first, by `newest_first`.
     pub fn latest_session(&self) -> Option<&Session> {
-        self.sessions.iter().max_by_key(|s| s.created_at)
+        self.sessions.iter().min_by(|a, b| newest_first(a, b))
+    }
+
+    /// When the most recent session was created, or `None` for an empty label.
+    pub fn last_activity(&self) -> Option<DateTime<Utc>> {
+        self.latest_session().map(|s| s.created_at)
+    }
+
+    pub fn find_session(&self, session_id: &str) -> Option<&Session> {
+        self.sessions.iter().find(|s| s.session_id == session_id)
+    }
+
+    /// Whether the label already holds `session_id` recorded at `path`.
+    pub fn has_session_at(&self, session_id: &str, path: &str) -> bool {
+        self.sessions
+            .iter()
+            .any(|s| s.session_id == session_id && s.path == path)
+    }
+
+    pub fn find_session_mut(&mut self, session_id: &str) -> Option<&mut Session> {
+        self.sessions
+            .iter_mut()
+            .find(|s| s.session_id == session_id)
     }
 
     pub fn remove_session(&mut self, session_id: &str) -> bool {
@@ -39,43 +133,342 @@ impl Label {
         self.sessions.retain(|s| s.session_id != session_id);
         self.sessions.len() < len_before
     }
+
+    /// Collapse sessions that share an id into one, keeping the newest copy
+    /// (merging the others into it when `merge` is set). Each surviving session
+    /// stays where its id first appeared. Returns how many sessions were removed.
+    pub fn dedup_sessions(&mut self, merge: bool) -> usize {
+        let mut deduped: Vec<Session> = Vec::with_capacity(self.sessions.len());
+        let mut duplicates: Vec<Vec<Session>> = Vec::new();
+
+        for session in self.sessions.drain(..) {
+            match deduped
+                .iter()
+                .position(|s| s.session_id == session.session_id)
+            {
+                Some(index) => duplicates[index].push(session),
+                None => {
+                    deduped.push(session);
+                    duplicates.push(Vec::new());
+                }
+            }
+        }
+
+        let mut removed = 0;
+        for (kept, others) in deduped.iter_mut().zip(duplicates) {
+            removed += others.len();
+            for other in others {
+                if other.created_at > kept.created_at {
+                    let older = std::mem::replace(kept, other);
+                    if merge {
+                        kept.merge_from(&older);
+                    }
+                } else if merge {
+                    kept.merge_from(&other);
+                }
+            }
+        }
+
+        self.sessions = deduped;
+        removed
+    }
+
+    /// Remove every session, keeping the label itself. Returns how many were removed.
+    pub fn clear_sessions(&mut self) -> usize {
+        let removed = self.sessions.len();
+        self.sessions.clear();
+        removed
+    }
+
+    /// Remove sessions created before `cutoff`. Returns how many were removed.
+    pub fn remove_sessions_before(&mut self, cutoff: DateTime<Utc>) -> usize {
+        self.remove_sessions_where(|s| s.created_at < cutoff)
+    }
+
+    /// Keep only the session(s) with id `session_id`, removing every other one.
+    /// All copies of a duplicated id are kept. Returns how many were removed.
+    pub fn retain_only(&mut self, session_id: &str) -> Result<usize> {
+        if self.find_session(session_id).is_none() {
+            bail!("Session '{}' not found", session_id);
+        }
+        Ok(self.remove_sessions_where(|s| s.session_id != session_id))
+    }
+
+    /// Change the id of the session(s) with id `old` to `new`, keeping
+    /// everything else about them. Fails if `old` is missing or another
+    /// session in the label already has `new`.
+    pub fn rename_session(&mut self, old: &str, new: &str) -> Result<()> {
+        if self.find_session(old).is_none() {
+            bail!("Session '{}' not found", old);
+        }
+        if old != new && self.find_session(new).is_some() {
+            bail!("Session '{}' already exists in this label", new);
+        }
+        for session in self.sessions.iter_mut().filter(|s| s.session_id == old) {
+            session.session_id = new.to_string();
+        }
+        Ok(())
+    }
+
+    /// Set the creation time of the session(s) with id `session_id` to `now`,
+    /// making it the label's latest. Returns whether the id was found.
+    pub fn touch_session(&mut self, session_id: &str, now: DateTime<Utc>) -> bool {
+        let mut found = false;
+        for session in self
+            .sessions
+            .iter_mut()
+            .filter(|s| s.session_id == session_id)
+        {
+            session.created_at = now;
+            found = true;
+        }
+        found
+    }
+
+    /// Move every session's creation time up to `now` while keeping their
+    /// order: the newest becomes `now` and each older one is a second earlier
+    /// than the one after it (in `newest_first` order, as in listings).
+    /// Returns how many were touched.
+    pub fn touch_all(&mut self, now: DateTime<Utc>) -> usize {
+        let mut order: Vec<usize> = (0..self.sessions.len()).collect();
+        order.sort_by(|&a, &b| newest_first(&self.sessions[a], &self.sessions[b]));
+        for (rank, index) in order.into_iter().enumerate() {
+            self.sessions[index].created_at = now - Duration::seconds(rank as i64);
+        }
+        self.sessions.len()
+    }
+
+    /// Remove every session matching `pred`. Returns how many were removed.
+    pub fn remove_sessions_where<F>(&mut self, mut pred: F) -> usize
+    where
+        F: FnMut(&Session) -> bool,
+    {
+        let before = self.sessions.len();
+        self.sessions.retain(|s| !pred(s));
+        before - self.sessions.len()
+    }
+}
+
+/// Display order for sessions: newest first, ties broken by session id so
+/// sessions created at the same instant always come out in the same order.
+pub fn newest_first(a: &Session, b: &Session) -> Ordering {
+    b.created_at
+        .cmp(&a.created_at)
+        .then_with(|| a.session_id.cmp(&b.session_id))
+}
+
+/// What happened to a label when it was imported into a store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportOutcome {
+    /// The label did not exist and was inserted as-is.
+    Added,
+    /// The label existed; `sessions` new sessions were merged into it, and
+    /// `conflicts` lists ids present on both sides with different timestamps.
+    Merged {
+        sessions: usize,
+        conflicts: Vec<SessionConflict>,
+    },
+    /// The label existed and was replaced.
+    Overwritten,
+    /// The label existed and was left alone.
+    Skipped,
+}
+
+/// What to do with an imported label whose name is already taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelResolution {
+    /// Append sessions with new ids; fill in the description only if unset.
+    Merge,
+    /// Replace the existing label with the imported one.
+    Overwrite,
+    /// Keep the existing label and drop the imported one.
+    Skip,
+}
+
+/// The label that results from importing `incoming` over `existing` under
+/// `resolution`, and how it came about. When merging, a session whose id
+/// exists with a different `created_at` is resolved according to `keep`.
+pub fn resolve_label_conflict(
+    existing: &Label,
+    incoming: Label,
+    resolution: LabelResolution,
+    keep: KeepOnConflict,
+) -> (Label, ImportOutcome) {
+    let mut merged = match resolution {
+        LabelResolution::Skip => return (existing.clone(), ImportOutcome::Skipped),
+        LabelResolution::Overwrite => return (incoming, ImportOutcome::Overwritten),
+        LabelResolution::Merge => existing.clone(),
+    };
+
+    if merged.description.is_none() {
+        merged.set_description(incoming.description, Utc::now());
+    }
+    let mut sessions = 0;
+    let mut conflicts = Vec::new();
+    for session in incoming.sessions {
+        let Some(current) = merged.find_session_mut(&session.session_id) else {
+            merged.add_session(session);
+            sessions += 1;
+            continue;
+        };
+        if current.created_at == session.created_at {
+            continue;
+        }
+
+        let kept_incoming =
+            keep == KeepOnConflict::Newer && session.created_at > current.created_at;
+        conflicts.push(SessionConflict {
+            session_id: session.session_id.clone(),
+            existing: current.created_at,
+            incoming: session.created_at,
+            kept_incoming,
+        });
+        if kept_incoming {
+            *current = session;
+        }
+    }
+    (
+        merged,
+        ImportOutcome::Merged {
+            sessions,
+            conflicts,
+        },
+    )
+}
+
+/// Which copy to keep when an imported session has the same id as an existing
+/// one but a different `created_at`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeepOnConflict {
+    /// Whichever copy has the later `created_at`
+    #[default]
+    Newer,
+    /// The session already in the store
+    Existing,
+}
+
+/// A session id found in both stores with different `created_at` values,
+/// most likely the same session edited separately.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionConflict {
+    pub session_id: String,
+    pub existing: DateTime<Utc>,
+    pub incoming: DateTime<Utc>,
+    /// Whether the imported copy replaced the existing one.
+    pub kept_incoming: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Store {
     pub labels: HashMap<String, Label>,
+    #[serde(default, skip_serializing_if = "Settings::is_default")]
+    pub settings: Settings,
 }
 
 impl Store {
     pub fn new() -> Self {
         Self {
             labels: HashMap::new(),
+            settings: Settings::default(),
+        }
+    }
+
+    /// The label name `name` refers to: itself when such a label exists,
+    /// otherwise the target of an alias called `name`. Real names always win
+    /// over aliases. Every label lookup below goes through this.
+    pub fn resolve_label_name<'a>(&'a self, name: &'a str) -> &'a str {
+        if self.labels.contains_key(name) {
+            return name;
+        }
+        match self.settings.aliases.get(name) {
+            Some(target) => target,
+            None => name,
         }
     }
 
     pub fn get_label(&self, name: &str) -> Option<&Label> {
-        self.labels.get(name)
+        self.labels.get(self.resolve_label_name(name))
     }
 
     pub fn get_label_mut(&mut self, name: &str) -> Option<&mut Label> {
-        self.labels.get_mut(name)
+        let name = self.resolve_label_name(name).to_string();
+        self.labels.get_mut(&name)
     }
 
     pub fn get_or_create_label(&mut self, name: &str) -> &mut Label {
+        let name = self.resolve_label_name(name).to_string();
+        self.labels.entry(name).or_insert_with(|| Label::new(None))
+    }
+
+    /// Every session in the store with the name of the label holding it, in
+    /// label name order.
+    pub fn sessions(&self) -> impl Iterator<Item = (&String, &Session)> {
+        let mut labels: Vec<(&String, &Label)> = self.labels.iter().collect();
+        labels.sort_by_key(|(name, _)| *name);
+        labels
+            .into_iter()
+            .flat_map(|(name, label)| label.sessions.iter().map(move |s| (name, s)))
+    }
+
+    /// The first label, by name, holding a session with this id.
+    pub fn find_session_label(&self, session_id: &str) -> Option<&String> {
         self.labels
-            .entry(name.to_string())
-            .or_insert_with(|| Label::new(None))
+            .iter()
+            .filter(|(_, label)| label.find_session(session_id).is_some())
+            .map(|(name, _)| name)
+            .min()
     }
 
     pub fn remove_label(&mut self, name: &str) -> bool {
-        self.labels.remove(name).is_some()
+        let name = self.resolve_label_name(name).to_string();
+        self.labels.remove(&name).is_some()
+    }
+
+    /// Insert an incoming label. An existing label with the same name is
+    /// merged into, replaced or kept according to `resolution` (see
+    /// `resolve_label_conflict`).
+    pub fn import_label(
+        &mut self,
+        name: String,
+        label: Label,
+        resolution: LabelResolution,
+        keep: KeepOnConflict,
+    ) -> ImportOutcome {
+        let Some(existing) = self.labels.get(&name) else {
+            self.labels.insert(name, label);
+            return ImportOutcome::Added;
+        };
+
+        let (label, outcome) = resolve_label_conflict(existing, label, resolution, keep);
+        self.labels.insert(name, label);
+        outcome
+    }
+
+    /// Copy `source` into a new label `dest`, including its sessions only when
+    /// `with_sessions` is set. Returns false if `source` is missing or `dest` exists.
+    pub fn clone_label(&mut self, source: &str, dest: &str, with_sessions: bool) -> bool {
+        if self.labels.contains_key(dest) {
+            return false;
+        }
+        let Some(source_label) = self.get_label(source) else {
+            return false;
+        };
+
+        let mut cloned = Label::new(source_label.description.clone());
+        cloned.description_history = source_label.description_history.clone();
+        cloned.color = source_label.color.clone();
+        if with_sessions {
+            cloned.sessions = source_label.sessions.clone();
+        }
+        self.labels.insert(dest.to_string(), cloned);
+        true
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::TimeZone;
+    use chrono::{Datelike, TimeZone};
 
     fn create_test_session(id: &str, path: &str, desc: Option<&str>) -> Session {
         Session {
@@ -83,6 +476,7 @@ mod tests {
             path: path.to_string(),
             description: desc.map(|s| s.to_string()),
             created_at: Utc::now(),
+            ..Default::default()
         }
     }
 
@@ -92,6 +486,7 @@ mod tests {
             path: "/test/path".to_string(),
             description: None,
             created_at: Utc.with_ymd_and_hms(year, month, day, 12, 0, 0).unwrap(),
+            ..Default::default()
         }
     }
 
@@ -137,6 +532,105 @@ mod tests {
         assert!(!json.contains("description"));
     }
 
+    #[test]
+    fn test_session_serialization_skips_empty_meta() {
+        let session = create_test_session("sess123", "/project", None);
+
+        let json = serde_json::to_string(&session).unwrap();
+
+        assert!(!json.contains("meta"));
+    }
+
+    #[test]
+    fn test_session_meta_roundtrip() {
+        let mut session = create_test_session("sess123", "/project", None);
+        session.meta.insert("model".to_string(), "opus".to_string());
+
+        let json = serde_json::to_string(&session).unwrap();
+        let deserialized: Session = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized.meta.get("model"), Some(&"opus".to_string()));
+    }
+
+    #[test]
+    fn test_session_without_meta_field_deserializes() {
+        let json = r#"{"session_id":"s1","path":"/p","created_at":"2024-01-01T12:00:00Z"}"#;
+
+        let session: Session = serde_json::from_str(json).unwrap();
+
+        assert!(session.meta.is_empty());
+    }
+
+    // ==================== Session Merge Tests ====================
+
+    #[test]
+    fn test_merge_from_fills_missing_description() {
+        let mut newer = create_session_with_time("dup", 2024, 6, 1);
+        let mut older = create_session_with_time("dup", 2024, 1, 1);
+        older.description = Some("Older notes".to_string());
+
+        newer.merge_from(&older);
+
+        assert_eq!(newer.description, Some("Older notes".to_string()));
+        assert_eq!(
+            newer.created_at,
+            Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_merge_from_keeps_own_description() {
+        let mut newer = create_session_with_time("dup", 2024, 6, 1);
+        newer.description = Some("Mine".to_string());
+        let mut older = create_session_with_time("dup", 2024, 1, 1);
+        older.description = Some("Theirs".to_string());
+
+        newer.merge_from(&older);
+
+        assert_eq!(newer.description, Some("Mine".to_string()));
+    }
+
+    #[test]
+    fn test_merge_from_empty_description_is_replaced() {
+        let mut newer = create_session_with_time("dup", 2024, 6, 1);
+        newer.description = Some(String::new());
+        let mut older = create_session_with_time("dup", 2024, 1, 1);
+        older.description = Some("Real notes".to_string());
+
+        newer.merge_from(&older);
+
+        assert_eq!(newer.description, Some("Real notes".to_string()));
+    }
+
+    #[test]
+    fn test_merge_from_unions_meta() {
+        let mut newer = create_session_with_time("dup", 2024, 6, 1);
+        newer.meta.insert("model".to_string(), "opus".to_string());
+        let mut older = create_session_with_time("dup", 2024, 1, 1);
+        older.meta.insert("model".to_string(), "sonnet".to_string());
+        older
+            .meta
+            .insert("reviewer".to_string(), "alice".to_string());
+
+        newer.merge_from(&older);
+
+        assert_eq!(newer.meta.len(), 2);
+        assert_eq!(newer.meta.get("model"), Some(&"opus".to_string()));
+        assert_eq!(newer.meta.get("reviewer"), Some(&"alice".to_string()));
+    }
+
+    #[test]
+    fn test_merge_from_takes_newer_timestamp_and_missing_path() {
+        let mut older = create_session_with_time("dup", 2024, 1, 1);
+        older.path = String::new();
+        let newer = create_session_with_time("dup", 2024, 6, 1);
+
+        older.merge_from(&newer);
+
+        assert_eq!(older.created_at, newer.created_at);
+        assert_eq!(older.path, "/test/path");
+    }
+
     // ==================== Label Tests ====================
 
     #[test]
@@ -177,6 +671,16 @@ mod tests {
         assert_eq!(label.sessions.len(), 3);
     }
 
+    #[test]
+    fn test_label_has_session_at_needs_id_and_path() {
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("sess1", "/path1", None));
+
+        assert!(label.has_session_at("sess1", "/path1"));
+        assert!(!label.has_session_at("sess1", "/path2"));
+        assert!(!label.has_session_at("sess2", "/path1"));
+    }
+
     #[test]
     fn test_label_latest_session_empty() {
         let label = Label::new(None);
@@ -206,6 +710,70 @@ mod tests {
         assert_eq!(latest.session_id, "newest");
     }
 
+    #[test]
+    fn test_label_latest_session_breaks_ties_by_id() {
+        let mut label = Label::new(None);
+        label.add_session(create_session_with_time("b", 2024, 2, 1));
+        label.add_session(create_session_with_time("a", 2024, 2, 1));
+        label.add_session(create_session_with_time("c", 2024, 2, 1));
+
+        // Whatever the insertion order, the first session `list` shows
+        let mut sorted: Vec<&Session> = label.sessions.iter().collect();
+        sorted.sort_by(|a, b| newest_first(a, b));
+        assert_eq!(label.latest_session().unwrap().session_id, "a");
+        assert_eq!(sorted[0].session_id, "a");
+    }
+
+    #[test]
+    fn test_newest_first_orders_by_time_then_id() {
+        let older = create_session_with_time("a", 2024, 1, 1);
+        let tie_b = create_session_with_time("b", 2024, 2, 1);
+        let tie_c = create_session_with_time("c", 2024, 2, 1);
+
+        assert_eq!(newest_first(&tie_b, &older), Ordering::Less);
+        assert_eq!(newest_first(&tie_b, &tie_c), Ordering::Less);
+        assert_eq!(newest_first(&tie_c, &tie_b), Ordering::Greater);
+        assert_eq!(newest_first(&tie_b, &tie_b), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_newest_first_is_stable_regardless_of_input_order() {
+        let sessions = [
+            create_session_with_time("z", 2024, 2, 1),
+            create_session_with_time("m", 2024, 2, 1),
+            create_session_with_time("a", 2024, 1, 1),
+            create_session_with_time("b", 2024, 2, 1),
+        ];
+
+        let mut forward: Vec<&Session> = sessions.iter().collect();
+        let mut backward: Vec<&Session> = sessions.iter().rev().collect();
+        forward.sort_by(|a, b| newest_first(a, b));
+        backward.sort_by(|a, b| newest_first(a, b));
+
+        let ids = |v: &[&Session]| v.iter().map(|s| s.session_id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&forward), vec!["b", "m", "z", "a"]);
+        assert_eq!(ids(&forward), ids(&backward));
+    }
+
+    #[test]
+    fn test_label_last_activity() {
+        let mut label = Label::new(None);
+        label.add_session(create_session_with_time("old", 2023, 1, 1));
+        label.add_session(create_session_with_time("newest", 2024, 6, 15));
+
+        assert_eq!(
+            label.last_activity(),
+            Some(Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_label_last_activity_empty() {
+        let label = Label::new(None);
+
+        assert_eq!(label.last_activity(), None);
+    }
+
     #[test]
     fn test_label_remove_session_success() {
         let mut label = Label::new(None);
@@ -230,6 +798,18 @@ mod tests {
         assert_eq!(label.sessions.len(), 1);
     }
 
+    #[test]
+    fn test_label_find_session_mut() {
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("sess1", "/path1", None));
+
+        let session = label.find_session_mut("sess1").unwrap();
+        session.description = Some("Found".to_string());
+
+        assert_eq!(label.sessions[0].description, Some("Found".to_string()));
+        assert!(label.find_session_mut("missing").is_none());
+    }
+
     #[test]
     fn test_label_remove_session_from_empty() {
         let mut label = Label::new(None);
@@ -239,6 +819,301 @@ mod tests {
         assert!(!removed);
     }
 
+    #[test]
+    fn test_label_dedup_sessions_keeps_newest() {
+        let mut label = Label::new(None);
+        let mut old = create_session_with_time("dup", 2024, 1, 1);
+        old.description = Some("Old".to_string());
+        label.add_session(old);
+        label.add_session(create_session_with_time("unique", 2024, 2, 1));
+        label.add_session(create_session_with_time("dup", 2024, 6, 1));
+
+        let removed = label.dedup_sessions(false);
+
+        assert_eq!(removed, 1);
+        assert_eq!(label.sessions.len(), 2);
+        assert_eq!(label.sessions[0].session_id, "dup");
+        assert_eq!(label.sessions[0].description, None);
+        assert_eq!(
+            label.sessions[0].created_at,
+            Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+        );
+        assert_eq!(label.sessions[1].session_id, "unique");
+    }
+
+    #[test]
+    fn test_label_dedup_sessions_merge() {
+        let mut label = Label::new(None);
+        let mut old = create_session_with_time("dup", 2024, 1, 1);
+        old.description = Some("Old".to_string());
+        old.meta.insert("a".to_string(), "1".to_string());
+        label.add_session(old);
+        let mut new = create_session_with_time("dup", 2024, 6, 1);
+        new.meta.insert("b".to_string(), "2".to_string());
+        label.add_session(new);
+        label.add_session(create_session_with_time("dup", 2024, 3, 1));
+
+        let removed = label.dedup_sessions(true);
+
+        assert_eq!(removed, 2);
+        assert_eq!(label.sessions.len(), 1);
+        let merged = &label.sessions[0];
+        assert_eq!(merged.description, Some("Old".to_string()));
+        assert_eq!(merged.meta.len(), 2);
+        assert_eq!(
+            merged.created_at,
+            Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_label_dedup_sessions_no_duplicates() {
+        let mut label = Label::new(None);
+        label.add_session(create_session_with_time("a", 2024, 1, 1));
+        label.add_session(create_session_with_time("b", 2024, 1, 2));
+
+        assert_eq!(label.dedup_sessions(true), 0);
+        assert_eq!(label.sessions.len(), 2);
+    }
+
+    #[test]
+    fn test_label_clear_sessions() {
+        let mut label = Label::new(Some("Keep me".to_string()));
+        label.add_session(create_test_session("sess1", "/path1", None));
+        label.add_session(create_test_session("sess2", "/path2", None));
+
+        let removed = label.clear_sessions();
+
+        assert_eq!(removed, 2);
+        assert!(label.sessions.is_empty());
+        assert_eq!(label.description, Some("Keep me".to_string()));
+        assert_eq!(label.clear_sessions(), 0);
+    }
+
+    #[test]
+    fn test_label_remove_sessions_before() {
+        let mut label = Label::new(None);
+        let mut old = create_test_session("old", "/p", None);
+        old.created_at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut edge = create_test_session("edge", "/p", None);
+        edge.created_at = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+        label.add_session(old);
+        label.add_session(edge);
+
+        let cutoff = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+
+        assert_eq!(label.remove_sessions_before(cutoff), 1);
+        assert_eq!(label.sessions.len(), 1);
+        assert_eq!(label.sessions[0].session_id, "edge");
+        assert_eq!(label.remove_sessions_before(cutoff), 0);
+    }
+
+    #[test]
+    fn test_label_retain_only() {
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("s1", "/p", None));
+        label.add_session(create_test_session("s2", "/p", None));
+        label.add_session(create_test_session("s3", "/p", None));
+
+        assert_eq!(label.retain_only("s2").unwrap(), 2);
+        assert_eq!(label.sessions.len(), 1);
+        assert_eq!(label.sessions[0].session_id, "s2");
+    }
+
+    #[test]
+    fn test_label_retain_only_missing_id_changes_nothing() {
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("s1", "/p", None));
+
+        assert!(label.retain_only("nope").is_err());
+        assert_eq!(label.sessions.len(), 1);
+    }
+
+    #[test]
+    fn test_label_retain_only_keeps_every_copy_of_duplicate_id() {
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("dup", "/a", None));
+        label.add_session(create_test_session("other", "/p", None));
+        label.add_session(create_test_session("dup", "/b", None));
+
+        assert_eq!(label.retain_only("dup").unwrap(), 1);
+        let paths: Vec<&str> = label.sessions.iter().map(|s| s.path.as_str()).collect();
+        assert_eq!(paths, ["/a", "/b"]);
+    }
+
+    #[test]
+    fn test_label_rename_session_keeps_fields() {
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("old-id", "/work", Some("Notes")));
+        label.add_session(create_test_session("other", "/p", None));
+        let created_at = label.sessions[0].created_at;
+
+        label.rename_session("old-id", "new-id").unwrap();
+
+        let renamed = label.find_session("new-id").unwrap();
+        assert_eq!(renamed.path, "/work");
+        assert_eq!(renamed.description, Some("Notes".to_string()));
+        assert_eq!(renamed.created_at, created_at);
+        assert!(label.find_session("old-id").is_none());
+        // Position in the label is unchanged
+        assert_eq!(label.sessions[0].session_id, "new-id");
+    }
+
+    #[test]
+    fn test_label_rename_session_missing_source() {
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("s1", "/p", None));
+
+        let err = label.rename_session("missing", "s2").unwrap_err();
+
+        assert!(err.to_string().contains("missing"));
+        assert_eq!(label.sessions[0].session_id, "s1");
+    }
+
+    #[test]
+    fn test_label_set_description_records_history() {
+        let mut label = Label::new(None);
+        let t1 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let t2 = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+        let t3 = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+
+        assert!(label.set_description(Some("first".to_string()), t1));
+        assert!(label.set_description(Some("second".to_string()), t2));
+        assert!(label.set_description(None, t3));
+
+        assert_eq!(label.description, None);
+        assert_eq!(
+            label.description_history,
+            vec![
+                (t1, "first".to_string()),
+                (t2, "second".to_string()),
+                (t3, String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_label_set_description_unchanged_is_noop() {
+        let mut label = Label::new(None);
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        label.set_description(Some("same".to_string()), now);
+        assert!(!label.set_description(Some("same".to_string()), now));
+        assert!(!label.set_description(Some("same".to_string()), now));
+        assert_eq!(label.description_history.len(), 1);
+
+        // Clearing an already empty description isn't recorded either
+        let mut empty = Label::new(None);
+        assert!(!empty.set_description(None, now));
+        assert!(empty.description_history.is_empty());
+    }
+
+    #[test]
+    fn test_label_description_history_is_capped() {
+        let mut label = Label::new(None);
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let total = DESCRIPTION_HISTORY_LIMIT + 5;
+
+        for i in 0..total {
+            label.set_description(Some(format!("v{}", i)), start + Duration::days(i as i64));
+        }
+
+        assert_eq!(label.description_history.len(), DESCRIPTION_HISTORY_LIMIT);
+        assert_eq!(label.description_history[0].1, "v5");
+        assert_eq!(
+            label.description_history.last().unwrap().1,
+            format!("v{}", total - 1)
+        );
+    }
+
+    #[test]
+    fn test_label_touch_session() {
+        let mut label = Label::new(None);
+        label.add_session(create_session_with_time("old", 2024, 1, 1));
+        label.add_session(create_session_with_time("new", 2024, 6, 1));
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+
+        assert!(label.touch_session("old", now));
+        assert!(!label.touch_session("missing", now));
+
+        assert_eq!(label.latest_session().unwrap().session_id, "old");
+        assert_eq!(label.find_session("new").unwrap().created_at.year(), 2024);
+    }
+
+    #[test]
+    fn test_label_touch_all_advances_and_keeps_order() {
+        let mut label = Label::new(None);
+        label.add_session(create_session_with_time("middle", 2024, 3, 1));
+        label.add_session(create_session_with_time("newest", 2024, 6, 1));
+        label.add_session(create_session_with_time("oldest", 2023, 1, 1));
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+
+        assert_eq!(label.touch_all(now), 3);
+
+        let at = |id: &str| label.find_session(id).unwrap().created_at;
+        assert_eq!(at("newest"), now);
+        assert_eq!(at("middle"), now - Duration::seconds(1));
+        assert_eq!(at("oldest"), now - Duration::seconds(2));
+    }
+
+    #[test]
+    fn test_label_touch_all_ties_break_by_id() {
+        let mut label = Label::new(None);
+        label.add_session(create_session_with_time("b", 2024, 1, 1));
+        label.add_session(create_session_with_time("a", 2024, 1, 1));
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+
+        label.touch_all(now);
+
+        assert_eq!(label.find_session("a").unwrap().created_at, now);
+        assert_eq!(
+            label.find_session("b").unwrap().created_at,
+            now - Duration::seconds(1)
+        );
+        assert_eq!(Label::new(None).touch_all(now), 0);
+    }
+
+    #[test]
+    fn test_label_rename_session_colliding_target() {
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("s1", "/a", None));
+        label.add_session(create_test_session("s2", "/b", None));
+
+        let err = label.rename_session("s1", "s2").unwrap_err();
+
+        assert!(err.to_string().contains("already exists"));
+        let ids: Vec<&str> = label
+            .sessions
+            .iter()
+            .map(|s| s.session_id.as_str())
+            .collect();
+        assert_eq!(ids, ["s1", "s2"]);
+    }
+
+    #[test]
+    fn test_label_remove_sessions_where_description() {
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("s1", "/p", Some("scratch: try a fix")));
+        label.add_session(create_test_session("s2", "/p", Some("Real work")));
+        label.add_session(create_test_session("s3", "/p", None));
+        label.add_session(create_test_session("s4", "/p", Some("more scratch")));
+
+        let is_scratch = |s: &Session| {
+            s.description
+                .as_deref()
+                .is_some_and(|d| d.contains("scratch"))
+        };
+
+        assert_eq!(label.remove_sessions_where(is_scratch), 2);
+        let ids: Vec<&str> = label
+            .sessions
+            .iter()
+            .map(|s| s.session_id.as_str())
+            .collect();
+        assert_eq!(ids, ["s2", "s3"]);
+        assert_eq!(label.remove_sessions_where(is_scratch), 0);
+    }
+
     #[test]
     fn test_label_serialization_roundtrip() {
         let mut label = Label::new(Some("My label".to_string()));
@@ -353,6 +1228,345 @@ mod tests {
         assert!(!removed);
     }
 
+    #[test]
+    fn test_store_clone_label_description_only() {
+        let mut store = Store::new();
+        let mut label = Label::new(Some("Sprint".to_string()));
+        label.add_session(create_test_session("s1", "/p1", None));
+        store.labels.insert("sprint-7".to_string(), label);
+
+        assert!(store.clone_label("sprint-7", "sprint-8", false));
+
+        let cloned = store.get_label("sprint-8").unwrap();
+        assert_eq!(cloned.description, Some("Sprint".to_string()));
+        assert!(cloned.sessions.is_empty());
+        assert_eq!(store.get_label("sprint-7").unwrap().sessions.len(), 1);
+    }
+
+    #[test]
+    fn test_store_clone_label_keeps_description_history() {
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        label.set_description(Some("First".to_string()), at);
+        label.set_description(Some("Second".to_string()), at);
+        store.labels.insert("source".to_string(), label);
+
+        assert!(store.clone_label("source", "dest", false));
+
+        assert_eq!(
+            store.get_label("dest").unwrap().description_history,
+            store.get_label("source").unwrap().description_history
+        );
+        assert_eq!(
+            store.get_label("dest").unwrap().description_history.len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_store_clone_label_keeps_color() {
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.color = Some("bright_red".to_string());
+        store.labels.insert("source".to_string(), label);
+
+        assert!(store.clone_label("source", "dest", false));
+
+        assert_eq!(
+            store.get_label("dest").unwrap().color.as_deref(),
+            Some("bright_red")
+        );
+    }
+
+    #[test]
+    fn test_store_clone_label_with_sessions() {
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("s1", "/p1", None));
+        label.add_session(create_test_session("s2", "/p2", None));
+        store.labels.insert("source".to_string(), label);
+
+        assert!(store.clone_label("source", "dest", true));
+
+        assert_eq!(store.get_label("dest").unwrap().sessions.len(), 2);
+    }
+
+    #[test]
+    fn test_store_clone_label_missing_source_or_existing_dest() {
+        let mut store = Store::new();
+        store.labels.insert(
+            "source".to_string(),
+            Label::new(Some("Original".to_string())),
+        );
+        store
+            .labels
+            .insert("dest".to_string(), Label::new(Some("Keep me".to_string())));
+
+        assert!(!store.clone_label("missing", "new", false));
+        assert!(!store.clone_label("source", "dest", false));
+        assert_eq!(
+            store.get_label("dest").unwrap().description,
+            Some("Keep me".to_string())
+        );
+    }
+
+    #[test]
+    fn test_store_import_label_added() {
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("s1", "/p1", None));
+
+        let outcome = store.import_label(
+            "new".to_string(),
+            label,
+            LabelResolution::Merge,
+            KeepOnConflict::Newer,
+        );
+
+        assert_eq!(outcome, ImportOutcome::Added);
+        assert_eq!(store.get_label("new").unwrap().sessions.len(), 1);
+    }
+
+    #[test]
+    fn test_store_import_label_merges_new_sessions() {
+        let mut store = Store::new();
+        let mut existing = Label::new(None);
+        existing.add_session(create_test_session("s1", "/mine", None));
+        store.labels.insert("shared".to_string(), existing);
+
+        let mut incoming = Label::new(Some("Theirs".to_string()));
+        let mut same = create_test_session("s1", "/theirs", None);
+        same.created_at = store.labels["shared"].sessions[0].created_at;
+        incoming.add_session(same);
+        incoming.add_session(create_test_session("s2", "/theirs", None));
+
+        let outcome = store.import_label(
+            "shared".to_string(),
+            incoming,
+            LabelResolution::Merge,
+            KeepOnConflict::Newer,
+        );
+
+        assert_eq!(
+            outcome,
+            ImportOutcome::Merged {
+                sessions: 1,
+                conflicts: Vec::new()
+            }
+        );
+        let label = store.get_label("shared").unwrap();
+        assert_eq!(label.description, Some("Theirs".to_string()));
+        assert_eq!(label.sessions.len(), 2);
+        assert_eq!(label.sessions[0].path, "/mine");
+    }
+
+    fn conflicting_import(keep: KeepOnConflict, incoming_newer: bool) -> (Store, ImportOutcome) {
+        let now = Utc::now();
+        let (mine_at, theirs_at) = if incoming_newer {
+            (now - chrono::Duration::days(1), now)
+        } else {
+            (now, now - chrono::Duration::days(1))
+        };
+
+        let mut store = Store::new();
+        let mut existing = Label::new(None);
+        let mut mine = create_test_session("s1", "/mine", None);
+        mine.created_at = mine_at;
+        existing.add_session(mine);
+        store.labels.insert("shared".to_string(), existing);
+
+        let mut incoming = Label::new(None);
+        let mut theirs = create_test_session("s1", "/theirs", None);
+        theirs.created_at = theirs_at;
+        incoming.add_session(theirs);
+
+        let outcome =
+            store.import_label("shared".to_string(), incoming, LabelResolution::Merge, keep);
+        (store, outcome)
+    }
+
+    #[test]
+    fn test_store_import_label_conflict_newer_wins() {
+        let (store, outcome) = conflicting_import(KeepOnConflict::Newer, true);
+
+        let ImportOutcome::Merged {
+            sessions,
+            conflicts,
+        } = outcome
+        else {
+            panic!("expected a merge");
+        };
+        assert_eq!(sessions, 0);
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].kept_incoming);
+        let label = store.get_label("shared").unwrap();
+        assert_eq!(label.sessions.len(), 1);
+        assert_eq!(label.sessions[0].path, "/theirs");
+    }
+
+    #[test]
+    fn test_store_import_label_conflict_older_incoming_is_dropped() {
+        let (store, outcome) = conflicting_import(KeepOnConflict::Newer, false);
+
+        let ImportOutcome::Merged { conflicts, .. } = outcome else {
+            panic!("expected a merge");
+        };
+        assert!(!conflicts[0].kept_incoming);
+        assert_eq!(store.get_label("shared").unwrap().sessions[0].path, "/mine");
+    }
+
+    #[test]
+    fn test_store_import_label_conflict_keep_existing() {
+        let (store, outcome) = conflicting_import(KeepOnConflict::Existing, true);
+
+        let ImportOutcome::Merged { conflicts, .. } = outcome else {
+            panic!("expected a merge");
+        };
+        assert_eq!(conflicts.len(), 1);
+        assert!(!conflicts[0].kept_incoming);
+        assert_eq!(store.get_label("shared").unwrap().sessions[0].path, "/mine");
+    }
+
+    #[test]
+    fn test_store_import_label_keeps_existing_description() {
+        let mut store = Store::new();
+        store
+            .labels
+            .insert("shared".to_string(), Label::new(Some("Mine".to_string())));
+
+        store.import_label(
+            "shared".to_string(),
+            Label::new(Some("Theirs".to_string())),
+            LabelResolution::Merge,
+            KeepOnConflict::Newer,
+        );
+
+        assert_eq!(
+            store.get_label("shared").unwrap().description,
+            Some("Mine".to_string())
+        );
+    }
+
+    #[test]
+    fn test_store_import_label_overwrite() {
+        let mut store = Store::new();
+        let mut existing = Label::new(Some("Mine".to_string()));
+        existing.add_session(create_test_session("s1", "/mine", None));
+        store.labels.insert("shared".to_string(), existing);
+
+        let outcome = store.import_label(
+            "shared".to_string(),
+            Label::new(Some("Theirs".to_string())),
+            LabelResolution::Overwrite,
+            KeepOnConflict::Newer,
+        );
+
+        assert_eq!(outcome, ImportOutcome::Overwritten);
+        let label = store.get_label("shared").unwrap();
+        assert_eq!(label.description, Some("Theirs".to_string()));
+        assert!(label.sessions.is_empty());
+    }
+
+    fn resolution_pair() -> (Label, Label) {
+        let mut existing = Label::new(Some("Mine".to_string()));
+        existing.add_session(create_session_with_time("s1", 2024, 1, 1));
+        let mut incoming = Label::new(Some("Theirs".to_string()));
+        incoming.add_session(create_session_with_time("s1", 2024, 1, 1));
+        incoming.add_session(create_session_with_time("s2", 2024, 2, 1));
+        (existing, incoming)
+    }
+
+    #[test]
+    fn test_resolve_label_conflict_skip_keeps_existing() {
+        let (existing, incoming) = resolution_pair();
+
+        let (label, outcome) = resolve_label_conflict(
+            &existing,
+            incoming,
+            LabelResolution::Skip,
+            KeepOnConflict::Newer,
+        );
+
+        assert_eq!(outcome, ImportOutcome::Skipped);
+        assert_eq!(label.description, Some("Mine".to_string()));
+        let ids: Vec<_> = label
+            .sessions
+            .iter()
+            .map(|s| s.session_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["s1"]);
+    }
+
+    #[test]
+    fn test_resolve_label_conflict_overwrite_takes_incoming() {
+        let (existing, incoming) = resolution_pair();
+
+        let (label, outcome) = resolve_label_conflict(
+            &existing,
+            incoming,
+            LabelResolution::Overwrite,
+            KeepOnConflict::Newer,
+        );
+
+        assert_eq!(outcome, ImportOutcome::Overwritten);
+        assert_eq!(label.description, Some("Theirs".to_string()));
+        let ids: Vec<_> = label
+            .sessions
+            .iter()
+            .map(|s| s.session_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["s1", "s2"]);
+    }
+
+    #[test]
+    fn test_resolve_label_conflict_merge_appends_new_sessions() {
+        let (existing, incoming) = resolution_pair();
+
+        let (label, outcome) = resolve_label_conflict(
+            &existing,
+            incoming,
+            LabelResolution::Merge,
+            KeepOnConflict::Newer,
+        );
+
+        assert_eq!(
+            outcome,
+            ImportOutcome::Merged {
+                sessions: 1,
+                conflicts: Vec::new()
+            }
+        );
+        assert_eq!(label.description, Some("Mine".to_string()));
+        let ids: Vec<_> = label
+            .sessions
+            .iter()
+            .map(|s| s.session_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["s1", "s2"]);
+    }
+
+    #[test]
+    fn test_resolve_label_conflict_merge_records_filled_description() {
+        let (_, incoming) = resolution_pair();
+        let existing = Label::new(None);
+
+        let (label, _) = resolve_label_conflict(
+            &existing,
+            incoming,
+            LabelResolution::Merge,
+            KeepOnConflict::Newer,
+        );
+
+        assert_eq!(label.description, Some("Theirs".to_string()));
+        let history: Vec<_> = label
+            .description_history
+            .iter()
+            .map(|(_, text)| text.as_str())
+            .collect();
+        assert_eq!(history, vec!["Theirs"]);
+    }
+
     #[test]
     fn test_store_serialization_roundtrip() {
         let mut store = Store::new();
@@ -388,6 +1602,142 @@ mod tests {
         assert!(deserialized.labels.is_empty());
     }
 
+    #[test]
+    fn test_store_serialization_skips_default_settings() {
+        let store = Store::new();
+
+        let json = serde_json::to_string(&store).unwrap();
+
+        assert!(!json.contains("settings"));
+    }
+
+    #[test]
+    fn test_store_settings_roundtrip() {
+        let mut store = Store::new();
+        store.settings.checksum = true;
+
+        let json = serde_json::to_string(&store).unwrap();
+        let deserialized: Store = serde_json::from_str(&json).unwrap();
+
+        assert!(deserialized.settings.checksum);
+    }
+
+    #[test]
+    fn test_store_find_session_label() {
+        let mut store = Store::new();
+        for name in ["beta", "alpha", "gamma"] {
+            let mut label = Label::new(None);
+            if name != "gamma" {
+                label.add_session(create_test_session("shared", "/p", None));
+            }
+            store.labels.insert(name.to_string(), label);
+        }
+
+        // Lowest name wins when several labels hold the id
+        assert_eq!(
+            store.find_session_label("shared").map(String::as_str),
+            Some("alpha")
+        );
+        assert!(store.find_session_label("missing").is_none());
+    }
+
+    #[test]
+    fn test_store_sessions_in_label_name_order() {
+        let mut store = Store::new();
+        for name in ["delta", "beta", "alpha", "gamma", "epsilon"] {
+            let mut label = Label::new(None);
+            label.add_session(create_test_session(&format!("{}-1", name), "/p", None));
+            label.add_session(create_test_session(&format!("{}-2", name), "/p", None));
+            store.labels.insert(name.to_string(), label);
+        }
+
+        let names: Vec<&str> = store.sessions().map(|(name, _)| name.as_str()).collect();
+        let ids: Vec<&str> = store
+            .sessions()
+            .map(|(_, s)| s.session_id.as_str())
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "alpha", "alpha", "beta", "beta", "delta", "delta", "epsilon", "epsilon", "gamma",
+                "gamma"
+            ]
+        );
+        assert_eq!(ids[..4], ["alpha-1", "alpha-2", "beta-1", "beta-2"]);
+    }
+
+    // ==================== Alias Tests ====================
+
+    fn create_aliased_store() -> Store {
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("s1", "/p", None));
+        store
+            .labels
+            .insert("2024-Q3-platform-migration".to_string(), label);
+        store
+            .settings
+            .aliases
+            .insert("mig".to_string(), "2024-Q3-platform-migration".to_string());
+        store
+    }
+
+    #[test]
+    fn test_alias_resolves_to_label() {
+        let mut store = create_aliased_store();
+
+        assert_eq!(
+            store.resolve_label_name("mig"),
+            "2024-Q3-platform-migration"
+        );
+        assert_eq!(store.get_label("mig").unwrap().sessions.len(), 1);
+
+        store
+            .get_or_create_label("mig")
+            .add_session(create_test_session("s2", "/p", None));
+        assert_eq!(store.labels.len(), 1);
+        assert_eq!(
+            store
+                .get_label("2024-Q3-platform-migration")
+                .unwrap()
+                .sessions
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_real_label_wins_over_alias() {
+        let mut store = create_aliased_store();
+        store.labels.insert("mig".to_string(), Label::new(None));
+
+        assert_eq!(store.resolve_label_name("mig"), "mig");
+        assert!(store.get_label("mig").unwrap().sessions.is_empty());
+    }
+
+    #[test]
+    fn test_alias_to_missing_label() {
+        let mut store = create_aliased_store();
+        store.labels.clear();
+
+        assert_eq!(
+            store.resolve_label_name("mig"),
+            "2024-Q3-platform-migration"
+        );
+        assert!(store.get_label("mig").is_none());
+        assert!(store.get_label_mut("mig").is_none());
+        assert!(!store.remove_label("mig"));
+    }
+
+    #[test]
+    fn test_unknown_name_resolves_to_itself() {
+        let store = create_aliased_store();
+
+        assert_eq!(store.resolve_label_name("other"), "other");
+        assert!(store.get_label("other").is_none());
+    }
+
     // ==================== Edge Case Tests ====================
 
     #[test]
@@ -489,6 +1839,7 @@ mod tests {
                     path: "/test".to_string(),
                     description: None,
                     created_at: Utc.with_ymd_and_hms(year, month, 15, 12, 0, 0).unwrap(),
+                    ..Default::default()
                 });
             }
         }
@@ -0,0 +1,267 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+use crate::data::Store;
+
+/// Something wrong with a store that `doctor` reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The same session id appears more than once in a label.
+    DuplicateSession { label: String, session_id: String },
+    /// A session's recorded directory doesn't exist.
+    MissingPath {
+        label: String,
+        session_id: String,
+        path: String,
+    },
+    /// A label has no sessions.
+    EmptyLabel { label: String },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::DuplicateSession { label, session_id } => {
+                write!(
+                    f,
+                    "{}: session '{}' appears more than once",
+                    label, session_id
+                )
+            }
+            Problem::MissingPath {
+                label,
+                session_id,
+                path,
+            } => write!(
+                f,
+                "{}: session '{}' points to a missing directory {}",
+                label, session_id, path
+            ),
+            Problem::EmptyLabel { label } => write!(f, "{}: label has no sessions", label),
+        }
+    }
+}
+
+/// Check every label of `store`, in name order, using `is_dir` to decide
+/// whether a session's path still exists. Works on any store, not just the
+/// live one.
+pub fn scan<F>(store: &Store, is_dir: F) -> Vec<Problem>
+where
+    F: Fn(&Path) -> bool,
+{
+    let mut names: Vec<&String> = store.labels.keys().collect();
+    names.sort();
+
+    let mut problems = Vec::new();
+    for name in names {
+        let label = &store.labels[name];
+        if label.sessions.is_empty() {
+            problems.push(Problem::EmptyLabel {
+                label: name.clone(),
+            });
+            continue;
+        }
+
+        let mut seen = HashSet::new();
+        let mut reported = HashSet::new();
+        for session in &label.sessions {
+            let id = session.session_id.as_str();
+            if !seen.insert(id) && reported.insert(id) {
+                problems.push(Problem::DuplicateSession {
+                    label: name.clone(),
+                    session_id: id.to_string(),
+                });
+            }
+        }
+
+        for session in &label.sessions {
+            if !is_dir(Path::new(&session.path)) {
+                problems.push(Problem::MissingPath {
+                    label: name.clone(),
+                    session_id: session.session_id.clone(),
+                    path: session.path.clone(),
+                });
+            }
+        }
+    }
+    problems
+}
+
+/// What to do about a session whose directory is missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathRepair {
+    /// Point the session at this directory instead.
+    Replace(String),
+    /// Drop the session from its label.
+    Remove,
+    /// Leave it as it is.
+    Skip,
+}
+
+/// Apply `repair` to the session `session_id` in `label` that still has
+/// `old_path` (so only the copy that was reported is touched). Returns whether
+/// the store changed.
+pub fn apply_repair(
+    store: &mut Store,
+    label: &str,
+    session_id: &str,
+    old_path: &str,
+    repair: &PathRepair,
+) -> bool {
+    let Some(label) = store.labels.get_mut(label) else {
+        return false;
+    };
+    let Some(index) = label
+        .sessions
+        .iter()
+        .position(|s| s.session_id == session_id && s.path == old_path)
+    else {
+        return false;
+    };
+
+    match repair {
+        PathRepair::Replace(path) => {
+            label.sessions[index].path = path.clone();
+            true
+        }
+        PathRepair::Remove => {
+            label.sessions.remove(index);
+            true
+        }
+        PathRepair::Skip => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Label, Session};
+
+    fn session(id: &str, path: &str) -> Session {
+        Session {
+            session_id: id.to_string(),
+            path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    // ==================== Scan Tests ====================
+
+    #[test]
+    fn test_scan_clean_store() {
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.add_session(session("s1", "/present"));
+        label.add_session(session("s2", "/present"));
+        store.labels.insert("ok".to_string(), label);
+
+        assert!(scan(&store, |_| true).is_empty());
+    }
+
+    #[test]
+    fn test_scan_reports_each_kind_of_problem() {
+        let mut store = Store::new();
+        store.labels.insert("empty".to_string(), Label::new(None));
+        let mut label = Label::new(None);
+        label.add_session(session("dup", "/present"));
+        label.add_session(session("dup", "/present"));
+        label.add_session(session("dup", "/present"));
+        label.add_session(session("gone", "/absent"));
+        store.labels.insert("messy".to_string(), label);
+
+        let problems = scan(&store, |p| p == Path::new("/present"));
+
+        assert_eq!(
+            problems,
+            vec![
+                Problem::EmptyLabel {
+                    label: "empty".to_string()
+                },
+                Problem::DuplicateSession {
+                    label: "messy".to_string(),
+                    session_id: "dup".to_string()
+                },
+                Problem::MissingPath {
+                    label: "messy".to_string(),
+                    session_id: "gone".to_string(),
+                    path: "/absent".to_string()
+                },
+            ]
+        );
+    }
+
+    // ==================== Repair Tests ====================
+
+    fn repair_store() -> Store {
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.add_session(session("s1", "/gone"));
+        label.add_session(session("s2", "/present"));
+        store.labels.insert("app".to_string(), label);
+        store
+    }
+
+    #[test]
+    fn test_apply_repair_replace_sets_path() {
+        let mut store = repair_store();
+        let repair = PathRepair::Replace("/moved".to_string());
+
+        assert!(apply_repair(&mut store, "app", "s1", "/gone", &repair));
+        assert_eq!(store.labels["app"].sessions[0].path, "/moved");
+        assert_eq!(store.labels["app"].sessions.len(), 2);
+    }
+
+    #[test]
+    fn test_apply_repair_remove_drops_session() {
+        let mut store = repair_store();
+
+        assert!(apply_repair(
+            &mut store,
+            "app",
+            "s1",
+            "/gone",
+            &PathRepair::Remove
+        ));
+        let sessions = &store.labels["app"].sessions;
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_id, "s2");
+    }
+
+    #[test]
+    fn test_apply_repair_skip_changes_nothing() {
+        let mut store = repair_store();
+
+        assert!(!apply_repair(
+            &mut store,
+            "app",
+            "s1",
+            "/gone",
+            &PathRepair::Skip
+        ));
+        assert_eq!(store.labels["app"].sessions[0].path, "/gone");
+        assert_eq!(store.labels["app"].sessions.len(), 2);
+    }
+
+    #[test]
+    fn test_apply_repair_ignores_stale_target() {
+        let mut store = repair_store();
+
+        // The path no longer matches what was reported, e.g. already fixed
+        assert!(!apply_repair(
+            &mut store,
+            "app",
+            "s1",
+            "/elsewhere",
+            &PathRepair::Remove
+        ));
+        assert!(!apply_repair(
+            &mut store,
+            "missing",
+            "s1",
+            "/gone",
+            &PathRepair::Remove
+        ));
+        assert_eq!(store.labels["app"].sessions.len(), 2);
+    }
+}
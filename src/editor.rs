@@ -0,0 +1,175 @@
+use anyhow::{bail, Context, Result};
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many names `create_temp_file` tries before giving up.
+const TEMP_FILE_ATTEMPTS: usize = 16;
+
+/// Open `path` in `$VISUAL`, `$EDITOR` or `vi` and wait for it to exit.
+pub fn run_editor(path: &Path) -> Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let Some(program) = words.next() else {
+        bail!("$EDITOR is empty");
+    };
+
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to start editor '{}'", editor))?;
+    if !status.success() {
+        bail!("Editor exited with status: {}", status);
+    }
+    Ok(())
+}
+
+/// A hard-to-guess suffix for a temp file name. `RandomState` is seeded
+/// randomly per process and per instance, which is all the randomness std
+/// offers; the time is mixed in for good measure.
+fn random_suffix() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    hasher.write_u128(nanos);
+    hasher.write_u32(std::process::id());
+    format!("{:016x}", hasher.finish())
+}
+
+/// Create a new temp file only this user can read, under a random name.
+/// `create_new` makes sure it is ours and not a file (or symlink) someone
+/// else put there first.
+fn create_temp_file(dir: &Path) -> Result<(PathBuf, File)> {
+    for _ in 0..TEMP_FILE_ATTEMPTS {
+        let path = dir.join(format!("claude-sessions-edit-{}.md", random_suffix()));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Could not create temp file: {:?}", path))
+            }
+        }
+    }
+    bail!("Could not create a temp file in {:?}", dir)
+}
+
+/// Write `initial` to a temp file, let `edit` change it, and read it back.
+/// Returns `None` when the result is empty; trailing whitespace is dropped.
+pub fn edit_text<F>(initial: &str, edit: F) -> Result<Option<String>>
+where
+    F: FnOnce(&Path) -> Result<()>,
+{
+    let (path, mut file) = create_temp_file(&env::temp_dir())?;
+    let written = file
+        .write_all(initial.as_bytes())
+        .with_context(|| format!("Could not write temp file: {:?}", path));
+    drop(file);
+    if let Err(err) = written {
+        let _ = fs::remove_file(&path);
+        return Err(err);
+    }
+
+    let result = edit(&path).and_then(|_| {
+        fs::read_to_string(&path).with_context(|| format!("Could not read temp file: {:?}", path))
+    });
+    let _ = fs::remove_file(&path);
+
+    let edited = result?;
+    let edited = edited.trim_end();
+    if edited.trim().is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(edited.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    // ==================== Edit Text Tests ====================
+
+    #[test]
+    fn test_edit_text_prefills_and_reads_back() {
+        let seen = RefCell::new(String::new());
+
+        let edited = edit_text("Current description", |path| {
+            *seen.borrow_mut() = fs::read_to_string(path).unwrap();
+            fs::write(path, "New **description**\n- with a list\n\n").unwrap();
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(*seen.borrow(), "Current description");
+        assert_eq!(
+            edited,
+            Some("New **description**\n- with a list".to_string())
+        );
+    }
+
+    #[test]
+    fn test_edit_text_empty_result_clears() {
+        let edited = edit_text("Something", |path| {
+            fs::write(path, "  \n\n").unwrap();
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(edited, None);
+    }
+
+    #[test]
+    fn test_edit_text_unchanged() {
+        assert_eq!(
+            edit_text("Keep me", |_| Ok(())).unwrap(),
+            Some("Keep me".to_string())
+        );
+    }
+
+    #[test]
+    fn test_create_temp_file_uses_fresh_private_names() {
+        let dir = env::temp_dir();
+        let (first, _) = create_temp_file(&dir).unwrap();
+        let (second, _) = create_temp_file(&dir).unwrap();
+
+        assert_ne!(first, second);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&first).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_file(&first).unwrap();
+        fs::remove_file(&second).unwrap();
+    }
+
+    #[test]
+    fn test_edit_text_editor_failure_removes_temp_file() {
+        let used = RefCell::new(PathBuf::new());
+
+        let result = edit_text("text", |path| {
+            *used.borrow_mut() = path.to_path_buf();
+            bail!("editor crashed")
+        });
+
+        assert!(result.is_err());
+        assert!(!used.borrow().exists());
+    }
+}
@@ -0,0 +1,973 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, Local, Utc};
+use chrono_tz::Tz;
+use colored::{Color, ColoredString, Colorize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Ages below this many days are shown in days.
+const DAYS_UNTIL_WEEKS: i64 = 14;
+/// Ages below this many days (and past `DAYS_UNTIL_WEEKS`) are shown in weeks.
+const DAYS_UNTIL_MONTHS: i64 = 60;
+
+/// Render an elapsed duration as a short relative age, e.g. `5m ago` or `3w ago`.
+pub fn humanize_age(age: Duration) -> String {
+    let minutes = age.num_minutes();
+    let hours = age.num_hours();
+    let days = age.num_days();
+
+    if minutes < 1 {
+        "just now".to_string()
+    } else if hours < 1 {
+        format!("{}m ago", minutes)
+    } else if days < 1 {
+        format!("{}h ago", hours)
+    } else if days < DAYS_UNTIL_WEEKS {
+        format!("{}d ago", days)
+    } else if days < DAYS_UNTIL_MONTHS {
+        format!("{}w ago", days / 7)
+    } else if days < 365 {
+        format!("{}mo ago", days / 30)
+    } else {
+        format!("{}y ago", days / 365)
+    }
+}
+
+/// How stale something is, in the same steps `humanize_age` switches units:
+/// days, weeks, months or more, or no activity at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heat {
+    Recent,
+    Weeks,
+    Months,
+    Never,
+}
+
+impl Heat {
+    pub fn of(age: Option<Duration>) -> Heat {
+        match age.map(|age| age.num_days()) {
+            None => Heat::Never,
+            Some(days) if days < DAYS_UNTIL_WEEKS => Heat::Recent,
+            Some(days) if days < DAYS_UNTIL_MONTHS => Heat::Weeks,
+            Some(_) => Heat::Months,
+        }
+    }
+
+    /// A plain-text stand-in for the color when colors are off.
+    pub fn marker(self) -> &'static str {
+        match self {
+            Heat::Recent => "[recent]",
+            Heat::Weeks => "[weeks]",
+            Heat::Months => "[months]",
+            Heat::Never => "[never]",
+        }
+    }
+}
+
+/// The `list --heat` color for a label whose latest session is `age` old
+/// (`None` for a label without sessions): green, yellow, red, or dim gray.
+pub fn heat_color(age: Option<Duration>) -> Color {
+    match Heat::of(age) {
+        Heat::Recent => Color::Green,
+        Heat::Weeks => Color::Yellow,
+        Heat::Months => Color::Red,
+        Heat::Never => Color::BrightBlack,
+    }
+}
+
+/// Color names accepted by `parse_color`.
+pub const COLOR_NAMES: &[&str] = &[
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright_black",
+    "bright_red",
+    "bright_green",
+    "bright_yellow",
+    "bright_blue",
+    "bright_magenta",
+    "bright_cyan",
+    "bright_white",
+];
+
+/// The canonical spelling of a named terminal color, e.g. `bright_blue` for
+/// `Bright-Blue` (case-insensitive; `-` or a space may stand in for `_`).
+pub fn normalize_color_name(name: &str) -> Result<String> {
+    let normalized = name.trim().to_ascii_lowercase().replace(['-', ' '], "_");
+    if !COLOR_NAMES.contains(&normalized.as_str()) {
+        bail!(
+            "Unknown color '{}' (expected one of: {})",
+            name,
+            COLOR_NAMES.join(", ")
+        );
+    }
+    Ok(normalized)
+}
+
+/// Parse a named terminal color, spelled as `normalize_color_name` accepts.
+pub fn parse_color(name: &str) -> Result<Color> {
+    let normalized = normalize_color_name(name)?;
+    match normalized.replace('_', " ").parse::<Color>() {
+        Ok(color) => Ok(color),
+        Err(()) => bail!("Unknown color '{}'", name),
+    }
+}
+
+/// A label name styled for human output: bold, in the label's own color, or
+/// cyan when it has none (or an unrecognized one).
+pub fn style_label_name(name: &str, color: Option<&str>) -> ColoredString {
+    match color.and_then(|c| parse_color(c).ok()) {
+        Some(color) => name.color(color).bold(),
+        None => name.cyan().bold(),
+    }
+}
+
+/// How human-readable output is decorated. Decided once per run by
+/// `OutputMode::detect` and installed process-wide, so commands ask
+/// `OutputMode::current()` instead of checking the terminal themselves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Colors and Unicode markers, for a terminal.
+    #[default]
+    Styled,
+    /// No ANSI escapes and ASCII-only markers, for pipes, files and `--plain`.
+    Plain,
+}
+
+static PLAIN_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+impl OutputMode {
+    /// Plain when asked for or when stdout isn't a terminal.
+    pub fn detect(stdout_is_terminal: bool, plain: bool) -> Self {
+        if plain || !stdout_is_terminal {
+            OutputMode::Plain
+        } else {
+            OutputMode::Styled
+        }
+    }
+
+    /// Make this the mode `current` returns; `Plain` also turns colors off.
+    pub fn install(self) {
+        PLAIN_OUTPUT.store(self == OutputMode::Plain, Ordering::Relaxed);
+        if self == OutputMode::Plain {
+            colored::control::set_override(false);
+        }
+    }
+
+    pub fn current() -> Self {
+        if PLAIN_OUTPUT.load(Ordering::Relaxed) {
+            OutputMode::Plain
+        } else {
+            OutputMode::Styled
+        }
+    }
+
+    /// `text`, with its colors and styles dropped in plain mode.
+    pub fn style(self, text: ColoredString) -> ColoredString {
+        match self {
+            OutputMode::Styled => text,
+            OutputMode::Plain => text.clear(),
+        }
+    }
+
+    fn marker(self, styled: ColoredString, plain: &str) -> ColoredString {
+        match self {
+            OutputMode::Styled => styled,
+            OutputMode::Plain => plain.normal(),
+        }
+    }
+
+    /// Leads each item of a list.
+    pub fn bullet(self) -> ColoredString {
+        self.marker("•".green(), "-")
+    }
+
+    /// Leads the message of a change that succeeded.
+    pub fn ok_mark(self) -> ColoredString {
+        self.marker("✓".green(), "ok")
+    }
+
+    /// Leads each problem in a report.
+    pub fn fail_mark(self) -> ColoredString {
+        self.marker("✗".red(), "x")
+    }
+
+    /// Points from one thing to what it becomes or refers to.
+    pub fn arrow(self) -> ColoredString {
+        self.marker("→".blue(), "->")
+    }
+
+    /// Joins the ends of a range, as in "21–40".
+    pub fn range_dash(self) -> &'static str {
+        match self {
+            OutputMode::Styled => "–",
+            OutputMode::Plain => "-",
+        }
+    }
+}
+
+/// A run of text with the inline styles that apply to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownSpan {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// Split one line into styled spans, understanding `**bold**` and `*italic*`.
+/// A marker only opens a style when a matching marker follows it, so stray
+/// asterisks (as in `2 * 3`) stay literal.
+pub fn markdown_spans(line: &str) -> Vec<MarkdownSpan> {
+    let mut spans = Vec::new();
+    let mut text = String::new();
+    let mut bold = false;
+    let mut italic = false;
+
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c != '*' {
+            text.push(c);
+            continue;
+        }
+
+        let double = line[i..].starts_with("**");
+        let (marker, rest) = if double {
+            ("**", &line[i + 2..])
+        } else {
+            ("*", &line[i + 1..])
+        };
+        let toggles = if double {
+            bold || rest.contains("**")
+        } else {
+            italic || rest.contains('*')
+        };
+
+        if double {
+            chars.next();
+        }
+        if !toggles {
+            text.push_str(marker);
+            continue;
+        }
+
+        if !text.is_empty() {
+            spans.push(MarkdownSpan {
+                text: std::mem::take(&mut text),
+                bold,
+                italic,
+            });
+        }
+        if double {
+            bold = !bold;
+        } else {
+            italic = !italic;
+        }
+    }
+
+    if !text.is_empty() {
+        spans.push(MarkdownSpan { text, bold, italic });
+    }
+    spans
+}
+
+/// The inline style of a rendered markdown character.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SpanStyle {
+    bold: bool,
+    italic: bool,
+}
+
+impl SpanStyle {
+    /// `text` in this style, or as-is in plain mode.
+    fn apply(self, text: &str, mode: OutputMode) -> String {
+        if mode == OutputMode::Plain {
+            return text.to_string();
+        }
+        let mut styled = text.normal();
+        if self.bold {
+            styled = styled.bold();
+        }
+        if self.italic {
+            styled = styled.italic();
+        }
+        styled.to_string()
+    }
+}
+
+/// Split a markdown line into its indentation, the bullet it renders with
+/// in `mode` (if it is a `- ` item) and the text after them.
+fn markdown_line_parts(line: &str, mode: OutputMode) -> (&str, &'static str, &str) {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    match trimmed.strip_prefix("- ") {
+        Some(body) if mode == OutputMode::Plain => (indent, "- ", body),
+        Some(body) => (indent, "• ", body),
+        None => (indent, "", trimmed),
+    }
+}
+
+/// Render a small markdown subset for the terminal: `**bold**`, `*italic*`
+/// and `- ` bullets. Everything else passes through unchanged. Plain mode
+/// only drops the markers.
+pub fn render_markdown(text: &str, mode: OutputMode) -> String {
+    text.lines()
+        .map(|line| {
+            let (indent, bullet, body) = markdown_line_parts(line, mode);
+            let body: String = markdown_spans(body)
+                .into_iter()
+                .map(|span| {
+                    let style = SpanStyle {
+                        bold: span.bold,
+                        italic: span.italic,
+                    };
+                    style.apply(&span.text, mode)
+                })
+                .collect();
+            format!("{}{}{}", indent, bullet, body)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Like `render_markdown`, but with each line wrapped to `width` columns of
+/// rendered text, so neither the markers nor the escape codes that replace
+/// them count towards the width. Continuation lines of a bullet item line up
+/// under its text. A width of 0 disables wrapping.
+pub fn render_markdown_wrapped(text: &str, width: usize, mode: OutputMode) -> Vec<String> {
+    if width == 0 {
+        return render_markdown(text, mode)
+            .lines()
+            .map(str::to_string)
+            .collect();
+    }
+
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let (indent, bullet, body) = markdown_line_parts(line, mode);
+        let lead = format!("{}{}", indent, bullet);
+        let hang = " ".repeat(lead.width());
+        let cells: Vec<(char, SpanStyle)> = markdown_spans(body)
+            .into_iter()
+            .flat_map(|span| {
+                let style = SpanStyle {
+                    bold: span.bold,
+                    italic: span.italic,
+                };
+                span.text
+                    .chars()
+                    .map(move |c| (c, style))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        let wrapped = wrap_cells(&cells, width.saturating_sub(lead.width()).max(1));
+        for (i, cells) in wrapped.iter().enumerate() {
+            let mut rendered = if i == 0 { lead.clone() } else { hang.clone() };
+            for run in cells.chunk_by(|a, b| a.1 == b.1) {
+                let text: String = run.iter().map(|&(c, _)| c).collect();
+                rendered.push_str(&run[0].1.apply(&text, mode));
+            }
+            lines.push(rendered);
+        }
+    }
+    lines
+}
+
+/// Break `text` into lines that fit in `width` terminal columns. Words are
+/// kept whole where possible; a word wider than `width` is split between
+/// characters, never inside one, so a wide (e.g. CJK) character is moved to the
+/// next line rather than cut. Existing line breaks are preserved, and a width
+/// of 0 disables wrapping.
+pub fn wrap_to_width(text: &str, width: usize) -> Vec<String> {
+    if width == 0 {
+        return text.lines().map(str::to_string).collect();
+    }
+
+    let mut lines = Vec::new();
+    for source in text.lines() {
+        let cells: Vec<(char, ())> = source.chars().map(|c| (c, ())).collect();
+        for line in wrap_cells(&cells, width) {
+            lines.push(line.into_iter().map(|(c, _)| c).collect());
+        }
+    }
+    lines
+}
+
+/// Wrap one line of characters, each carrying a style `S`, the way
+/// `wrap_to_width` wraps text. Runs of whitespace between words become a
+/// single unstyled space.
+fn wrap_cells<S: Copy + Default>(source: &[(char, S)], width: usize) -> Vec<Vec<(char, S)>> {
+    let mut lines = Vec::new();
+    let mut line = Vec::new();
+    let mut line_width = 0;
+
+    for word in source
+        .split(|&(c, _)| c.is_whitespace())
+        .filter(|word| !word.is_empty())
+    {
+        let word_width = word.iter().map(|&(c, _)| c).collect::<String>().width();
+        let needed = if line.is_empty() {
+            word_width
+        } else {
+            line_width + 1 + word_width
+        };
+        if needed <= width {
+            if !line.is_empty() {
+                line.push((' ', S::default()));
+                line_width += 1;
+            }
+            line.extend_from_slice(word);
+            line_width += word_width;
+            continue;
+        }
+
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+            line_width = 0;
+        }
+        if word_width <= width {
+            line.extend_from_slice(word);
+            line_width = word_width;
+            continue;
+        }
+
+        for &(c, style) in word {
+            let char_width = c.width().unwrap_or(0);
+            if line_width + char_width > width && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+            line.push((c, style));
+            line_width += char_width;
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+/// Shorten `text` to at most `width` columns, ending in `…` when cut.
+pub fn truncate_to_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+
+    let mut out = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        out.push(c);
+        used += char_width;
+    }
+    if width > 0 {
+        out.push('…');
+    }
+    out
+}
+
+/// The time zone timestamps are shown in. Stored times are always UTC; this
+/// only affects display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DisplayZone {
+    #[default]
+    Utc,
+    /// The system's local zone.
+    Local,
+    /// An IANA zone such as `Europe/Berlin`.
+    Named(Tz),
+}
+
+impl DisplayZone {
+    /// Parse `utc`, `local` (any case) or an IANA zone name.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "utc" => Ok(DisplayZone::Utc),
+            "local" => Ok(DisplayZone::Local),
+            _ => match value.parse::<Tz>() {
+                Ok(tz) => Ok(DisplayZone::Named(tz)),
+                Err(_) => bail!(
+                    "Unknown time zone '{}': expected utc, local or an IANA name like Europe/Berlin",
+                    value
+                ),
+            },
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            DisplayZone::Utc => "utc".to_string(),
+            DisplayZone::Local => "local".to_string(),
+            DisplayZone::Named(tz) => tz.name().to_string(),
+        }
+    }
+}
+
+/// Format a stored UTC timestamp with `fmt` in the given zone.
+pub fn format_ts(at: DateTime<Utc>, zone: DisplayZone, fmt: &str) -> String {
+    match zone {
+        DisplayZone::Utc => at.format(fmt).to_string(),
+        DisplayZone::Local => at.with_timezone(&Local).format(fmt).to_string(),
+        DisplayZone::Named(tz) => at.with_timezone(&tz).format(fmt).to_string(),
+    }
+}
+
+/// Replace each `{name}` in `template` with `value(name)`, failing on the
+/// first name it rejects. `{{` and `}}` stand for literal braces.
+pub fn expand_template<F>(template: &str, mut value: F) -> Result<String>
+where
+    F: FnMut(&str) -> Result<String>,
+{
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(pos) = rest.find(['{', '}']) {
+        out.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..1]);
+            rest = &tail[2..];
+        } else if let Some(after) = tail.strip_prefix('}') {
+            out.push('}');
+            rest = after;
+        } else {
+            let Some(end) = tail.find('}') else {
+                bail!("Unclosed '{{' in template: {}", template);
+            };
+            out.push_str(&value(&tail[1..end])?);
+            rest = &tail[end + 1..];
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, bold: bool, italic: bool) -> MarkdownSpan {
+        MarkdownSpan {
+            text: text.to_string(),
+            bold,
+            italic,
+        }
+    }
+
+    // ==================== Markdown Tests ====================
+
+    #[test]
+    fn test_markdown_spans_plain_text() {
+        assert_eq!(
+            markdown_spans("no markup here"),
+            vec![span("no markup here", false, false)]
+        );
+        assert!(markdown_spans("").is_empty());
+    }
+
+    #[test]
+    fn test_markdown_spans_bold_and_italic() {
+        assert_eq!(
+            markdown_spans("a **bold** and *italic* word"),
+            vec![
+                span("a ", false, false),
+                span("bold", true, false),
+                span(" and ", false, false),
+                span("italic", false, true),
+                span(" word", false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markdown_spans_nested() {
+        assert_eq!(
+            markdown_spans("**bold *both* bold**"),
+            vec![
+                span("bold ", true, false),
+                span("both", true, true),
+                span(" bold", true, false),
+            ]
+        );
+        assert_eq!(
+            markdown_spans("*it **both** it*"),
+            vec![
+                span("it ", false, true),
+                span("both", true, true),
+                span(" it", false, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markdown_spans_unmatched_markers_stay_literal() {
+        assert_eq!(markdown_spans("2 * 3"), vec![span("2 * 3", false, false)]);
+        assert_eq!(
+            markdown_spans("**unclosed"),
+            vec![span("**unclosed", false, false)]
+        );
+        assert_eq!(markdown_spans("*"), vec![span("*", false, false)]);
+        assert_eq!(
+            markdown_spans("trailing *"),
+            vec![span("trailing *", false, false)]
+        );
+    }
+
+    #[test]
+    fn test_markdown_spans_odd_input_does_not_panic() {
+        for input in [
+            "*",
+            "**",
+            "***",
+            "****",
+            "*****",
+            "* * *",
+            "**a*",
+            "*a**",
+            "***a***",
+            "🚀*é*🚀",
+            "**功能**",
+            "a*b**c***d",
+        ] {
+            let spans = markdown_spans(input);
+            let text: String = spans.iter().map(|s| s.text.as_str()).collect();
+            assert!(text.chars().all(|c| input.contains(c)));
+        }
+        assert_eq!(
+            markdown_spans("🚀*é*🚀"),
+            vec![
+                span("🚀", false, false),
+                span("é", false, true),
+                span("🚀", false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_markdown_bullets() {
+        assert_eq!(
+            render_markdown(
+                "Todo:\n- first\n  - nested item\n-not a bullet",
+                OutputMode::Styled
+            ),
+            "Todo:\n• first\n  • nested item\n-not a bullet"
+        );
+    }
+
+    #[test]
+    fn test_render_markdown_plain_drops_markers_only() {
+        assert_eq!(
+            render_markdown(
+                "Todo:\n- **first**\n  - nested *item*\n-not a bullet",
+                OutputMode::Plain
+            ),
+            "Todo:\n- first\n  - nested item\n-not a bullet"
+        );
+    }
+
+    #[test]
+    fn test_render_markdown_wrapped_counts_rendered_text() {
+        // The markers would push "**bold** words" past 10 columns unrendered
+        assert_eq!(
+            render_markdown_wrapped("**bold** words here", 10, OutputMode::Plain),
+            vec!["bold words", "here"]
+        );
+        assert_eq!(
+            render_markdown_wrapped("- one two three", 9, OutputMode::Styled),
+            vec!["• one two", "  three"]
+        );
+        assert_eq!(
+            render_markdown_wrapped("a *b* c", 0, OutputMode::Plain),
+            vec!["a b c"]
+        );
+    }
+
+    #[test]
+    fn test_wrap_cells_keeps_styles_across_lines() {
+        let bold = SpanStyle {
+            bold: true,
+            italic: false,
+        };
+        let cells: Vec<(char, SpanStyle)> = "bold text".chars().map(|c| (c, bold)).collect();
+
+        let lines = wrap_cells(&cells, 5);
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().flatten().all(|&(_, style)| style == bold));
+    }
+
+    // ==================== Output Mode Tests ====================
+
+    #[test]
+    fn test_output_mode_detect() {
+        assert_eq!(OutputMode::detect(true, false), OutputMode::Styled);
+        assert_eq!(OutputMode::detect(false, false), OutputMode::Plain);
+        assert_eq!(OutputMode::detect(true, true), OutputMode::Plain);
+        assert_eq!(OutputMode::detect(false, true), OutputMode::Plain);
+    }
+
+    #[test]
+    fn test_output_mode_plain_drops_styles_and_uses_ascii() {
+        let plain = OutputMode::Plain;
+        assert!(plain.style("name".red().bold()).is_plain());
+        assert!(!OutputMode::Styled.style("name".red().bold()).is_plain());
+
+        for marker in [
+            plain.bullet(),
+            plain.ok_mark(),
+            plain.fail_mark(),
+            plain.arrow(),
+        ] {
+            assert!(marker.is_plain());
+            assert!(marker.is_ascii(), "{:?}", &*marker);
+        }
+        assert_eq!(&*OutputMode::Styled.bullet(), "•");
+    }
+
+    // ==================== Humanize Tests ====================
+
+    #[test]
+    fn test_humanize_age_just_now() {
+        assert_eq!(humanize_age(Duration::seconds(30)), "just now");
+        assert_eq!(humanize_age(Duration::seconds(-5)), "just now");
+    }
+
+    #[test]
+    fn test_humanize_age_minutes_and_hours() {
+        assert_eq!(humanize_age(Duration::minutes(5)), "5m ago");
+        assert_eq!(humanize_age(Duration::minutes(59)), "59m ago");
+        assert_eq!(humanize_age(Duration::hours(1)), "1h ago");
+        assert_eq!(humanize_age(Duration::hours(23)), "23h ago");
+    }
+
+    #[test]
+    fn test_humanize_age_days_weeks_months_years() {
+        assert_eq!(humanize_age(Duration::days(1)), "1d ago");
+        assert_eq!(humanize_age(Duration::days(13)), "13d ago");
+        assert_eq!(humanize_age(Duration::days(14)), "2w ago");
+        assert_eq!(humanize_age(Duration::days(90)), "3mo ago");
+        assert_eq!(humanize_age(Duration::days(800)), "2y ago");
+    }
+
+    // ==================== Heat Tests ====================
+
+    #[test]
+    fn test_heat_color_thresholds() {
+        assert_eq!(heat_color(Some(Duration::minutes(5))), Color::Green);
+        assert_eq!(heat_color(Some(Duration::days(13))), Color::Green);
+        assert_eq!(heat_color(Some(Duration::days(14))), Color::Yellow);
+        assert_eq!(heat_color(Some(Duration::days(59))), Color::Yellow);
+        assert_eq!(heat_color(Some(Duration::days(60))), Color::Red);
+        assert_eq!(heat_color(Some(Duration::days(800))), Color::Red);
+    }
+
+    #[test]
+    fn test_heat_color_never() {
+        assert_eq!(heat_color(None), Color::BrightBlack);
+        assert_eq!(Heat::of(None).marker(), "[never]");
+    }
+
+    #[test]
+    fn test_heat_matches_humanize_units() {
+        assert_eq!(humanize_age(Duration::days(13)), "13d ago");
+        assert_eq!(Heat::of(Some(Duration::days(13))), Heat::Recent);
+        assert_eq!(humanize_age(Duration::days(14)), "2w ago");
+        assert_eq!(Heat::of(Some(Duration::days(14))), Heat::Weeks);
+        assert_eq!(humanize_age(Duration::days(60)), "2mo ago");
+        assert_eq!(Heat::of(Some(Duration::days(60))), Heat::Months);
+    }
+
+    // ==================== Wrap Tests ====================
+
+    #[test]
+    fn test_wrap_to_width_breaks_at_word_boundary() {
+        assert_eq!(
+            wrap_to_width("the quick brown fox", 9),
+            vec!["the quick", "brown fox"]
+        );
+        assert_eq!(wrap_to_width("fits exactly", 12), vec!["fits exactly"]);
+    }
+
+    #[test]
+    fn test_wrap_to_width_splits_long_word() {
+        assert_eq!(
+            wrap_to_width("see /very/long/path/here ok", 8),
+            vec!["see", "/very/lo", "ng/path/", "here ok"]
+        );
+    }
+
+    #[test]
+    fn test_wrap_to_width_does_not_split_wide_chars() {
+        // Each character is two columns wide; five columns fit two of them
+        let lines = wrap_to_width("日本語テキスト", 5);
+        assert_eq!(lines, vec!["日本", "語テ", "キス", "ト"]);
+        assert!(lines.iter().all(|l| l.width() <= 5));
+    }
+
+    #[test]
+    fn test_wrap_to_width_keeps_line_breaks() {
+        assert_eq!(
+            wrap_to_width("first line\n\nthird", 20),
+            vec!["first line", "", "third"]
+        );
+        assert_eq!(wrap_to_width("a b c", 0), vec!["a b c"]);
+    }
+
+    #[test]
+    fn test_truncate_to_width() {
+        assert_eq!(truncate_to_width("short", 10), "short");
+        assert_eq!(truncate_to_width("a longer text", 8), "a longe…");
+        assert_eq!(truncate_to_width("日本語テキスト", 6), "日本…");
+    }
+
+    // ==================== Color Tests ====================
+
+    #[test]
+    fn test_normalize_color_name() {
+        assert_eq!(normalize_color_name(" Bright-Red ").unwrap(), "bright_red");
+        assert_eq!(normalize_color_name("bright blue").unwrap(), "bright_blue");
+        assert!(normalize_color_name("chartreuse").is_err());
+    }
+
+    #[test]
+    fn test_parse_color_valid_names() {
+        assert_eq!(parse_color("red").unwrap(), Color::Red);
+        assert_eq!(parse_color("Magenta").unwrap(), Color::Magenta);
+        assert_eq!(parse_color("bright_blue").unwrap(), Color::BrightBlue);
+        assert_eq!(parse_color("bright-green").unwrap(), Color::BrightGreen);
+    }
+
+    #[test]
+    fn test_parse_color_invalid_name() {
+        let err = parse_color("chartreuse").unwrap_err().to_string();
+        assert!(err.contains("chartreuse"));
+        assert!(err.contains("bright_white"));
+        assert!(parse_color("").is_err());
+    }
+
+    #[test]
+    fn test_style_label_name_uses_color_or_default() {
+        assert_eq!(
+            style_label_name("TICKET-1", Some("red")).fgcolor,
+            Some(Color::Red)
+        );
+        assert_eq!(
+            style_label_name("TICKET-1", None).fgcolor,
+            Some(Color::Cyan)
+        );
+        assert_eq!(
+            style_label_name("TICKET-1", Some("not-a-color")).fgcolor,
+            Some(Color::Cyan)
+        );
+    }
+
+    // ==================== Template Tests ====================
+
+    fn upper(name: &str) -> Result<String> {
+        match name {
+            "a" | "b" => Ok(name.to_uppercase()),
+            _ => bail!("unknown {}", name),
+        }
+    }
+
+    #[test]
+    fn test_expand_template_replaces_variables() {
+        assert_eq!(expand_template("{a}-{b} {a}", upper).unwrap(), "A-B A");
+        assert_eq!(expand_template("no vars", upper).unwrap(), "no vars");
+    }
+
+    #[test]
+    fn test_expand_template_escaped_braces() {
+        assert_eq!(expand_template("{{a}} {a} }", upper).unwrap(), "{a} A }");
+    }
+
+    #[test]
+    fn test_expand_template_rejected_name_fails() {
+        let err = expand_template("x {zzz} y", upper).unwrap_err();
+        assert_eq!(err.to_string(), "unknown zzz");
+    }
+
+    #[test]
+    fn test_expand_template_unclosed_brace_fails() {
+        assert!(expand_template("oops {a", upper).is_err());
+    }
+
+    // ==================== Time Zone Tests ====================
+
+    fn fixed_instant() -> DateTime<Utc> {
+        use chrono::TimeZone;
+        Utc.with_ymd_and_hms(2024, 7, 1, 12, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn test_format_ts_utc() {
+        assert_eq!(
+            format_ts(fixed_instant(), DisplayZone::Utc, "%Y-%m-%d %H:%M"),
+            "2024-07-01 12:30"
+        );
+    }
+
+    /// Set in the copy of the test binary that `test_format_ts_local` runs
+    /// with a known `TZ`.
+    const LOCAL_ZONE_CHILD: &str = "CLAUDE_SESSIONS_TEST_LOCAL_ZONE";
+
+    #[cfg(unix)]
+    #[test]
+    fn test_format_ts_local() {
+        if std::env::var_os(LOCAL_ZONE_CHILD).is_some() {
+            // A POSIX zone (UTC+9, no daylight saving) needs no tz database
+            assert_eq!(
+                format_ts(fixed_instant(), DisplayZone::Local, "%Y-%m-%d %H:%M %z"),
+                "2024-07-01 21:30 +0900"
+            );
+            return;
+        }
+
+        // The local zone is process-wide, so check it in a child process
+        // rather than changing TZ under the other tests
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "format::tests::test_format_ts_local"])
+            .env(LOCAL_ZONE_CHILD, "1")
+            .env("TZ", "JST-9")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        assert!(String::from_utf8_lossy(&output.stdout).contains("1 passed"));
+    }
+
+    #[test]
+    fn test_format_ts_named_zone() {
+        let tokyo = DisplayZone::parse("Asia/Tokyo").unwrap();
+        let new_york = DisplayZone::parse("America/New_York").unwrap();
+
+        assert_eq!(
+            format_ts(fixed_instant(), tokyo, "%Y-%m-%d %H:%M"),
+            "2024-07-01 21:30"
+        );
+        // Daylight saving time applies in July
+        assert_eq!(
+            format_ts(fixed_instant(), new_york, "%Y-%m-%d %H:%M"),
+            "2024-07-01 08:30"
+        );
+    }
+
+    #[test]
+    fn test_display_zone_parse() {
+        assert_eq!(DisplayZone::parse("UTC").unwrap(), DisplayZone::Utc);
+        assert_eq!(DisplayZone::parse("local").unwrap(), DisplayZone::Local);
+        assert_eq!(
+            DisplayZone::parse("Europe/Berlin").unwrap().name(),
+            "Europe/Berlin"
+        );
+        assert!(DisplayZone::parse("Mars/Olympus").is_err());
+    }
+}
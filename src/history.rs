@@ -0,0 +1,171 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// One resume, as recorded in the history log (one JSON object per line).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub at: DateTime<Utc>,
+    pub label: String,
+    pub session_id: String,
+    pub dir: String,
+    /// Exit code of `claude`, or `None` if it couldn't be started or was
+    /// killed by a signal.
+    pub exit_code: Option<i32>,
+}
+
+pub fn format_entry(entry: &HistoryEntry) -> Result<String> {
+    serde_json::to_string(entry).context("Could not serialize history entry")
+}
+
+/// Parse one log line, returning `None` for blank or unreadable lines.
+pub fn parse_entry(line: &str) -> Option<HistoryEntry> {
+    serde_json::from_str(line.trim()).ok()
+}
+
+pub fn append(path: &Path, entry: &HistoryEntry) -> Result<()> {
+    let line = format_entry(entry)?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Could not open history file: {:?}", path))?;
+    writeln!(file, "{}", line).with_context(|| format!("Could not write history file: {:?}", path))
+}
+
+/// Every readable entry in the log, oldest first. A missing log is empty.
+pub fn read(path: &Path) -> Result<Vec<HistoryEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Could not read history file: {:?}", path))?;
+    Ok(content.lines().filter_map(parse_entry).collect())
+}
+
+/// The newest `count` entries (optionally only for `label`), newest first.
+pub fn select<'a>(
+    entries: &'a [HistoryEntry],
+    label: Option<&str>,
+    count: usize,
+) -> Vec<&'a HistoryEntry> {
+    entries
+        .iter()
+        .rev()
+        .filter(|e| label.is_none_or(|label| e.label == label))
+        .take(count)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::env;
+
+    fn entry(label: &str, session_id: &str, day: u32, exit_code: Option<i32>) -> HistoryEntry {
+        HistoryEntry {
+            at: Utc.with_ymd_and_hms(2024, 1, day, 9, 0, 0).unwrap(),
+            label: label.to_string(),
+            session_id: session_id.to_string(),
+            dir: "/work/app".to_string(),
+            exit_code,
+        }
+    }
+
+    // ==================== Format Tests ====================
+
+    #[test]
+    fn test_format_parse_roundtrip() {
+        let original = HistoryEntry {
+            label: "team/web\tTICKET-1\nnext".to_string(),
+            dir: "/home/me/功能 🚀".to_string(),
+            ..entry("x", "sess-1", 1, Some(0))
+        };
+
+        let line = format_entry(&original).unwrap();
+
+        assert!(!line.contains('\n'));
+        assert_eq!(parse_entry(&line), Some(original));
+    }
+
+    #[test]
+    fn test_format_parse_roundtrip_without_exit_code() {
+        let original = entry("work", "sess-1", 2, None);
+
+        assert_eq!(
+            parse_entry(&format_entry(&original).unwrap()),
+            Some(original)
+        );
+    }
+
+    #[test]
+    fn test_parse_entry_rejects_garbage() {
+        assert_eq!(parse_entry(""), None);
+        assert_eq!(parse_entry("not json"), None);
+        assert_eq!(parse_entry(r#"{"label":"work"}"#), None);
+    }
+
+    // ==================== Select Tests ====================
+
+    #[test]
+    fn test_select_newest_first_with_count() {
+        let entries = vec![
+            entry("a", "1", 1, Some(0)),
+            entry("b", "2", 2, Some(0)),
+            entry("a", "3", 3, Some(1)),
+        ];
+
+        let ids: Vec<&str> = select(&entries, None, 2)
+            .iter()
+            .map(|e| e.session_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["3", "2"]);
+    }
+
+    #[test]
+    fn test_select_filters_by_label() {
+        let entries = vec![
+            entry("a", "1", 1, Some(0)),
+            entry("b", "2", 2, Some(0)),
+            entry("a", "3", 3, Some(1)),
+        ];
+
+        let ids: Vec<&str> = select(&entries, Some("a"), 10)
+            .iter()
+            .map(|e| e.session_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["3", "1"]);
+        assert!(select(&entries, Some("missing"), 10).is_empty());
+    }
+
+    // ==================== File Tests ====================
+
+    #[test]
+    fn test_append_and_read_skip_bad_lines() {
+        let path = env::temp_dir().join(format!(
+            "claude-sessions-history-test-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        assert!(read(&path).unwrap().is_empty());
+
+        append(&path, &entry("a", "1", 1, Some(0))).unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"truncated {\n")
+            .unwrap();
+        append(&path, &entry("b", "2", 2, None)).unwrap();
+
+        let entries = read(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].label, "b");
+        fs::remove_file(&path).unwrap();
+    }
+}
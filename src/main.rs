@@ -1,93 +1,1385 @@
+mod claude;
 mod commands;
 mod data;
+mod doctor;
+mod editor;
+mod format;
+mod history;
+mod parse;
+mod paths;
+mod session_lock;
+mod settings;
+mod shutdown;
 mod storage;
+mod version;
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use colored::Colorize;
+use std::io::{self, BufRead, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process;
 
-use storage::Storage;
+use data::KeepOnConflict;
+use format::{DisplayZone, OutputMode};
+use shutdown::{for_each_until_stopped, StopFlag};
+use storage::{Storage, StorageError};
 
 #[derive(Parser)]
 #[command(name = "claude-sessions")]
 #[command(about = "CLI tool for managing Claude Code sessions", long_about = None)]
 #[command(version)]
 struct Cli {
+    /// Bypass safety checks: a data file checksum mismatch, the allowed_roots setting,
+    /// or resuming a session that is already running
+    #[arg(long, global = true)]
+    force: bool,
+
+    /// Use a separate store (data file <NAME>.json; "default" is data.json)
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        env = "CLAUDE_SESSIONS_PROFILE"
+    )]
+    profile: Option<String>,
+
+    /// Show what would change without writing the data file
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Log data file operations to stderr
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Separate records with NUL instead of newline (for xargs -0)
+    #[arg(short = '0', long, global = true)]
+    null: bool,
+
+    /// Show full paths instead of abbreviating the home directory to ~
+    #[arg(long, global = true)]
+    no_abbrev: bool,
+
+    /// Disable colored output
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// No colors and ASCII-only markers (automatic when stdout isn't a terminal)
+    #[arg(long, global = true)]
+    plain: bool,
+
+    /// Leave labels without sessions out of list, stats, search, browse and export
+    #[arg(long, global = true)]
+    compact_empty: bool,
+
+    /// Fail on unknown fields in the data file or an import file instead of ignoring them
+    #[arg(long, global = true)]
+    strict_load: bool,
+
+    /// After a command that changed the data, print the data file path
+    #[arg(long, global = true)]
+    print_data_path: bool,
+
+    /// Show timestamps in the local time zone instead of UTC
+    #[arg(long, global = true, conflicts_with = "tz")]
+    local: bool,
+
+    /// Show timestamps in this time zone (utc, local or an IANA name like Europe/Berlin)
+    #[arg(long, global = true, value_name = "ZONE", value_parser = DisplayZone::parse)]
+    tz: Option<DisplayZone>,
+
     #[command(subcommand)]
     command: Commands,
 }
 
-#[derive(Subcommand)]
+#[derive(Clone, Subcommand)]
 enum Commands {
     /// Add a session to a label (creates label if it doesn't exist)
     Add {
         /// The label name to add the session to
         label: String,
         /// The Claude session ID
-        session_id: String,
+        #[arg(required_unless_present = "stdin_json")]
+        session_id: Option<String>,
         /// Optional description for this session
         #[arg(short, long)]
         description: Option<String>,
+        /// Also set the label's own description
+        #[arg(short = 'L', long)]
+        label_description: Option<String>,
+        /// Move the session here if another label already holds it, instead of duplicating
+        #[arg(long)]
+        move_existing: bool,
+        /// Do nothing if the label already has this session id at this path
+        #[arg(long)]
+        idempotent: bool,
+        /// Read the session as a JSON object from stdin instead
+        #[arg(long, conflicts_with_all = ["session_id", "description", "at"])]
+        stdin_json: bool,
+        /// Resume the session right after adding it
+        #[arg(short, long)]
+        resume: bool,
+        /// Attach metadata to the session (repeatable)
+        #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = parse::parse_kv)]
+        meta: Vec<(String, String)>,
+        /// Warn when the session id doesn't look like a UUID
+        #[arg(long, conflicts_with = "strict_id")]
+        warn_id: bool,
+        /// Reject session ids that don't look like a UUID
+        #[arg(long)]
+        strict_id: bool,
+        /// Record $SHELL and whether stdin is a terminal in the session's metadata
+        #[arg(long)]
+        capture_env: bool,
+        /// Record the session as created at this time (RFC 3339 or YYYY-MM-DD)
+        #[arg(long, value_name = "DATETIME", value_parser = parse::parse_datetime)]
+        at: Option<DateTime<Utc>>,
     },
 
     /// Resume a session by label
     Resume {
-        /// The label name to resume
-        label: String,
+        /// The label name to resume (pick one interactively if omitted)
+        label: Option<String>,
         /// Interactively pick from multiple sessions
         #[arg(short, long)]
         pick: bool,
+        /// Show full session ids in the picker instead of the first 8 characters
+        #[arg(long, requires = "pick")]
+        no_session_truncation: bool,
+        /// Which session the picker starts on
+        #[arg(
+            long = "default",
+            alias = "select-default",
+            value_enum,
+            default_value_t,
+            requires = "pick"
+        )]
+        picker_default: commands::PickerDefault,
+        /// Start Claude in this subdirectory of the session path
+        #[arg(long, value_name = "SUBDIR")]
+        cd: Option<String>,
+        /// Allow --cd to point outside the session path
+        #[arg(long, requires = "cd")]
+        allow_escape: bool,
+        /// If the session's directory was deleted, start in its nearest existing parent
+        #[arg(long)]
+        nearest_dir: bool,
+        /// Resume this label instead if the first one is missing or empty
+        #[arg(long = "or", value_name = "LABEL")]
+        fallback: Option<String>,
+        /// Send this as the first message (needs a claude CLI that accepts a prompt argument)
+        #[arg(short = 'P', long)]
+        prompt: Option<String>,
+        /// Set an environment variable for the claude process (repeatable)
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse::parse_kv)]
+        env: Vec<(String, String)>,
+        /// Open $SHELL (or /bin/sh) in the session directory instead of running claude
+        #[arg(long, conflicts_with = "prompt")]
+        shell: bool,
     },
 
     /// List all labels, or sessions for a specific label
     List {
         /// Optional label name to show details for
         label: Option<String>,
+        /// Pick the label to show from a type-to-filter list (terminal only)
+        #[arg(long, conflicts_with_all = ["label", "json", "porcelain", "tree", "heat", "active_since"])]
+        pick: bool,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        /// Stable, tab-separated output for scripts (no colors)
+        #[arg(long, conflicts_with = "json")]
+        porcelain: bool,
+        /// Emit JSON on a single line (requires --json)
+        #[arg(long, requires = "json")]
+        compact: bool,
+        /// With --json, print an array of label summaries instead of every session
+        #[arg(long, requires = "json", conflicts_with_all = ["label", "tree"])]
+        overview: bool,
+        /// Indent JSON by N spaces per level; 0 is a single line (requires --json)
+        #[arg(long, value_name = "N", requires = "json", conflicts_with = "compact")]
+        indent: Option<usize>,
+        /// Extra session fields to show (comma-separated)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        fields: Vec<commands::ListField>,
+        /// Show how long ago each label's latest session was created
+        #[arg(long)]
+        age: bool,
+        /// Color labels by how recent their latest session is (a text marker with --no-color)
+        #[arg(long, conflicts_with = "label")]
+        heat: bool,
+        /// How to order labels
+        #[arg(long, value_enum, default_value_t)]
+        sort: commands::LabelSort,
+        /// Reverse the label order
+        #[arg(long)]
+        reverse: bool,
+        /// Group labels into a tree by splitting names on '/'
+        #[arg(long)]
+        tree: bool,
+        /// Only show sessions whose directory still exists
+        #[arg(long, conflicts_with = "path_missing")]
+        path_exists: bool,
+        /// Only show sessions whose directory no longer exists
+        #[arg(long)]
+        path_missing: bool,
+        /// Only show sessions that have never been resumed
+        #[arg(long)]
+        never_resumed: bool,
+        /// Only show sessions last resumed before this date (RFC 3339 or YYYY-MM-DD)
+        #[arg(long, value_name = "DATE", value_parser = parse::parse_datetime)]
+        resumed_before: Option<DateTime<Utc>>,
+        /// Render markdown (bold, italic, bullets) in descriptions on a terminal
+        #[arg(long)]
+        render: bool,
+        /// Write timestamps in --json/--porcelain output as Unix seconds
+        #[arg(long)]
+        epoch: bool,
+        /// Only labels with a session created within this window (e.g. 7d, 12h)
+        #[arg(long, value_name = "DURATION", value_parser = parse::parse_lookback, conflicts_with = "label")]
+        active_since: Option<chrono::Duration>,
+        /// Wrap descriptions and paths to this many columns (default: terminal width)
+        #[arg(long, value_name = "N")]
+        width: Option<usize>,
+        /// Show at most this many labels (or sessions with a label), after sorting
+        #[arg(long, value_name = "N", conflicts_with_all = ["json", "tree"])]
+        limit: Option<usize>,
+        /// Skip this many labels (or sessions with a label) before --limit
+        #[arg(long, value_name = "N", default_value_t = 0, conflicts_with_all = ["json", "tree"])]
+        offset: usize,
+    },
+
+    /// Show the most recently created sessions across all labels
+    Recent {
+        /// Maximum number of sessions to show
+        #[arg(short, long, default_value_t = 10)]
+        count: usize,
+        /// Only sessions created at or after this time (e.g. 7d, 12h, 2024-06-01)
+        #[arg(long)]
+        since: Option<String>,
+        /// Only sessions created at or before this time (e.g. 1d, 2024-06-30)
+        #[arg(long)]
+        until: Option<String>,
+    },
+
+    /// Search labels and sessions (case-insensitive substring match)
+    Search {
+        /// Text to look for
+        query: String,
+        /// Only match label names and session ids (skip paths and descriptions)
+        #[arg(long)]
+        names_only: bool,
+        /// Only scan the first N characters of each description
+        #[arg(long, value_name = "N")]
+        max_desc_len: Option<usize>,
+        /// Print only the number of matching sessions
+        #[arg(long)]
+        count_only: bool,
+        /// Only search labels matching this name or pattern (`*`, `?`; repeatable)
+        #[arg(long = "in", value_name = "LABEL")]
+        in_labels: Vec<String>,
+    },
+
+    /// Show recorded resumes, newest first
+    History {
+        /// Only show resumes of this label
+        #[arg(long)]
+        label: Option<String>,
+        /// Number of entries to show
+        #[arg(short, long, default_value_t = 20)]
+        count: usize,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print label names, one per line (fast, no formatting)
+    Labels {
+        /// Print only the number of labels
+        #[arg(short, long)]
+        count: bool,
+    },
+
+    /// Show summary statistics for the store or a single label
+    Stats {
+        /// Only summarize this label
+        #[arg(long)]
+        label: Option<String>,
+        /// Show each session path with its session and label counts instead
+        #[arg(long, conflicts_with = "label")]
+        paths: bool,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Count sessions grouped by label, path or month (`key<TAB>count` rows)
+    Count {
+        /// What to group sessions by
+        #[arg(long, value_enum, default_value = "label")]
+        by: commands::CountBy,
+        /// Output as a JSON object
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Copy a label's description (and optionally its sessions) to a new label
+    Clone {
+        /// The label to copy from
+        source: String,
+        /// The new label name (must not exist)
+        dest: String,
+        /// Also copy the source label's sessions
+        #[arg(long)]
+        with_sessions: bool,
+    },
+
+    /// Import labels from another data file (merges into existing labels)
+    Import {
+        /// Path to a JSON file in the data.json format, or a directory of <label>.json files
+        file: PathBuf,
+        /// Replace existing labels instead of merging into them
+        #[arg(long)]
+        overwrite: bool,
+        /// Ask whether to merge, overwrite or skip each label that already exists
+        #[arg(short, long, conflicts_with = "overwrite")]
+        interactive: bool,
+        /// Prefix every imported label name (e.g. "alice/")
+        #[arg(long)]
+        prefix: Option<String>,
+        /// Which copy to keep when a session id exists with a different created_at
+        #[arg(long, value_enum, default_value_t, conflicts_with = "overwrite")]
+        keep: KeepArg,
+        /// Only check the file (parse it and run the doctor checks); import nothing
+        #[arg(long, conflicts_with_all = ["overwrite", "interactive", "prefix"])]
+        validate_only: bool,
+    },
+
+    /// Export all labels as JSON (importable) or CSV
+    Export {
+        /// File to write to (prints to stdout if omitted)
+        file: Option<PathBuf>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t)]
+        format: commands::ExportFormat,
+        /// Export only this label
+        #[arg(short, long)]
+        label: Option<String>,
+        /// Replace paths with placeholders and drop descriptions, e.g. for a bug report
+        #[arg(long)]
+        anonymize: bool,
+    },
+
+    /// Collapse sessions that share an id within a label
+    Dedup {
+        /// Only dedup this label (all labels if omitted)
+        label: Option<String>,
+        /// Merge descriptions and metadata of duplicates instead of dropping them
+        #[arg(long)]
+        merge: bool,
     },
 
     /// Remove a label or a specific session from a label
+    #[command(group = ArgGroup::new("confirmable").args(["match_desc", "keep"]))]
     Remove {
         /// The label name
         label: String,
         /// Optional session ID to remove (removes entire label if not specified)
         session_id: Option<String>,
+        /// Remove every session but keep the label and its description
+        #[arg(long, conflicts_with = "session_id")]
+        all_sessions: bool,
+        /// Remove the sessions whose description contains this text
+        #[arg(long, value_name = "TEXT", conflicts_with_all = ["session_id", "all_sessions"])]
+        match_desc: Option<String>,
+        /// Keep only this session and remove the label's other sessions
+        #[arg(long, value_name = "SESSION_ID", conflicts_with_all = ["session_id", "all_sessions", "match_desc"])]
+        keep: Option<String>,
+        /// Confirm removing several sessions with --match-desc or --keep
+        #[arg(short, long, requires = "confirmable")]
+        yes: bool,
+        /// Remove this session (repeatable); ids not in the label are reported
+        #[arg(long = "session", value_name = "SESSION_ID", conflicts_with_all = ["session_id", "all_sessions", "match_desc", "keep"])]
+        sessions: Vec<String>,
     },
 
     /// Set or update a label's description
     Describe {
-        /// The label name
-        label: String,
+        /// The label name (pick one interactively if omitted)
+        label: Option<String>,
         /// The description (clears if not provided)
-        #[arg(short, long)]
+        #[arg(short, long, conflicts_with = "edit")]
         description: Option<String>,
+        /// Edit the current description in $EDITOR
+        #[arg(short, long)]
+        edit: bool,
+        /// Describe this session instead of the label
+        #[arg(long, value_name = "SESSION_ID")]
+        session: Option<String>,
+        /// Create the label if it doesn't exist instead of failing
+        #[arg(long, conflicts_with = "session")]
+        create: bool,
+        /// Print the current description as-is (nothing if unset) instead of changing it
+        #[arg(long, conflicts_with_all = ["description", "edit", "create"])]
+        show: bool,
+        /// Expand {label}, {date}, {user} and {count} in the description before saving it
+        #[arg(long, conflicts_with = "show")]
+        template: bool,
+        /// Print how the label's description changed over time instead of changing it
+        #[arg(long, conflicts_with_all = ["description", "edit", "session", "create", "show", "template"])]
+        history: bool,
+        /// Set many labels' descriptions from a JSON or TOML (by extension) file of label = description
+        #[arg(
+            long,
+            value_name = "FILE",
+            conflicts_with_all = ["label", "description", "edit", "session", "create", "show", "template", "history"]
+        )]
+        from_map: Option<PathBuf>,
+        /// With --from-map, skip labels that don't exist instead of failing
+        #[arg(long, requires = "from_map")]
+        skip_unknown: bool,
+    },
+
+    /// Change the id of a session, keeping its path, description and timestamps
+    RenameSession {
+        /// The label holding the session
+        label: String,
+        /// The session's current id
+        old_id: String,
+        /// The id to give it
+        new_id: String,
     },
 
-    /// Show configuration info
-    Config,
+    /// Make a session current by setting its creation time to now
+    Touch {
+        /// The label holding the session(s)
+        label: String,
+        /// The session to touch
+        #[arg(required_unless_present = "all")]
+        session_id: Option<String>,
+        /// Touch every session in the label, keeping their order
+        #[arg(long, conflicts_with = "session_id")]
+        all: bool,
+    },
+
+    /// Show a label's name in a color (omit the color to clear it)
+    Color {
+        /// The label name
+        label: String,
+        /// A color name, e.g. red, green, bright_blue
+        color: Option<String>,
+    },
+
+    /// Show or edit a session's metadata
+    Meta {
+        /// The label name
+        label: String,
+        /// The session ID
+        session_id: String,
+        /// Set a metadata entry (repeatable)
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse::parse_kv)]
+        set: Vec<(String, String)>,
+        /// Remove a metadata entry by key (repeatable)
+        #[arg(long, value_name = "KEY")]
+        unset: Vec<String>,
+    },
+
+    /// Interactively browse labels and sessions
+    Browse,
+
+    /// Watch the current directory for new Claude sessions and add them to a label
+    Watch {
+        /// The label to add new sessions to
+        label: String,
+        /// Seconds between checks
+        #[arg(short, long, default_value_t = 2)]
+        interval: u64,
+    },
+
+    /// Add the Claude sessions recorded for the current directory to a label
+    Sync {
+        /// The label to add sessions to
+        label: String,
+    },
+
+    /// Remove sessions older than a cutoff from every label
+    Sweep {
+        /// Age cutoff, e.g. 90d, 12w
+        #[arg(long, value_name = "DURATION", value_parser = parse::parse_lookback)]
+        older_than: chrono::Duration,
+        /// Also remove labels left without sessions
+        #[arg(long)]
+        prune_empty: bool,
+    },
+
+    /// List, add or remove short aliases for label names
+    Alias {
+        #[command(subcommand)]
+        action: Option<AliasAction>,
+    },
+
+    /// Print the version; with --verbose also the commit, rustc and target
+    Version {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check for duplicate session ids, missing paths and empty labels
+    Doctor {
+        /// Interactively fix (re-point, remove or skip) sessions with missing paths
+        #[arg(long)]
+        fix_paths: bool,
+    },
+
+    /// Show configuration info, or get/set a preference
+    #[command(args_conflicts_with_subcommands = true)]
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+        /// Output as JSON (only without a subcommand)
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Run commands read from stdin, one per line, with a single load and save
+    Batch,
 }
 
-fn main() -> Result<()> {
+/// One line of `batch` input: a subcommand and its arguments, without the
+/// program name.
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+struct BatchLine {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Clone, Subcommand)]
+enum AliasAction {
+    /// Make ALIAS refer to LABEL
+    Add {
+        /// The short name
+        alias: String,
+        /// The label it stands for
+        label: String,
+        /// Create the label if it doesn't exist instead of failing
+        #[arg(long)]
+        create: bool,
+    },
+    /// Remove an alias
+    Rm {
+        /// The alias to remove
+        alias: String,
+    },
+}
+
+#[derive(Clone, Subcommand)]
+enum ConfigAction {
+    /// Print the value of a setting
+    Get {
+        /// The setting name
+        key: String,
+    },
+
+    /// Change the value of a setting
+    Set {
+        /// The setting name
+        key: String,
+        /// The new value
+        value: String,
+    },
+
+    /// List the profiles that have a data file (* marks the active one)
+    Profiles,
+
+    /// Write just the settings (no labels or sessions) to a JSON file
+    Export {
+        /// The file to write
+        file: PathBuf,
+    },
+
+    /// Replace the settings with those from a `config export` file; labels are kept
+    Import {
+        /// The file to read
+        file: PathBuf,
+    },
+}
+
+/// `import --keep`: the command-line spelling of `KeepOnConflict`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum KeepArg {
+    /// Whichever copy has the later `created_at`
+    #[default]
+    Newer,
+    /// The session already in the store
+    Existing,
+}
+
+impl From<KeepArg> for KeepOnConflict {
+    fn from(keep: KeepArg) -> Self {
+        match keep {
+            KeepArg::Newer => KeepOnConflict::Newer,
+            KeepArg::Existing => KeepOnConflict::Existing,
+        }
+    }
+}
+
+fn main() {
     let cli = Cli::parse();
-    let storage = Storage::new()?;
 
-    match cli.command {
+    if let Err(err) = run(cli) {
+        match err.downcast_ref::<StorageError>() {
+            Some(StorageError::Corrupt { path, source }) => print_corrupt_help(path, source),
+            None => eprintln!("Error: {:?}", err),
+        }
+        process::exit(1);
+    }
+}
+
+/// Recovery steps for a data file that no longer parses.
+fn print_corrupt_help(path: &Path, source: &serde_json::Error) {
+    eprintln!(
+        "{} the data file is corrupt and could not be loaded.",
+        "Error:".red().bold()
+    );
+    eprintln!("  File:   {}", path.display());
+    eprintln!("  Reason: {}", source);
+    eprintln!();
+    eprintln!("To recover:");
+
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    let backup = PathBuf::from(backup);
+    if backup.exists() {
+        eprintln!(
+            "  - A backup exists at {}; copy it over the data file to restore it",
+            backup.display()
+        );
+    } else {
+        eprintln!(
+            "  - Check for a backup next to the data file (e.g. {})",
+            backup.display()
+        );
+    }
+    eprintln!(
+        "  - Open {} in an editor and fix the JSON near the position above",
+        path.display()
+    );
+    eprintln!("  - Or move the file aside to start over with an empty store");
+}
+
+/// The width of the terminal stdout is attached to, if any. Plain output is
+/// never fitted to a width, so it doesn't change with the terminal.
+fn terminal_width() -> Option<usize> {
+    if OutputMode::current() == OutputMode::Plain {
+        return None;
+    }
+    terminal_size::terminal_size().map(|(terminal_size::Width(w), _)| w as usize)
+}
+
+/// The label given on the command line or, when there is none and we're in a
+/// terminal, one picked interactively.
+fn label_or_pick(label: Option<String>, storage: &Storage) -> Result<String> {
+    match label {
+        Some(label) => Ok(label),
+        None if io::stdin().is_terminal() && io::stdout().is_terminal() => {
+            commands::pick_label(storage)
+        }
+        None => bail!("No label given (pass one, or run in a terminal to pick one)"),
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    OutputMode::detect(io::stdout().is_terminal(), cli.plain).install();
+    if cli.no_color {
+        colored::control::set_override(false);
+    }
+
+    let mut storage = Storage::new(cli.profile.as_deref())?;
+    storage.set_ignore_checksum(cli.force);
+    storage.set_dry_run(cli.dry_run);
+    storage.set_verbose(cli.verbose);
+    storage.set_compact_empty(cli.compact_empty);
+    storage.set_strict_load(cli.strict_load);
+
+    let zone = if cli.local {
+        Some(DisplayZone::Local)
+    } else {
+        cli.tz
+    };
+
+    execute(&cli, cli.command.clone(), &storage, zone)?;
+    if cli.print_data_path {
+        commands::print_data_path(&mut io::stdout(), &storage)?;
+    }
+    Ok(())
+}
+
+fn execute(
+    cli: &Cli,
+    command: Commands,
+    storage: &Storage,
+    zone: Option<DisplayZone>,
+) -> Result<()> {
+    match command {
         Commands::Add {
             label,
             session_id,
             description,
-        } => commands::add(&storage, &label, &session_id, description),
+            label_description,
+            move_existing,
+            idempotent,
+            resume,
+            meta,
+            stdin_json: _,
+            warn_id,
+            strict_id,
+            capture_env,
+            at,
+        } => {
+            let options = commands::AddOptions {
+                resume,
+                meta,
+                warn_id,
+                strict_id,
+                at,
+                label_description,
+                move_existing,
+                idempotent,
+                force: cli.force,
+                capture_env,
+            };
+            match session_id {
+                Some(session_id) => {
+                    commands::add(storage, &label, &session_id, description, &options)
+                }
+                None => {
+                    let mut json = String::new();
+                    io::stdin()
+                        .read_to_string(&mut json)
+                        .context("Could not read session JSON from stdin")?;
+                    commands::add_json(storage, &label, &json, &options)
+                }
+            }
+        }
+
+        Commands::Resume {
+            label,
+            pick,
+            no_session_truncation,
+            picker_default,
+            cd,
+            allow_escape,
+            nearest_dir,
+            fallback,
+            prompt,
+            env,
+            shell,
+        } => commands::resume(
+            storage,
+            &label_or_pick(label, storage)?,
+            &commands::ResumeOptions {
+                pick,
+                full_ids: no_session_truncation,
+                cd,
+                allow_escape,
+                fallback,
+                prompt,
+                env,
+                zone,
+                picker_default,
+                nearest_dir,
+                shell,
+                force: cli.force,
+            },
+        ),
+
+        Commands::List {
+            label,
+            pick,
+            json,
+            porcelain,
+            compact,
+            overview,
+            indent,
+            fields,
+            age,
+            heat,
+            sort,
+            reverse,
+            tree,
+            path_exists,
+            path_missing,
+            never_resumed,
+            resumed_before,
+            render,
+            width,
+            active_since,
+            epoch,
+            limit,
+            offset,
+        } => commands::list(
+            storage,
+            if pick {
+                Some(label_or_pick(None, storage)?)
+            } else {
+                label
+            }
+            .as_deref(),
+            &commands::ListOptions {
+                json,
+                compact,
+                overview,
+                indent,
+                porcelain,
+                fields,
+                age,
+                heat,
+                sort,
+                reverse,
+                tree,
+                path_filter: if path_exists {
+                    Some(commands::PathFilter::Exists)
+                } else if path_missing {
+                    Some(commands::PathFilter::Missing)
+                } else {
+                    None
+                },
+                resume_filter: commands::ResumeFilter {
+                    never_resumed,
+                    resumed_before,
+                },
+                render: render && !cli.no_color && OutputMode::current() == OutputMode::Styled,
+                null: cli.null,
+                no_abbrev: cli.no_abbrev,
+                width: width.or_else(terminal_width),
+                active_since,
+                epoch,
+                zone,
+                page: commands::Page { offset, limit },
+                mode: OutputMode::current(),
+            },
+        ),
+
+        Commands::Recent {
+            count,
+            since,
+            until,
+        } => commands::recent(storage, count, since.as_deref(), until.as_deref(), zone),
+
+        Commands::Search {
+            query,
+            names_only,
+            max_desc_len,
+            count_only,
+            in_labels,
+        } => commands::search(
+            storage,
+            &query,
+            &commands::SearchOptions {
+                names_only,
+                max_desc_len,
+                count_only,
+                no_abbrev: cli.no_abbrev,
+                labels: in_labels,
+            },
+        ),
+
+        Commands::History { label, count, json } => {
+            commands::history(storage, label.as_deref(), count, json, zone)
+        }
+
+        Commands::Labels { count } => commands::labels(storage, count, cli.null),
+
+        Commands::Stats { label, paths, json } => {
+            commands::stats(storage, label.as_deref(), paths, json, zone)
+        }
+
+        Commands::Count { by, json } => commands::count(storage, by, json),
+
+        Commands::Clone {
+            source,
+            dest,
+            with_sessions,
+        } => commands::clone_label(storage, &source, &dest, with_sessions),
+
+        Commands::Import {
+            file,
+            overwrite,
+            interactive,
+            prefix,
+            keep,
+            validate_only,
+        } => {
+            if validate_only {
+                commands::validate_import(&file, cli.strict_load)
+            } else {
+                commands::import(
+                    storage,
+                    &file,
+                    overwrite,
+                    interactive,
+                    prefix.as_deref(),
+                    keep.into(),
+                )
+            }
+        }
+
+        Commands::Export {
+            file,
+            format,
+            label,
+            anonymize,
+        } => commands::export(
+            storage,
+            file.as_deref(),
+            format,
+            label.as_deref(),
+            anonymize,
+        ),
+
+        Commands::Dedup { label, merge } => commands::dedup(storage, label.as_deref(), merge),
+
+        Commands::Remove {
+            label,
+            session_id,
+            all_sessions,
+            match_desc,
+            keep,
+            yes,
+            sessions,
+        } => commands::remove(
+            storage,
+            &label,
+            session_id.as_deref(),
+            &commands::RemoveOptions {
+                all_sessions,
+                match_desc,
+                keep,
+                yes,
+                sessions,
+            },
+        ),
+
+        Commands::Describe {
+            from_map: Some(file),
+            skip_unknown,
+            ..
+        } => commands::describe_from_map(storage, &file, skip_unknown),
+
+        Commands::Describe {
+            label,
+            description,
+            edit,
+            session,
+            create,
+            show,
+            template,
+            history,
+            from_map: None,
+            skip_unknown: _,
+        } => commands::describe(
+            storage,
+            &label_or_pick(label, storage)?,
+            description,
+            &commands::DescribeOptions {
+                edit,
+                session,
+                create,
+                show,
+                template,
+                history,
+                zone,
+            },
+        ),
+
+        Commands::RenameSession {
+            label,
+            old_id,
+            new_id,
+        } => commands::rename_session(storage, &label, &old_id, &new_id),
+
+        Commands::Touch {
+            label,
+            session_id,
+            all,
+        } => commands::touch(storage, &label, session_id.as_deref(), all),
+
+        Commands::Color { label, color } => commands::color(storage, &label, color.as_deref()),
+
+        Commands::Meta {
+            label,
+            session_id,
+            set,
+            unset,
+        } => commands::meta(storage, &label, &session_id, set, unset),
+
+        Commands::Browse => commands::browse(storage, cli.no_abbrev, cli.force, zone),
+
+        Commands::Watch { label, interval } => commands::watch(storage, &label, interval),
+
+        Commands::Sync { label } => commands::sync(storage, &label),
+
+        Commands::Sweep {
+            older_than,
+            prune_empty,
+        } => commands::sweep(storage, older_than, prune_empty, zone),
+
+        Commands::Alias { action } => match action {
+            None => commands::alias_list(storage),
+            Some(AliasAction::Add {
+                alias,
+                label,
+                create,
+            }) => commands::alias_add(storage, &alias, &label, create),
+            Some(AliasAction::Rm { alias }) => commands::alias_remove(storage, &alias),
+        },
+
+        Commands::Version { json } => commands::version(cli.verbose, json),
+
+        Commands::Doctor { fix_paths } => commands::doctor(storage, fix_paths),
+
+        Commands::Batch => run_batch(
+            cli,
+            storage,
+            zone,
+            io::stdin().lock(),
+            &StopFlag::on_ctrl_c()?,
+        ),
+
+        Commands::Config { action, json } => match action {
+            None => commands::config(storage, json),
+            Some(ConfigAction::Get { key }) => commands::config_get(storage, &key),
+            Some(ConfigAction::Set { key, value }) => commands::config_set(storage, &key, &value),
+            Some(ConfigAction::Profiles) => commands::config_profiles(storage),
+            Some(ConfigAction::Export { file }) => commands::config_export(storage, &file),
+            Some(ConfigAction::Import { file }) => commands::config_import(storage, &file),
+        },
+    }
+}
 
-        Commands::Resume { label, pick } => commands::resume(&storage, &label, pick),
+/// Why `command` can't run inside `batch`, if it can't: the batch holds the
+/// data lock, its store never changes underneath it, and stdin is the script.
+fn not_batchable(command: &Commands) -> Option<&'static str> {
+    match command {
+        Commands::Batch => Some("batch can't be nested"),
+        Commands::Resume { .. } | Commands::Add { resume: true, .. } => {
+            Some("resuming launches claude and can't run in a batch")
+        }
+        Commands::Watch { .. } => Some("watch never finishes and can't run in a batch"),
+        Commands::Browse => Some("browse is interactive and can't run in a batch"),
+        Commands::Doctor { fix_paths: true } => {
+            Some("doctor --fix-paths is interactive and can't run in a batch")
+        }
+        Commands::Import {
+            interactive: true, ..
+        } => Some("import --interactive can't run in a batch"),
+        Commands::Describe { edit: true, .. } => {
+            Some("describe --edit opens an editor and can't run in a batch")
+        }
+        Commands::Describe {
+            label: None,
+            from_map: None,
+            ..
+        } => Some("describe needs a label in a batch (there is no picker)"),
+        Commands::List { pick: true, .. } => {
+            Some("list --pick is interactive and can't run in a batch")
+        }
+        _ => None,
+    }
+}
 
-        Commands::List { label } => commands::list(&storage, label.as_deref()),
+/// Parse every line of `batch` input up front, so a typo anywhere stops the
+/// batch before anything runs. Blank lines and `#` comments are skipped.
+fn parse_batch<R: BufRead>(input: R) -> Result<Vec<(usize, Commands)>> {
+    let mut commands = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let number = index + 1;
+        let line = line.with_context(|| format!("Could not read line {}", number))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
 
-        Commands::Remove { label, session_id } => {
-            commands::remove(&storage, &label, session_id.as_deref())
+        let words =
+            shell_words::split(line).with_context(|| format!("line {}: bad quoting", number))?;
+        let parsed = match BatchLine::try_parse_from(words) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                let rendered = err.to_string();
+                let message = rendered.lines().next().unwrap_or_default();
+                bail!("line {}: {}", number, message.trim_start_matches("error: "));
+            }
+        };
+        if let Some(reason) = not_batchable(&parsed.command) {
+            bail!("line {}: {}", number, reason);
         }
+        commands.push((number, parsed.command));
+    }
+    Ok(commands)
+}
+
+/// Run `batch` input against one in-memory store. The data file is written
+/// once at the end, and not at all if any line fails. When `stop` is set the
+/// line in progress finishes and what ran so far is saved.
+fn run_batch<R: BufRead>(
+    cli: &Cli,
+    storage: &Storage,
+    zone: Option<DisplayZone>,
+    input: R,
+    stop: &StopFlag,
+) -> Result<()> {
+    let commands = parse_batch(input)?;
+    let total = commands.len();
+
+    storage.begin_batch()?;
+    let result = for_each_until_stopped(commands, stop, |(number, command)| {
+        execute(cli, command, storage, zone).with_context(|| format!("line {}", number))
+    });
+    let done = match result {
+        Ok(done) => done,
+        Err(err) => {
+            storage.abort_batch();
+            return Err(err);
+        }
+    };
+    storage.commit_batch()?;
+
+    if done < total {
+        eprintln!(
+            "{} Stopped after {} of {} commands; their changes were saved",
+            "!".yellow(),
+            done,
+            total
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn test_storage(name: &str) -> (Storage, PathBuf) {
+        let path = std::env::temp_dir().join(format!(
+            "claude-sessions-main-test-{}-{}.json",
+            name,
+            process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        (Storage::with_path(path.clone()), path)
+    }
+
+    fn cleanup(storage: &Storage, path: &Path) {
+        let _ = fs::remove_file(path);
+        let _ = fs::remove_file(storage.checksum_path());
+    }
+
+    fn batch_cli() -> Cli {
+        Cli::try_parse_from(["claude-sessions", "batch"]).unwrap()
+    }
+
+    // ==================== Batch Tests ====================
+
+    #[test]
+    fn test_batch_applies_lines_in_order() {
+        let (storage, path) = test_storage("batch-apply");
+        let input = "\
+# set up the work label
+add work s1 -d 'first session'
+add work s2
+
+describe work -d \"Work in progress\"
+remove work s1
+";
+
+        run_batch(
+            &batch_cli(),
+            &storage,
+            None,
+            input.as_bytes(),
+            &StopFlag::default(),
+        )
+        .unwrap();
+
+        let store = storage.load().unwrap();
+        let label = &store.labels["work"];
+        assert_eq!(label.description.as_deref(), Some("Work in progress"));
+        let ids: Vec<_> = label.sessions.iter().map(|s| &s.session_id).collect();
+        assert_eq!(ids, vec!["s2"]);
+        cleanup(&storage, &path);
+    }
+
+    #[test]
+    fn test_batch_bad_line_reports_number_and_saves_nothing() {
+        let (storage, path) = test_storage("batch-bad");
+        let input = "add work s1\n\nfrobnicate work\n";
+
+        let err = run_batch(
+            &batch_cli(),
+            &storage,
+            None,
+            input.as_bytes(),
+            &StopFlag::default(),
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().starts_with("line 3:"), "{}", err);
+        assert!(!path.exists());
+        cleanup(&storage, &path);
+    }
+
+    #[test]
+    fn test_batch_failing_line_saves_nothing() {
+        let (storage, path) = test_storage("batch-fail");
+        let input = "add work s1\nremove missing s1\n";
+
+        let err = run_batch(
+            &batch_cli(),
+            &storage,
+            None,
+            input.as_bytes(),
+            &StopFlag::default(),
+        )
+        .unwrap_err();
+
+        assert_eq!(err.to_string(), "line 2");
+        assert!(!path.exists());
+        cleanup(&storage, &path);
+    }
+
+    #[test]
+    fn test_batch_stopped_before_start_runs_nothing() {
+        let (storage, path) = test_storage("batch-stop");
+        let stop = StopFlag::default();
+        stop.request();
+
+        run_batch(
+            &batch_cli(),
+            &storage,
+            None,
+            "add work s1\n".as_bytes(),
+            &stop,
+        )
+        .unwrap();
+
+        assert!(storage.load().unwrap().labels.is_empty());
+        assert!(!storage.lock_path().exists());
+        cleanup(&storage, &path);
+    }
+
+    #[test]
+    fn test_list_pick_conflicts_with_label() {
+        let parsed = Cli::try_parse_from(["claude-sessions", "list", "work", "--pick"]);
+        assert_eq!(
+            parsed.err().map(|err| err.kind()),
+            Some(clap::error::ErrorKind::ArgumentConflict)
+        );
+        assert!(Cli::try_parse_from(["claude-sessions", "list", "--pick"]).is_ok());
+    }
+
+    #[test]
+    fn test_config_json_conflicts_with_subcommands() {
+        let parse = |args: &[&str]| {
+            Cli::try_parse_from(["claude-sessions", "config"].iter().chain(args))
+                .err()
+                .map(|err| err.kind())
+        };
+
+        assert_eq!(parse(&["--json"]), None);
+        assert_eq!(parse(&["get", "checksum"]), None);
+        assert!(parse(&["--json", "get", "checksum"]).is_some());
+        assert!(parse(&["get", "checksum", "--json"]).is_some());
+    }
+
+    #[test]
+    fn test_import_keep_maps_to_data_type() {
+        let cli =
+            Cli::try_parse_from(["claude-sessions", "import", "f.json", "--keep", "existing"])
+                .unwrap();
+        let Commands::Import { keep, .. } = cli.command else {
+            panic!("not an import");
+        };
+        assert_eq!(KeepOnConflict::from(keep), KeepOnConflict::Existing);
+        assert_eq!(
+            KeepOnConflict::from(KeepArg::default()),
+            KeepOnConflict::default()
+        );
+    }
+
+    #[test]
+    fn test_list_active_since_out_of_range() {
+        let parsed =
+            Cli::try_parse_from(["claude-sessions", "list", "--active-since", "20000000w"]);
+        assert_eq!(
+            parsed.err().map(|err| err.kind()),
+            Some(clap::error::ErrorKind::ValueValidation)
+        );
+    }
+
+    #[test]
+    fn test_sweep_older_than_out_of_range() {
+        let parsed = Cli::try_parse_from(["claude-sessions", "sweep", "--older-than", "20000000w"]);
+        assert_eq!(
+            parsed.err().map(|err| err.kind()),
+            Some(clap::error::ErrorKind::ValueValidation)
+        );
+    }
+
+    #[test]
+    fn test_remove_yes_requires_match_desc_or_keep() {
+        let parse =
+            |args: &[&str]| Cli::try_parse_from(["claude-sessions", "remove"].iter().chain(args));
+
+        for args in [&["work", "--yes"][..], &["work", "sess-1", "--yes"]] {
+            assert_eq!(
+                parse(args).err().map(|err| err.kind()),
+                Some(clap::error::ErrorKind::MissingRequiredArgument)
+            );
+        }
+        assert!(parse(&["work", "--match-desc", "scratch", "--yes"]).is_ok());
+        assert!(parse(&["work", "--keep", "sess-1", "--yes"]).is_ok());
+    }
+
+    #[test]
+    fn test_parse_batch_rejects_nested_batch() {
+        let Err(err) = parse_batch("batch\n".as_bytes()) else {
+            panic!("nested batch was accepted");
+        };
+        assert_eq!(err.to_string(), "line 1: batch can't be nested");
+    }
 
-        Commands::Describe { label, description } => {
-            commands::describe(&storage, &label, description)
+    #[test]
+    fn test_parse_batch_rejects_interactive_and_long_running_commands() {
+        for (line, expected) in [
+            ("resume work", "resuming launches claude"),
+            ("add work s1 --resume", "resuming launches claude"),
+            ("watch work", "watch never finishes"),
+            ("browse", "browse is interactive"),
+            ("doctor --fix-paths", "doctor --fix-paths is interactive"),
+            ("import file.json --interactive", "import --interactive"),
+            ("describe work --edit", "describe --edit opens an editor"),
+            ("describe", "describe needs a label"),
+            ("list --pick", "list --pick is interactive"),
+        ] {
+            let input = format!("add work s0\n{}\n", line);
+            let Err(err) = parse_batch(input.as_bytes()) else {
+                panic!("{:?} was accepted", line);
+            };
+            let message = err.to_string();
+            assert!(message.starts_with("line 2: "), "{}", message);
+            assert!(message.contains(expected), "{}", message);
         }
 
-        Commands::Config => commands::config(&storage),
+        // Their non-interactive forms are fine
+        let commands = parse_batch(
+            "doctor\nimport file.json\ndescribe work -d text\nadd work s1\n".as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(commands.len(), 4);
     }
 }
@@ -0,0 +1,275 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+
+/// Parse a `key=value` pair. The value may itself contain `=`.
+pub fn parse_kv(s: &str) -> Result<(String, String)> {
+    let Some((key, value)) = s.split_once('=') else {
+        bail!("Invalid key=value pair '{}': missing '='", s);
+    };
+
+    let key = key.trim();
+    if key.is_empty() {
+        bail!("Invalid key=value pair '{}': empty key", s);
+    }
+
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Parse a relative duration such as `30m`, `12h`, `7d` or `2w`.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .with_context(|| format!("Invalid duration '{}': missing unit (m, h, d, w)", s))?;
+    let (amount, unit) = s.split_at(split);
+
+    let amount: i64 = amount
+        .parse()
+        .with_context(|| format!("Invalid duration '{}': expected a number", s))?;
+
+    let duration = match unit {
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => bail!("Invalid duration '{}': unknown unit '{}'", s, unit),
+    };
+
+    duration.with_context(|| format!("Invalid duration '{}': out of range", s))
+}
+
+/// The instant `duration` before `now`, or an error when that is earlier than
+/// chrono can represent.
+pub fn time_ago(duration: Duration, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    now.checked_sub_signed(duration)
+        .context("Duration reaches past the earliest representable time")
+}
+
+/// `parse_duration` for look-back windows such as `--older-than`: also rejects
+/// durations that reach past the earliest representable time.
+pub fn parse_lookback(s: &str) -> Result<Duration> {
+    let duration = parse_duration(s)?;
+    if time_ago(duration, Utc::now()).is_err() {
+        bail!("Invalid duration '{}': out of range", s.trim());
+    }
+    Ok(duration)
+}
+
+/// Parse an absolute timestamp: RFC 3339, `YYYY-MM-DD HH:MM[:SS]` or `YYYY-MM-DD`.
+/// Values without an offset are interpreted as UTC.
+pub fn parse_datetime(s: &str) -> Result<DateTime<Utc>> {
+    let s = s.trim();
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+
+    for format in [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, format) {
+            return Ok(dt.and_utc());
+        }
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+
+    bail!(
+        "Invalid timestamp '{}': expected YYYY-MM-DD or an ISO-8601 date-time",
+        s
+    )
+}
+
+/// Parse a point in time given either as a duration ago (`7d`) or an absolute timestamp.
+pub fn parse_time_bound(s: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    match parse_duration(s) {
+        Ok(duration) => match now.checked_sub_signed(duration) {
+            Some(bound) => Ok(bound),
+            None => bail!("Invalid duration '{}': out of range", s),
+        },
+        Err(_) => parse_datetime(s),
+    }
+}
+
+/// Like `parse_time_bound`, for the end of a range: a bare `YYYY-MM-DD`
+/// means the last instant of that day, so the whole day is included.
+pub fn parse_time_upper_bound(s: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d") {
+        let next_day = date
+            .succ_opt()
+            .with_context(|| format!("Invalid timestamp '{}': date out of range", s))?;
+        return Ok(next_day.and_hms_opt(0, 0, 0).unwrap().and_utc() - Duration::nanoseconds(1));
+    }
+    parse_time_bound(s, now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // ==================== Key/Value Tests ====================
+
+    #[test]
+    fn test_parse_kv_valid() {
+        let (key, value) = parse_kv("model=opus").unwrap();
+
+        assert_eq!(key, "model");
+        assert_eq!(value, "opus");
+    }
+
+    #[test]
+    fn test_parse_kv_value_with_equals() {
+        let (key, value) = parse_kv("url=https://example.com/?a=b").unwrap();
+
+        assert_eq!(key, "url");
+        assert_eq!(value, "https://example.com/?a=b");
+    }
+
+    #[test]
+    fn test_parse_kv_empty_value_allowed() {
+        let (key, value) = parse_kv("reviewer=").unwrap();
+
+        assert_eq!(key, "reviewer");
+        assert_eq!(value, "");
+    }
+
+    #[test]
+    fn test_parse_kv_missing_equals() {
+        assert!(parse_kv("model").is_err());
+    }
+
+    #[test]
+    fn test_parse_kv_empty_key() {
+        assert!(parse_kv("=opus").is_err());
+        assert!(parse_kv("  =opus").is_err());
+    }
+
+    // ==================== Duration Tests ====================
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("30m").unwrap(), Duration::minutes(30));
+        assert_eq!(parse_duration("12h").unwrap(), Duration::hours(12));
+        assert_eq!(parse_duration("7d").unwrap(), Duration::days(7));
+        assert_eq!(parse_duration("2w").unwrap(), Duration::weeks(2));
+    }
+
+    #[test]
+    fn test_parse_duration_invalid() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("7y").is_err());
+        assert!(parse_duration("-7d").is_err());
+    }
+
+    #[test]
+    fn test_parse_lookback_rejects_unrepresentable_window() {
+        assert_eq!(parse_lookback("90d").unwrap(), Duration::days(90));
+
+        let err = parse_lookback("20000000w").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid duration '20000000w': out of range"
+        );
+    }
+
+    #[test]
+    fn test_time_ago_out_of_range() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            time_ago(Duration::days(1), now).unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 14, 12, 0, 0).unwrap()
+        );
+        assert!(time_ago(Duration::weeks(20_000_000), now).is_err());
+    }
+
+    // ==================== Datetime Tests ====================
+
+    #[test]
+    fn test_parse_datetime_date_only() {
+        let dt = parse_datetime("2024-06-15").unwrap();
+
+        assert_eq!(dt, Utc.with_ymd_and_hms(2024, 6, 15, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_parse_datetime_rfc3339_with_offset() {
+        let dt = parse_datetime("2024-06-15T12:30:00+02:00").unwrap();
+
+        assert_eq!(dt, Utc.with_ymd_and_hms(2024, 6, 15, 10, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn test_parse_datetime_rfc3339_utc() {
+        let dt = parse_datetime("2024-06-15T12:30:45Z").unwrap();
+
+        assert_eq!(dt, Utc.with_ymd_and_hms(2024, 6, 15, 12, 30, 45).unwrap());
+    }
+
+    #[test]
+    fn test_parse_datetime_naive_is_utc() {
+        let dt = parse_datetime("2024-06-15 12:30").unwrap();
+
+        assert_eq!(dt, Utc.with_ymd_and_hms(2024, 6, 15, 12, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn test_parse_datetime_invalid() {
+        assert!(parse_datetime("yesterday").is_err());
+        assert!(parse_datetime("2024-13-01").is_err());
+        assert!(parse_datetime("2024-06-15T25:00:00Z").is_err());
+        assert!(parse_datetime("").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_bound_duration_and_date() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            parse_time_bound("1d", now).unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 14, 12, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse_time_bound("2024-01-01", now).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_time_bound_huge_duration_is_error() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+
+        let err = parse_time_bound("20000000w", now).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Invalid duration '20000000w': out of range"
+        );
+    }
+
+    #[test]
+    fn test_parse_time_upper_bound_date_covers_whole_day() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+        let end = parse_time_upper_bound("2024-03-05", now).unwrap();
+
+        assert!(end > Utc.with_ymd_and_hms(2024, 3, 5, 23, 59, 59).unwrap());
+        assert!(end < Utc.with_ymd_and_hms(2024, 3, 6, 0, 0, 0).unwrap());
+        // Durations and explicit times are taken as given
+        assert_eq!(
+            parse_time_upper_bound("1d", now).unwrap(),
+            parse_time_bound("1d", now).unwrap()
+        );
+        assert_eq!(
+            parse_time_upper_bound("2024-03-05 10:00", now).unwrap(),
+            Utc.with_ymd_and_hms(2024, 3, 5, 10, 0, 0).unwrap()
+        );
+    }
+}
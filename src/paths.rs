@@ -0,0 +1,261 @@
+use anyhow::{bail, Result};
+use directories::BaseDirs;
+use std::path::{Component, Path, PathBuf};
+
+/// Resolve `.` and `..` components without touching the filesystem.
+pub fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Whether `path` is `root` or lies beneath it, compared component by component
+/// (so `/work/app-old` is not under `/work/app`).
+pub fn path_is_under(path: &Path, root: &Path) -> bool {
+    normalize_lexically(path).starts_with(normalize_lexically(root))
+}
+
+/// Join `subdir` onto `base` and check the result is an existing directory.
+/// Unless `allow_escape` is set, the result must stay inside `base`.
+pub fn resolve_subdir(base: &Path, subdir: &str, allow_escape: bool) -> Result<PathBuf> {
+    let joined = normalize_lexically(&base.join(subdir));
+
+    if !allow_escape && !path_is_under(&joined, base) {
+        bail!(
+            "Subdirectory '{}' escapes the session path {:?} (use --allow-escape to permit this)",
+            subdir,
+            base
+        );
+    }
+
+    if !joined.is_dir() {
+        bail!("Directory {:?} does not exist", joined);
+    }
+
+    Ok(joined)
+}
+
+/// `path` itself if it is a directory, otherwise its closest ancestor that
+/// is. `None` when no ancestor exists (only possible for relative paths).
+pub fn nearest_existing_dir(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .find(|dir| dir.is_dir())
+        .map(Path::to_path_buf)
+}
+
+/// The current user's home directory, if it can be determined.
+pub fn home_dir() -> Option<PathBuf> {
+    BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf())
+}
+
+/// Show `path` with a leading `home` replaced by `~`, for display only.
+/// Paths outside `home` (or a `home` of `/`) are returned unchanged.
+pub fn abbreviate_home(path: &Path, home: &Path) -> String {
+    if home.parent().is_none() {
+        return path.display().to_string();
+    }
+    match path.strip_prefix(home) {
+        Ok(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Ok(rest) => format!("~/{}", rest.display()),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "claude-sessions-paths-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cleanup(dir: &Path) {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    // ==================== Normalization Tests ====================
+
+    #[test]
+    fn test_normalize_lexically() {
+        assert_eq!(
+            normalize_lexically(Path::new("/a/b/../c/./d")),
+            PathBuf::from("/a/c/d")
+        );
+        assert_eq!(normalize_lexically(Path::new("/a/..")), PathBuf::from("/"));
+    }
+
+    #[test]
+    fn test_path_is_under_is_component_aware() {
+        assert!(path_is_under(
+            Path::new("/work/app"),
+            Path::new("/work/app")
+        ));
+        assert!(path_is_under(
+            Path::new("/work/app/src"),
+            Path::new("/work/app")
+        ));
+        assert!(!path_is_under(
+            Path::new("/work/app-old"),
+            Path::new("/work/app")
+        ));
+        assert!(!path_is_under(
+            Path::new("/work/app/../other"),
+            Path::new("/work/app")
+        ));
+    }
+
+    // ==================== Subdirectory Tests ====================
+
+    #[test]
+    fn test_resolve_subdir_joins_existing_dir() {
+        let base = temp_dir("join");
+        fs::create_dir_all(base.join("packages/api")).unwrap();
+
+        let resolved = resolve_subdir(&base, "packages/api", false).unwrap();
+
+        assert_eq!(resolved, base.join("packages/api"));
+        cleanup(&base);
+    }
+
+    #[test]
+    fn test_resolve_subdir_missing_dir_returns_error() {
+        let base = temp_dir("missing");
+
+        assert!(resolve_subdir(&base, "does-not-exist", false).is_err());
+        cleanup(&base);
+    }
+
+    #[test]
+    fn test_resolve_subdir_rejects_escape() {
+        let base = temp_dir("escape");
+        fs::create_dir_all(base.join("inner")).unwrap();
+        let inner = base.join("inner");
+
+        assert!(resolve_subdir(&inner, "..", false).is_err());
+        assert!(resolve_subdir(&inner, "../inner/../..", false).is_err());
+        assert!(resolve_subdir(&inner, "/tmp", false).is_err());
+        cleanup(&base);
+    }
+
+    #[test]
+    fn test_resolve_subdir_allows_escape_when_requested() {
+        let base = temp_dir("allow-escape");
+        fs::create_dir_all(base.join("inner")).unwrap();
+
+        let resolved = resolve_subdir(&base.join("inner"), "..", true).unwrap();
+
+        assert_eq!(resolved, base);
+        cleanup(&base);
+    }
+
+    #[test]
+    fn test_resolve_subdir_dotdot_that_stays_inside() {
+        let base = temp_dir("stays-inside");
+        fs::create_dir_all(base.join("a")).unwrap();
+        fs::create_dir_all(base.join("b")).unwrap();
+
+        let resolved = resolve_subdir(&base, "a/../b", false).unwrap();
+
+        assert_eq!(resolved, base.join("b"));
+        cleanup(&base);
+    }
+
+    // ==================== Nearest Existing Dir Tests ====================
+
+    #[test]
+    fn test_nearest_existing_dir_is_path_when_it_exists() {
+        let base = temp_dir("nearest-exists");
+
+        assert_eq!(nearest_existing_dir(&base), Some(base.clone()));
+        cleanup(&base);
+    }
+
+    #[test]
+    fn test_nearest_existing_dir_walks_up_to_ancestor() {
+        let base = temp_dir("nearest-ancestor");
+        fs::create_dir_all(base.join("project")).unwrap();
+
+        let deleted = base.join("project").join("worktree").join("src");
+
+        assert_eq!(nearest_existing_dir(&deleted), Some(base.join("project")));
+        cleanup(&base);
+    }
+
+    #[test]
+    fn test_nearest_existing_dir_none_when_no_ancestor_exists() {
+        let path = Path::new("claude-sessions-no-such-root").join("gone");
+
+        assert_eq!(nearest_existing_dir(&path), None);
+    }
+
+    #[test]
+    fn test_nearest_existing_dir_skips_files() {
+        let base = temp_dir("nearest-file");
+        fs::write(base.join("notes.txt"), "").unwrap();
+
+        assert_eq!(
+            nearest_existing_dir(&base.join("notes.txt").join("sub")),
+            Some(base.clone())
+        );
+        cleanup(&base);
+    }
+
+    // ==================== Abbreviate Home Tests ====================
+
+    #[test]
+    fn test_abbreviate_home_inside_home() {
+        let home = Path::new("/home/me");
+
+        assert_eq!(abbreviate_home(Path::new("/home/me/proj"), home), "~/proj");
+        assert_eq!(
+            abbreviate_home(Path::new("/home/me/work/app/src"), home),
+            "~/work/app/src"
+        );
+    }
+
+    #[test]
+    fn test_abbreviate_home_equal_to_home() {
+        let home = Path::new("/home/me");
+
+        assert_eq!(abbreviate_home(Path::new("/home/me"), home), "~");
+        assert_eq!(abbreviate_home(Path::new("/home/me/"), home), "~");
+    }
+
+    #[test]
+    fn test_abbreviate_home_outside_home() {
+        let home = Path::new("/home/me");
+
+        assert_eq!(abbreviate_home(Path::new("/tmp/proj"), home), "/tmp/proj");
+        // A sibling sharing the prefix as text is not inside home
+        assert_eq!(
+            abbreviate_home(Path::new("/home/meow/proj"), home),
+            "/home/meow/proj"
+        );
+        assert_eq!(abbreviate_home(Path::new("relative"), home), "relative");
+    }
+
+    #[test]
+    fn test_abbreviate_home_root_home_is_ignored() {
+        assert_eq!(abbreviate_home(Path::new("/etc"), Path::new("/")), "/etc");
+    }
+}
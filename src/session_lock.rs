@@ -0,0 +1,405 @@
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Marks a session as running: a `<session-id>.lock` file holding the PID of
+/// the `claude-sessions` process that resumed it. Removed again on drop.
+#[derive(Debug)]
+pub struct SessionLock {
+    path: PathBuf,
+    pid: u32,
+}
+
+/// Where session locks live: `$XDG_RUNTIME_DIR/claude-sessions`, or a
+/// per-user directory in the temp directory when that isn't set. Nothing
+/// there needs to survive a reboot.
+pub fn runtime_dir() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join("claude-sessions"),
+        None => env::temp_dir().join(temp_dir_name()),
+    }
+}
+
+/// The temp directory is shared, so the lock directory there is named for
+/// the user; `private_dir` then makes sure nobody else owns it.
+#[cfg(unix)]
+fn temp_dir_name() -> String {
+    format!("claude-sessions-{}", unsafe { libc::getuid() })
+}
+
+#[cfg(not(unix))]
+fn temp_dir_name() -> String {
+    "claude-sessions".to_string()
+}
+
+/// Create `dir` readable by this user only, and refuse one that already
+/// exists but belongs to someone else or is writable by others, since
+/// whoever controls it can plant or delete our locks.
+#[cfg(unix)]
+fn private_dir(dir: &Path) -> Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .with_context(|| format!("Could not create {:?}", dir))?;
+    let meta = fs::symlink_metadata(dir).with_context(|| format!("Could not read {:?}", dir))?;
+    let uid = unsafe { libc::getuid() };
+    if !meta.is_dir() || meta.uid() != uid || meta.mode() & 0o022 != 0 {
+        bail!(
+            "Refusing to keep session locks in {:?}: it is not a private directory owned by you",
+            dir
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn private_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("Could not create {:?}", dir))
+}
+
+/// Lock files are named after the session id with everything but lowercase
+/// letters, digits, `-` and `_` written as `%XX`, so two ids never share a
+/// lock, even on a case-insensitive filesystem.
+fn lock_path(dir: &Path, session_id: &str) -> PathBuf {
+    let mut name = String::with_capacity(session_id.len());
+    for byte in session_id.bytes() {
+        if byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'-' || byte == b'_' {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    dir.join(format!("{}.lock", name))
+}
+
+/// A name next to `path` that no other acquire, in this process or another,
+/// will pick at the same time.
+fn scratch_path(path: &Path, purpose: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}.{}.{}", purpose, process::id(), n));
+    PathBuf::from(name)
+}
+
+/// Publish a lock file holding `pid` at `path`, failing with `AlreadyExists`
+/// if there is one. The file is written aside and hard-linked into place, so
+/// nobody ever sees a lock without its PID and mistakes it for a stale one.
+fn create_lock_file(path: &Path, pid: u32) -> std::io::Result<()> {
+    let scratch = scratch_path(path, "new");
+    let result = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&scratch)
+        .and_then(|mut file| write!(file, "{}", pid))
+        .and_then(|()| fs::hard_link(&scratch, path));
+    let _ = fs::remove_file(&scratch);
+    result
+}
+
+/// The PID recorded in a lock file, if it can be read.
+fn lock_owner(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether a process with this PID exists.
+#[cfg(unix)]
+pub fn pid_alive(pid: u32) -> bool {
+    // 0 and anything past pid_t would address process groups, not a process
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    if pid <= 0 {
+        return false;
+    }
+    // Signal 0 only checks the process exists; EPERM means it does but isn't ours
+    let found = unsafe { libc::kill(pid, 0) } == 0;
+    found || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Without a way to check, every recorded PID is assumed to be running.
+#[cfg(not(unix))]
+pub fn pid_alive(_pid: u32) -> bool {
+    true
+}
+
+/// How often `acquire` clears an existing lock and tries again before
+/// giving up on a session whose lock keeps reappearing.
+const TAKEOVER_ATTEMPTS: usize = 5;
+
+fn already_running(session_id: &str, owner: u32) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Session '{}' is already running (PID {}); pass --force to resume it anyway",
+        session_id,
+        owner
+    )
+}
+
+impl SessionLock {
+    /// Lock `session_id` in `dir`. A lock left by a process that no longer
+    /// exists is taken over; a live one is an error unless `force` is set.
+    pub fn acquire(dir: &Path, session_id: &str, force: bool) -> Result<SessionLock> {
+        private_dir(dir)?;
+        let path = lock_path(dir, session_id);
+        let pid = process::id();
+
+        for _ in 0..TAKEOVER_ATTEMPTS {
+            match create_lock_file(&path, pid) {
+                Ok(()) => return Ok(SessionLock { path, pid }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Could not create lock file {:?}", path))
+                }
+            }
+            if !force {
+                if let Some(owner) = lock_owner(&path).filter(|&owner| pid_alive(owner)) {
+                    return Err(already_running(session_id, owner));
+                }
+            }
+            // Move the old lock aside before deleting it: of two resumes
+            // taking over at once only one gets it, and the other finds the
+            // winner's lock on its next attempt
+            let aside = scratch_path(&path, "old");
+            match fs::rename(&path, &aside) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Could not remove lock file {:?}", path))
+                }
+            }
+            let moved = lock_owner(&aside).filter(|&owner| pid_alive(owner));
+            if let Some(owner) = moved.filter(|_| !force) {
+                // Lost the race to a fresh lock after all: put it back
+                let _ = fs::hard_link(&aside, &path);
+                let _ = fs::remove_file(&aside);
+                return Err(already_running(session_id, owner));
+            }
+            let _ = fs::remove_file(&aside);
+        }
+        bail!(
+            "Could not lock session '{}': its lock file {:?} keeps reappearing",
+            session_id,
+            path
+        )
+    }
+}
+
+impl Drop for SessionLock {
+    fn drop(&mut self) {
+        // A forced resume may have taken the lock over; leave that one alone
+        if lock_owner(&self.path) == Some(self.pid) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "claude-sessions-lock-test-{}-{}",
+            name,
+            process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// The PID of a process that has already exited.
+    #[cfg(unix)]
+    fn dead_pid() -> u32 {
+        let mut child = Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        pid
+    }
+
+    // ==================== Pid Tests ====================
+
+    #[test]
+    fn test_pid_alive_for_this_process() {
+        assert!(pid_alive(process::id()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pid_alive_false_after_exit() {
+        assert!(!pid_alive(dead_pid()));
+        assert!(!pid_alive(0));
+    }
+
+    // ==================== Lock Tests ====================
+
+    #[test]
+    fn test_acquire_writes_pid_and_drop_removes() {
+        let dir = temp_dir("acquire");
+        let path = lock_path(&dir, "sess-1");
+
+        let lock = SessionLock::acquire(&dir, "sess-1", false).unwrap();
+        assert_eq!(lock_owner(&path), Some(process::id()));
+
+        drop(lock);
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_acquire_refuses_live_lock() {
+        let dir = temp_dir("live");
+        // Our own PID is as alive as it gets
+        let _held = SessionLock::acquire(&dir, "sess-1", false).unwrap();
+
+        let err = SessionLock::acquire(&dir, "sess-1", false).unwrap_err();
+
+        assert!(err.to_string().contains("already running"), "{}", err);
+        assert!(err.to_string().contains("--force"), "{}", err);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_acquire_force_overrides_live_lock() {
+        let dir = temp_dir("force");
+        fs::create_dir_all(&dir).unwrap();
+        let path = lock_path(&dir, "sess-1");
+        fs::write(&path, "1").unwrap();
+
+        let lock = SessionLock::acquire(&dir, "sess-1", true).unwrap();
+
+        assert_eq!(lock_owner(&path), Some(process::id()));
+        drop(lock);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_acquire_reclaims_stale_lock() {
+        let dir = temp_dir("stale");
+        fs::create_dir_all(&dir).unwrap();
+        let path = lock_path(&dir, "sess-1");
+        fs::write(&path, dead_pid().to_string()).unwrap();
+
+        let lock = SessionLock::acquire(&dir, "sess-1", false).unwrap();
+
+        assert_eq!(lock_owner(&path), Some(process::id()));
+        drop(lock);
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_acquire_reclaims_unreadable_lock() {
+        let dir = temp_dir("garbage");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(lock_path(&dir, "sess-1"), "not a pid").unwrap();
+
+        assert!(SessionLock::acquire(&dir, "sess-1", false).is_ok());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_drop_leaves_lock_taken_over_by_another_process() {
+        let dir = temp_dir("taken");
+        let path = lock_path(&dir, "sess-1");
+        let lock = SessionLock::acquire(&dir, "sess-1", false).unwrap();
+        fs::write(&path, "1").unwrap();
+
+        drop(lock);
+
+        assert!(path.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_lock_path_encodes_id() {
+        assert_eq!(
+            lock_path(Path::new("/run"), "../a b"),
+            PathBuf::from("/run/%2E%2E%2Fa%20b.lock")
+        );
+        assert_eq!(
+            lock_path(Path::new("/run"), "sess-1_a"),
+            PathBuf::from("/run/sess-1_a.lock")
+        );
+    }
+
+    #[test]
+    fn test_lock_path_keeps_distinct_ids_apart() {
+        let dir = Path::new("/run");
+        let ids = ["a/b", "a_b", "a b", "A_b", "a%2Fb"];
+        let paths: std::collections::HashSet<String> = ids
+            .iter()
+            .map(|id| lock_path(dir, id).to_string_lossy().to_lowercase())
+            .collect();
+        assert_eq!(paths.len(), ids.len(), "{:?}", paths);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_acquire_creates_private_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("private");
+        let _lock = SessionLock::acquire(&dir, "sess-1", false).unwrap();
+
+        let mode = fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_acquire_refuses_dir_writable_by_others() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("shared");
+        fs::create_dir_all(&dir).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+
+        let err = SessionLock::acquire(&dir, "sess-1", false).unwrap_err();
+
+        assert!(
+            err.to_string().contains("not a private directory"),
+            "{}",
+            err
+        );
+        assert!(!lock_path(&dir, "sess-1").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_concurrent_takeover_of_stale_lock_has_one_winner() {
+        let dir = temp_dir("race");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(lock_path(&dir, "sess-1"), dead_pid().to_string()).unwrap();
+
+        // All threads share this process's PID, so whichever wins holds a
+        // lock the others see as live
+        let results: Vec<_> = (0..8)
+            .map(|_| {
+                let dir = dir.clone();
+                std::thread::spawn(move || SessionLock::acquire(&dir, "sess-1", false))
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+
+        let winners = results.iter().filter(|result| result.is_ok()).count();
+        assert_eq!(winners, 1);
+        for err in results.iter().filter_map(|result| result.as_ref().err()) {
+            assert!(err.to_string().contains("already running"), "{}", err);
+        }
+        drop(results);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::format::DisplayZone;
+
+/// User preferences persisted alongside the labels.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// Write a `.sha256` sidecar on save and verify it on load.
+    #[serde(default)]
+    pub checksum: bool,
+    /// How labels are laid out on disk.
+    #[serde(default)]
+    pub layout: Layout,
+    /// Order each label's sessions by `created_at` when saving.
+    #[serde(default)]
+    pub sort_sessions_on_save: bool,
+    /// Zone timestamps are displayed in: `utc`, `local` or an IANA name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+    /// Directories `add` may record sessions under; empty allows any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_roots: Vec<String>,
+    /// Short names mapped to the label they stand for.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub aliases: HashMap<String, String>,
+}
+
+/// Where labels are stored: inside `data.json`, or one file per label under
+/// `labels/` next to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    #[default]
+    Single,
+    Flat,
+}
+
+impl Layout {
+    fn as_str(self) -> &'static str {
+        match self {
+            Layout::Single => "single",
+            Layout::Flat => "flat",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "single" => Ok(Layout::Single),
+            "flat" => Ok(Layout::Flat),
+            _ => bail!("Invalid layout '{}': expected single or flat", value),
+        }
+    }
+}
+
+impl Settings {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The configured display zone; UTC when unset or unrecognized.
+    pub fn display_zone(&self) -> DisplayZone {
+        self.time_zone
+            .as_deref()
+            .and_then(|name| DisplayZone::parse(name).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// A preference that can be read and written with `config get`/`config set`.
+pub struct SettingKey {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub get: fn(&Settings) -> String,
+    pub set: fn(&mut Settings, &str) -> Result<()>,
+}
+
+pub const SETTING_KEYS: &[SettingKey] = &[
+    SettingKey {
+        name: "checksum",
+        description: "Write and verify a SHA-256 checksum of the data file (true/false)",
+        get: |s| s.checksum.to_string(),
+        set: |s, v| {
+            s.checksum = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    SettingKey {
+        name: "layout",
+        description: "Store labels in data.json or one file per label under labels/ (single/flat)",
+        get: |s| s.layout.as_str().to_string(),
+        set: |s, v| {
+            s.layout = Layout::parse(v)?;
+            Ok(())
+        },
+    },
+    SettingKey {
+        name: "sort_sessions_on_save",
+        description: "Write each label's sessions ordered by creation time (true/false)",
+        get: |s| s.sort_sessions_on_save.to_string(),
+        set: |s, v| {
+            s.sort_sessions_on_save = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    SettingKey {
+        name: "time_zone",
+        description: "Show timestamps in utc, local or an IANA zone such as Europe/Berlin",
+        get: |s| s.display_zone().name(),
+        set: |s, v| {
+            s.time_zone = match DisplayZone::parse(v)? {
+                DisplayZone::Utc => None,
+                zone => Some(zone.name()),
+            };
+            Ok(())
+        },
+    },
+    SettingKey {
+        name: "allowed_roots",
+        description: "Comma-separated directories add may record sessions under (empty allows any)",
+        get: |s| s.allowed_roots.join(","),
+        set: |s, v| {
+            s.allowed_roots = v
+                .split(',')
+                .map(str::trim)
+                .filter(|root| !root.is_empty())
+                .map(str::to_string)
+                .collect();
+            Ok(())
+        },
+    },
+];
+
+pub fn find_key(name: &str) -> Result<&'static SettingKey> {
+    match SETTING_KEYS.iter().find(|k| k.name == name) {
+        Some(key) => Ok(key),
+        None => {
+            let known: Vec<&str> = SETTING_KEYS.iter().map(|k| k.name).collect();
+            bail!(
+                "Unknown setting '{}' (known settings: {})",
+                name,
+                known.join(", ")
+            )
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => bail!("Invalid value '{}': expected true or false", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ==================== Settings Tests ====================
+
+    #[test]
+    fn test_default_settings_is_default() {
+        assert!(Settings::default().is_default());
+    }
+
+    #[test]
+    fn test_set_get_roundtrip() {
+        let mut settings = Settings::default();
+        let key = find_key("checksum").unwrap();
+
+        (key.set)(&mut settings, "true").unwrap();
+        assert_eq!((key.get)(&settings), "true");
+        assert!(!settings.is_default());
+
+        (key.set)(&mut settings, "off").unwrap();
+        assert_eq!((key.get)(&settings), "false");
+    }
+
+    #[test]
+    fn test_unknown_key_returns_error() {
+        assert!(find_key("theme-color").is_err());
+    }
+
+    #[test]
+    fn test_bad_value_returns_error() {
+        let mut settings = Settings::default();
+        let key = find_key("checksum").unwrap();
+
+        assert!((key.set)(&mut settings, "maybe").is_err());
+        assert!(!settings.checksum);
+    }
+
+    #[test]
+    fn test_layout_set_get_roundtrip() {
+        let mut settings = Settings::default();
+        let key = find_key("layout").unwrap();
+        assert_eq!((key.get)(&settings), "single");
+
+        (key.set)(&mut settings, "FLAT").unwrap();
+        assert_eq!(settings.layout, Layout::Flat);
+        assert_eq!((key.get)(&settings), "flat");
+
+        assert!((key.set)(&mut settings, "nested").is_err());
+        assert_eq!(settings.layout, Layout::Flat);
+    }
+
+    #[test]
+    fn test_layout_serializes_lowercase() {
+        let settings = Settings {
+            layout: Layout::Flat,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        assert!(json.contains(r#""layout":"flat""#));
+    }
+
+    #[test]
+    fn test_time_zone_set_get_roundtrip() {
+        let mut settings = Settings::default();
+        let key = find_key("time_zone").unwrap();
+        assert_eq!((key.get)(&settings), "utc");
+
+        (key.set)(&mut settings, "Asia/Tokyo").unwrap();
+        assert_eq!((key.get)(&settings), "Asia/Tokyo");
+        assert_eq!(settings.display_zone().name(), "Asia/Tokyo");
+
+        assert!((key.set)(&mut settings, "Nowhere/Special").is_err());
+        assert_eq!((key.get)(&settings), "Asia/Tokyo");
+
+        // Back to UTC leaves the settings at their default
+        (key.set)(&mut settings, "UTC").unwrap();
+        assert!(settings.is_default());
+    }
+
+    #[test]
+    fn test_allowed_roots_set_get_roundtrip() {
+        let mut settings = Settings::default();
+        let key = find_key("allowed_roots").unwrap();
+        assert_eq!((key.get)(&settings), "");
+
+        (key.set)(&mut settings, "/work/mono, /work/tools,").unwrap();
+        assert_eq!(settings.allowed_roots, vec!["/work/mono", "/work/tools"]);
+        assert_eq!((key.get)(&settings), "/work/mono,/work/tools");
+
+        (key.set)(&mut settings, "").unwrap();
+        assert!(settings.is_default());
+    }
+
+    #[test]
+    fn test_keys_are_unique() {
+        for (i, key) in SETTING_KEYS.iter().enumerate() {
+            assert!(SETTING_KEYS[i + 1..].iter().all(|k| k.name != key.name));
+        }
+    }
+}
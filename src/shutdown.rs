@@ -0,0 +1,169 @@
+use anyhow::{Context, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+/// Set when the user asks a long-running command to stop, so it can finish
+/// the item in progress, save and report instead of dying mid-write.
+#[derive(Debug, Clone, Default)]
+pub struct StopFlag(Arc<AtomicBool>);
+
+impl StopFlag {
+    /// The flag Ctrl-C sets. The handler is installed on first use, so only
+    /// the commands that ask for it stop gracefully; everything else keeps
+    /// the default of exiting at once.
+    pub fn on_ctrl_c() -> Result<StopFlag> {
+        static FLAG: OnceLock<StopFlag> = OnceLock::new();
+        if let Some(flag) = FLAG.get() {
+            return Ok(flag.clone());
+        }
+
+        let flag = StopFlag::default();
+        let handler_flag = flag.clone();
+        ctrlc::set_handler(move || handler_flag.request())
+            .context("Could not install the Ctrl-C handler")?;
+        Ok(FLAG.get_or_init(|| flag).clone())
+    }
+
+    pub fn request(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Run `step` on each item until the items run out or `stop` is set. The
+/// check happens between items, so one in progress always finishes. Returns
+/// how many items were processed.
+pub fn for_each_until_stopped<I, F>(items: I, stop: &StopFlag, mut step: F) -> Result<usize>
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> Result<()>,
+{
+    let mut done = 0;
+    for item in items {
+        if stop.is_set() {
+            break;
+        }
+        step(item)?;
+        done += 1;
+    }
+    Ok(done)
+}
+
+/// Sleep for `duration`, waking early once `stop` is set. Returns whether it
+/// was stopped.
+pub fn sleep_unless_stopped(duration: Duration, stop: &StopFlag) -> bool {
+    const TICK: Duration = Duration::from_millis(100);
+    let mut left = duration;
+    while !left.is_zero() {
+        if stop.is_set() {
+            return true;
+        }
+        let nap = left.min(TICK);
+        thread::sleep(nap);
+        left -= nap;
+    }
+    stop.is_set()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::bail;
+    use std::time::Instant;
+
+    // ==================== Stop Flag Tests ====================
+
+    #[test]
+    fn test_stop_flag_is_shared_between_clones() {
+        let flag = StopFlag::default();
+        let copy = flag.clone();
+        assert!(!copy.is_set());
+
+        flag.request();
+
+        assert!(copy.is_set());
+    }
+
+    // ==================== Loop Tests ====================
+
+    #[test]
+    fn test_for_each_until_stopped_runs_everything_without_stop() {
+        let stop = StopFlag::default();
+        let mut seen = Vec::new();
+
+        let done = for_each_until_stopped(1..=4, &stop, |n| {
+            seen.push(n);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(done, 4);
+        assert_eq!(seen, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_for_each_until_stopped_finishes_current_item() {
+        let stop = StopFlag::default();
+        let mut seen = Vec::new();
+
+        let done = for_each_until_stopped(1..=4, &stop, |n| {
+            if n == 2 {
+                // Arrives mid-item: item 2 still completes, item 3 never starts
+                stop.request();
+            }
+            seen.push(n);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(done, 2);
+        assert_eq!(seen, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_for_each_until_stopped_already_stopped_does_nothing() {
+        let stop = StopFlag::default();
+        stop.request();
+
+        let done = for_each_until_stopped(1..=4, &stop, |_| bail!("should not run")).unwrap();
+
+        assert_eq!(done, 0);
+    }
+
+    #[test]
+    fn test_for_each_until_stopped_propagates_errors() {
+        let stop = StopFlag::default();
+
+        let result = for_each_until_stopped(1..=4, &stop, |n| {
+            if n == 3 {
+                bail!("item {} failed", n);
+            }
+            Ok(())
+        });
+
+        assert_eq!(result.unwrap_err().to_string(), "item 3 failed");
+    }
+
+    // ==================== Sleep Tests ====================
+
+    #[test]
+    fn test_sleep_unless_stopped_returns_early() {
+        let stop = StopFlag::default();
+        stop.request();
+
+        let start = Instant::now();
+        assert!(sleep_unless_stopped(Duration::from_secs(60), &stop));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_sleep_unless_stopped_full_duration() {
+        let stop = StopFlag::default();
+        assert!(!sleep_unless_stopped(Duration::from_millis(10), &stop));
+    }
+}
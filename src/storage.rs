@@ -1,69 +1,887 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
 use directories::ProjectDirs;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::data::Store;
+use crate::data::{Label, Store};
+use crate::settings::{Layout, Settings};
 
 pub struct Storage {
     path: PathBuf,
+    ignore_checksum: bool,
+    dry_run: bool,
+    verbose: bool,
+    /// Leave labels without sessions out of `load_view`.
+    compact_empty: bool,
+    /// Fail to load files with keys the stored structs don't have.
+    strict_load: bool,
+    /// Set once `save` has written the data file.
+    saved: Cell<bool>,
+    /// The in-memory store of a running batch, if any.
+    batch: RefCell<Option<Batch>>,
 }
 
-impl Storage {
-    pub fn new() -> Result<Self> {
-        let project_dirs = ProjectDirs::from("", "", "claude-sessions")
-            .context("Could not determine config directory")?;
+/// A store loaded once by `begin_batch`: `load`, `save` and `update` work on
+/// it until `commit_batch` writes it back. The data file stays locked the
+/// whole time.
+struct Batch {
+    store: Store,
+    dirty: bool,
+    _lock: LockGuard,
+}
+
+/// Where the config directory may live, in order of preference: the
+/// platform config directory, then `.claude-sessions` in the current
+/// directory. The shared temp directory is never used, since anyone could
+/// read or plant data there.
+fn config_dir_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    match ProjectDirs::from("", "", "claude-sessions") {
+        Some(dirs) => candidates.push(dirs.config_dir().to_path_buf()),
+        None => eprintln!(
+            "{} could not determine the platform config directory",
+            "warning:".yellow()
+        ),
+    }
+    if let Ok(cwd) = env::current_dir() {
+        candidates.push(cwd.join(".claude-sessions"));
+    }
+    candidates
+}
+
+/// The first of `candidates` that `create` succeeds on, along with the
+/// candidates that failed before it. Fails, naming every attempted path, when
+/// none can be created.
+fn resolve_config_dir<F>(
+    candidates: &[PathBuf],
+    mut create: F,
+) -> Result<(PathBuf, Vec<(PathBuf, io::Error)>)>
+where
+    F: FnMut(&Path) -> io::Result<()>,
+{
+    let mut failures = Vec::new();
+    for dir in candidates {
+        match create(dir) {
+            Ok(()) => return Ok((dir.clone(), failures)),
+            Err(err) => failures.push((dir.clone(), err)),
+        }
+    }
+
+    let attempts: Vec<String> = failures
+        .iter()
+        .map(|(dir, err)| format!("  {}: {}", dir.display(), err))
+        .collect();
+    if attempts.is_empty() {
+        bail!("Could not determine a config directory");
+    }
+    bail!(
+        "Could not create a config directory; tried:\n{}\nMake one of these writable, or run from a directory where .claude-sessions can be created",
+        attempts.join("\n")
+    )
+}
+
+/// The profile whose data file is the original `data.json`.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// The data file name for `profile`: `data.json` for the default profile and
+/// `<name>.json` for any other.
+pub fn profile_file_name(profile: &str) -> Result<String> {
+    if profile == DEFAULT_PROFILE {
+        return Ok("data.json".to_string());
+    }
+    if profile.is_empty()
+        || !profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!(
+            "Invalid profile name '{}': use letters, digits, '-' and '_'",
+            profile
+        );
+    }
+    if profile == "data" {
+        bail!("'data' is reserved for the default profile");
+    }
+    Ok(format!("{}.json", profile))
+}
+
+/// The profiles that have a data file in `dir`, sorted by name.
+pub fn list_profiles(dir: &Path) -> Result<Vec<String>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Could not read directory: {:?}", dir)),
+    };
+
+    let mut profiles = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(stem) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_suffix(".json"))
+        else {
+            continue;
+        };
+        let profile = if stem == "data" {
+            DEFAULT_PROFILE
+        } else {
+            stem
+        };
+        if profile_file_name(profile).is_ok() {
+            profiles.push(profile.to_string());
+        }
+    }
+    profiles.sort();
+    Ok(profiles)
+}
+
+/// Errors `load` reports with a dedicated variant so callers can react to them.
+#[derive(Debug)]
+pub enum StorageError {
+    /// The data file exists but is not valid JSON for a `Store`.
+    Corrupt {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+impl StorageError {
+    fn corrupt(path: &Path, source: serde_json::Error) -> Self {
+        StorageError::Corrupt {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Corrupt { path, source } => {
+                write!(f, "Could not parse data file {:?}: {}", path, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Corrupt { source, .. } => Some(source),
+        }
+    }
+}
+
+// Mirrors of `Store`, `Label`, `Session` and `Settings` that only check key
+// names, for `set_strict_load`. New fields on those structs must be added here.
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(dead_code)]
+struct StrictStore {
+    #[serde(default)]
+    labels: HashMap<String, StrictLabel>,
+    #[serde(default)]
+    settings: StrictSettings,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(dead_code)]
+struct StrictLabel {
+    #[serde(default)]
+    description: IgnoredAny,
+    #[serde(default)]
+    sessions: Vec<StrictSession>,
+    #[serde(default)]
+    color: IgnoredAny,
+    #[serde(default)]
+    description_history: IgnoredAny,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(dead_code)]
+struct StrictSession {
+    #[serde(default)]
+    session_id: IgnoredAny,
+    #[serde(default)]
+    path: IgnoredAny,
+    #[serde(default)]
+    description: IgnoredAny,
+    #[serde(default)]
+    created_at: IgnoredAny,
+    #[serde(default)]
+    meta: IgnoredAny,
+    #[serde(default)]
+    resume_count: IgnoredAny,
+    #[serde(default)]
+    last_resumed_at: IgnoredAny,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[allow(dead_code)]
+struct StrictSettings {
+    #[serde(default)]
+    checksum: IgnoredAny,
+    #[serde(default)]
+    layout: IgnoredAny,
+    #[serde(default)]
+    sort_sessions_on_save: IgnoredAny,
+    #[serde(default)]
+    time_zone: IgnoredAny,
+    #[serde(default)]
+    allowed_roots: IgnoredAny,
+    #[serde(default)]
+    aliases: IgnoredAny,
+}
+
+/// Only the label keys of the data file; every label body is skipped.
+#[derive(Deserialize)]
+struct LabelNames {
+    #[serde(default)]
+    labels: HashMap<String, IgnoredAny>,
+    #[serde(default)]
+    settings: Settings,
+}
+
+/// File name for a label in the flat layout. Lowercase ASCII letters,
+/// digits, `-` and `_` are kept; every other byte becomes `%XX`, so names
+/// containing `/` or `..` can't escape the labels directory, and names that
+/// differ only in case don't share a file on case-insensitive filesystems.
+fn encode_label_file_name(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len() + 5);
+    for byte in name.bytes() {
+        if byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'-' || byte == b'_' {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded.push_str(".json");
+    encoded
+}
+
+/// The label name for a flat-layout file name, or `None` if the file wasn't
+/// written by `encode_label_file_name`.
+fn decode_label_file_name(file_name: &str) -> Option<String> {
+    let encoded = file_name.strip_suffix(".json")?;
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    let name = String::from_utf8(bytes).ok()?;
+    (encode_label_file_name(&name) == file_name).then_some(name)
+}
+
+/// The flat-layout label files in `dir`, as `(label name, file path)` pairs.
+/// A missing directory has none.
+fn label_files_in(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("Could not read labels directory: {:?}", dir))?;
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Could not read labels directory: {:?}", dir))?;
+        let file_name = entry.file_name();
+        if let Some(name) = file_name.to_str().and_then(decode_label_file_name) {
+            files.push((name, entry.path()));
+        }
+    }
+    Ok(files)
+}
+
+/// A short summary of a store's size, e.g. `3 labels, 12 sessions`.
+fn describe_store(store: &Store) -> String {
+    let labels = store.labels.len();
+    let sessions: usize = store.labels.values().map(|l| l.sessions.len()).sum();
+    format!(
+        "{} label{}, {} session{}",
+        labels,
+        if labels == 1 { "" } else { "s" },
+        sessions,
+        if sessions == 1 { "" } else { "s" }
+    )
+}
 
-        let config_dir = project_dirs.config_dir();
-        fs::create_dir_all(config_dir)
-            .with_context(|| format!("Could not create config directory: {:?}", config_dir))?;
+/// How long `update` waits for another process to release the lock.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_POLL: Duration = Duration::from_millis(10);
+/// How often `update` re-runs after the data file changed underneath it.
+const UPDATE_ATTEMPTS: usize = 5;
 
-        let path = config_dir.join("data.json");
+/// An exclusive lock on the data file, held for as long as the guard lives.
+struct LockGuard {
+    path: PathBuf,
+}
+
+impl LockGuard {
+    fn acquire(path: PathBuf) -> Result<Self> {
+        let started = Instant::now();
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(Self { path }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if started.elapsed() >= LOCK_TIMEOUT {
+                        bail!(
+                            "Timed out waiting for lock {:?}. If no other claude-sessions \
+                             process is running, delete the lock file and retry",
+                            path
+                        );
+                    }
+                    thread::sleep(LOCK_POLL);
+                }
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Could not create lock file: {:?}", path))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn sha256_hex(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// The checksum sidecar of a data or label file: `<file>.sha256`.
+fn checksum_path_for(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".sha256");
+    PathBuf::from(name)
+}
+
+/// Write the checksum of `content` to `checksum_path` when `enabled`, or
+/// remove a sidecar left from when it was.
+fn write_checksum(checksum_path: &Path, content: &str, enabled: bool) -> Result<()> {
+    if enabled {
+        let checksum = sha256_hex(content);
+        if fs::read_to_string(checksum_path).ok().as_deref() != Some(checksum.as_str()) {
+            fs::write(checksum_path, checksum)
+                .with_context(|| format!("Could not write checksum file: {:?}", checksum_path))?;
+        }
+    } else if checksum_path.exists() {
+        fs::remove_file(checksum_path)
+            .with_context(|| format!("Could not remove checksum file: {:?}", checksum_path))?;
+    }
+    Ok(())
+}
+
+impl Storage {
+    /// Storage for `profile`'s data file in the config directory; `None` means
+    /// the default profile.
+    pub fn new(profile: Option<&str>) -> Result<Self> {
+        let (config_dir, failures) =
+            resolve_config_dir(&config_dir_candidates(), |dir| fs::create_dir_all(dir))?;
+        for (dir, err) in &failures {
+            eprintln!(
+                "{} could not create config directory {:?} ({})",
+                "warning:".yellow(),
+                dir,
+                err
+            );
+        }
+        if !failures.is_empty() {
+            eprintln!(
+                "{} storing data in {:?} instead",
+                "warning:".yellow(),
+                config_dir
+            );
+        }
+
+        let path = config_dir.join(profile_file_name(profile.unwrap_or(DEFAULT_PROFILE))?);
 
-        Ok(Self { path })
+        Ok(Self::with_path(path))
     }
 
-    #[cfg(test)]
     pub fn with_path(path: PathBuf) -> Self {
-        Self { path }
+        Self {
+            path,
+            ignore_checksum: false,
+            dry_run: false,
+            verbose: false,
+            compact_empty: false,
+            strict_load: false,
+            saved: Cell::new(false),
+            batch: RefCell::new(None),
+        }
+    }
+
+    /// Load the data file even when its checksum sidecar doesn't match.
+    pub fn set_ignore_checksum(&mut self, ignore: bool) {
+        self.ignore_checksum = ignore;
+    }
+
+    /// Turn `save` into a no-op that only reports what it would have written.
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Hide labels that have no sessions from read-only views.
+    pub fn set_compact_empty(&mut self, compact: bool) {
+        self.compact_empty = compact;
+    }
+
+    /// Treat unknown keys (e.g. a misspelled `descriptn`) as a corrupt file
+    /// instead of ignoring them. Loading is lenient by default so files
+    /// written by newer versions still load.
+    pub fn set_strict_load(&mut self, strict: bool) {
+        self.strict_load = strict;
+    }
+
+    pub fn is_strict_load(&self) -> bool {
+        self.strict_load
+    }
+
+    /// Lock the data file and load it once; until `commit_batch`, every load,
+    /// save and update works on that copy in memory.
+    pub fn begin_batch(&self) -> Result<()> {
+        if self.batch.borrow().is_some() {
+            bail!("A batch is already running");
+        }
+        let lock = LockGuard::acquire(self.lock_path())?;
+        let store = self.load_from_disk()?;
+        *self.batch.borrow_mut() = Some(Batch {
+            store,
+            dirty: false,
+            _lock: lock,
+        });
+        Ok(())
+    }
+
+    /// End the batch, writing its store if anything saved it, then unlock.
+    pub fn commit_batch(&self) -> Result<()> {
+        let batch = self
+            .batch
+            .borrow_mut()
+            .take()
+            .context("No batch is running")?;
+        if batch.dirty {
+            self.save(&batch.store)?;
+        }
+        Ok(())
+    }
+
+    /// End the batch without writing anything.
+    pub fn abort_batch(&self) {
+        self.batch.borrow_mut().take();
+    }
+
+    /// Whether this storage has written the data file (dry runs don't count).
+    pub fn was_saved(&self) -> bool {
+        self.saved.get()
+    }
+
+    /// Log storage operations to stderr.
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    fn log(&self, message: impl FnOnce() -> String) {
+        if self.verbose {
+            eprintln!("[storage] {}", message());
+        }
     }
 
     pub fn path(&self) -> &PathBuf {
         &self.path
     }
 
-    pub fn load(&self) -> Result<Store> {
+    /// Directory holding one file per label when the flat layout is enabled.
+    pub fn labels_dir(&self) -> PathBuf {
+        self.path.with_file_name(self.sidecar_name("labels"))
+    }
+
+    /// Log of every resume, kept next to the data file.
+    pub fn history_path(&self) -> PathBuf {
+        self.path.with_file_name(self.sidecar_name("resume.log"))
+    }
+
+    /// `name` for the default `data.json`, `<stem>.<name>` for any other data
+    /// file, so stores sharing a directory (profiles) don't share side files.
+    fn sidecar_name(&self, name: &str) -> String {
+        match self.path.file_stem().and_then(|s| s.to_str()) {
+            None | Some("data") => name.to_string(),
+            Some(stem) => format!("{}.{}", stem, name),
+        }
+    }
+
+    pub fn lock_path(&self) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(".lock");
+        PathBuf::from(name)
+    }
+
+    pub fn checksum_path(&self) -> PathBuf {
+        checksum_path_for(&self.path)
+    }
+
+    /// Check `content`, read from `path`, against its checksum sidecar if it
+    /// has one.
+    fn verify_checksum(&self, path: &Path, content: &str) -> Result<()> {
+        let checksum_path = checksum_path_for(path);
+        if !checksum_path.exists() {
+            return Ok(());
+        }
+
+        let expected = fs::read_to_string(&checksum_path)
+            .with_context(|| format!("Could not read checksum file: {:?}", checksum_path))?;
+        if expected.trim() == sha256_hex(content) {
+            return Ok(());
+        }
+
+        if self.ignore_checksum {
+            eprintln!(
+                "{} data file {:?} does not match its checksum {:?}; continuing because of --force",
+                "warning:".yellow(),
+                path,
+                checksum_path
+            );
+            return Ok(());
+        }
+
+        bail!(
+            "Data file {:?} does not match its checksum {:?}. It may be corrupted; \
+             inspect it and re-run with --force to load it anyway",
+            path,
+            checksum_path
+        )
+    }
+
+    /// Read the data file, returning `None` when it is missing or empty.
+    fn read_content(&self) -> Result<Option<String>> {
+        self.log(|| format!("data file: {}", self.path.display()));
         if !self.path.exists() {
-            return Ok(Store::new());
+            self.log(|| "data file does not exist yet".to_string());
+            return Ok(None);
         }
 
         let content = fs::read_to_string(&self.path)
             .with_context(|| format!("Could not read data file: {:?}", self.path))?;
+        self.log(|| format!("read {} bytes", content.len()));
+
+        self.verify_checksum(&self.path, &content)?;
 
         if content.trim().is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(content))
+    }
+
+    pub fn load(&self) -> Result<Store> {
+        if let Some(batch) = self.batch.borrow().as_ref() {
+            return Ok(batch.store.clone());
+        }
+        self.load_from_disk()
+    }
+
+    fn load_from_disk(&self) -> Result<Store> {
+        let Some(content) = self.read_content()? else {
             return Ok(Store::new());
+        };
+
+        if self.strict_load {
+            check_known_fields::<StrictStore>(&content, &self.path)?;
+        }
+        let mut store: Store =
+            serde_json::from_str(&content).map_err(|e| StorageError::corrupt(&self.path, e))?;
+        if store.settings.layout == Layout::Flat {
+            store.labels = self.load_label_files()?;
+        }
+        self.log(|| format!("loaded {}", describe_store(&store)));
+        Ok(store)
+    }
+
+    /// Label files in the flat layout, as `(label name, file path)` pairs.
+    fn label_files(&self) -> Result<Vec<(String, PathBuf)>> {
+        label_files_in(&self.labels_dir())
+    }
+
+    fn load_label_files(&self) -> Result<HashMap<String, Label>> {
+        self.load_label_files_from(&self.labels_dir())
+    }
+
+    /// Read a directory of flat-layout label files (e.g. another user's
+    /// `labels` directory) as a store without settings. Other files are
+    /// ignored; a directory without any label file is an error.
+    pub fn load_label_dir(&self, dir: &Path) -> Result<Store> {
+        let labels = self.load_label_files_from(dir)?;
+        if labels.is_empty() {
+            bail!("No label files (<label>.json) found in {:?}", dir);
+        }
+        Ok(Store {
+            labels,
+            ..Store::new()
+        })
+    }
+
+    fn load_label_files_from(&self, dir: &Path) -> Result<HashMap<String, Label>> {
+        let mut labels = HashMap::new();
+        for (name, path) in label_files_in(dir)? {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Could not read label file: {:?}", path))?;
+            self.verify_checksum(&path, &content)?;
+            if self.strict_load {
+                check_known_fields::<StrictLabel>(&content, &path)?;
+            }
+            let label: Label =
+                serde_json::from_str(&content).map_err(|e| StorageError::corrupt(&path, e))?;
+            labels.insert(name, label);
+        }
+        self.log(|| format!("read {} label files from {}", labels.len(), dir.display()));
+        Ok(labels)
+    }
+
+    /// Write every label whose file content changed and remove the files of
+    /// labels that no longer exist. Each label file gets a checksum sidecar
+    /// when `checksum` is set.
+    fn save_label_files(&self, labels: &HashMap<String, Label>, checksum: bool) -> Result<()> {
+        let dir = self.labels_dir();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Could not create labels directory: {:?}", dir))?;
+
+        for (name, label) in labels {
+            let path = dir.join(encode_label_file_name(name));
+            let content =
+                serde_json::to_string_pretty(label).context("Could not serialize label")?;
+            if fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+                fs::write(&path, &content)
+                    .with_context(|| format!("Could not write label file: {:?}", path))?;
+                self.log(|| format!("wrote label '{}' to {}", name, path.display()));
+            }
+            write_checksum(&checksum_path_for(&path), &content, checksum)?;
+        }
+
+        for (name, path) in self.label_files()? {
+            if labels.contains_key(&name) {
+                continue;
+            }
+            fs::remove_file(&path)
+                .with_context(|| format!("Could not remove label file: {:?}", path))?;
+            write_checksum(&checksum_path_for(&path), "", false)?;
+            self.log(|| format!("removed label file {}", path.display()));
+        }
+
+        Ok(())
+    }
+
+    /// Load the store for display. Like `load`, except that with
+    /// `set_compact_empty` labels without sessions are left out, so the
+    /// result must never be saved.
+    pub fn load_view(&self) -> Result<Store> {
+        let mut store = self.load()?;
+        if self.compact_empty {
+            store.labels.retain(|_, label| !label.sessions.is_empty());
+        }
+        Ok(store)
+    }
+
+    /// Sorted label names, read without building the sessions of each label.
+    /// Intended for read-only listings; mutating commands should use `load`.
+    pub fn load_label_names(&self) -> Result<Vec<String>> {
+        if self.compact_empty || self.strict_load || self.batch.borrow().is_some() {
+            // Emptiness and strict checks need the sessions, and a batch's
+            // labels only exist in memory, so none can take the fast path
+            let mut names: Vec<String> = self.load_view()?.labels.into_keys().collect();
+            names.sort();
+            return Ok(names);
+        }
+
+        let Some(content) = self.read_content()? else {
+            return Ok(Vec::new());
+        };
+
+        let names: LabelNames =
+            serde_json::from_str(&content).map_err(|e| StorageError::corrupt(&self.path, e))?;
+
+        let mut names: Vec<String> = if names.settings.layout == Layout::Flat {
+            self.label_files()?
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect()
+        } else {
+            names.labels.into_keys().collect()
+        };
+        names.sort();
+        Ok(names)
+    }
+
+    /// Load, apply `mutate` and save while holding the data file lock. If the
+    /// file is changed by a writer that bypassed the lock between the load and
+    /// the save, the whole read-modify-write is retried, so `mutate` may run
+    /// more than once. An error from `mutate` aborts without saving.
+    pub fn update<T, F>(&self, mut mutate: F) -> Result<T>
+    where
+        F: FnMut(&mut Store) -> Result<T>,
+    {
+        if self.batch.borrow().is_some() {
+            // Already locked, and nothing else can change the batch's store
+            let mut store = self.load()?;
+            let result = mutate(&mut store)?;
+            self.save(&store)?;
+            return Ok(result);
+        }
+
+        let _lock = LockGuard::acquire(self.lock_path())?;
+
+        for attempt in 1..=UPDATE_ATTEMPTS {
+            let before = self.fingerprint();
+            let mut store = self.load()?;
+            let result = mutate(&mut store)?;
+
+            if self.fingerprint() != before {
+                self.log(|| format!("data file changed during update (attempt {})", attempt));
+                continue;
+            }
+
+            self.save(&store)?;
+            return Ok(result);
         }
 
-        serde_json::from_str(&content)
-            .with_context(|| format!("Could not parse data file: {:?}", self.path))
+        bail!(
+            "Data file {:?} kept changing during the update; giving up after {} attempts",
+            self.path,
+            UPDATE_ATTEMPTS
+        )
+    }
+
+    /// The contents of the data file and of every flat-layout label file,
+    /// so `update` can tell whether another writer got in between.
+    fn fingerprint(&self) -> Vec<(PathBuf, Option<Vec<u8>>)> {
+        let mut files = vec![self.path.clone()];
+        if let Ok(label_files) = self.label_files() {
+            files.extend(label_files.into_iter().map(|(_, path)| path));
+        }
+        files.sort();
+        files
+            .into_iter()
+            .map(|path| {
+                let content = fs::read(&path).ok();
+                (path, content)
+            })
+            .collect()
     }
 
     pub fn save(&self, store: &Store) -> Result<()> {
-        let content = serde_json::to_string_pretty(store).context("Could not serialize store")?;
+        if let Some(batch) = self.batch.borrow_mut().as_mut() {
+            batch.store = store.clone();
+            batch.dirty = true;
+            return Ok(());
+        }
 
-        fs::write(&self.path, content)
-            .with_context(|| format!("Could not write data file: {:?}", self.path))?;
+        if self.dry_run {
+            eprintln!(
+                "dry run: would write {} label{} to {:?}",
+                store.labels.len(),
+                if store.labels.len() == 1 { "" } else { "s" },
+                self.path
+            );
+            return Ok(());
+        }
+
+        let sorted;
+        let store = if store.settings.sort_sessions_on_save {
+            sorted = with_sorted_sessions(store);
+            &sorted
+        } else {
+            store
+        };
+
+        let content = match store.settings.layout {
+            Layout::Single => serde_json::to_string_pretty(store),
+            Layout::Flat => {
+                self.save_label_files(&store.labels, store.settings.checksum)?;
+                serde_json::to_string_pretty(&Store {
+                    labels: HashMap::new(),
+                    settings: store.settings.clone(),
+                })
+            }
+        }
+        .context("Could not serialize store")?;
 
+        fs::write(&self.path, &content)
+            .with_context(|| format!("Could not write data file: {:?}", self.path))?;
+        self.log(|| {
+            format!(
+                "wrote {} bytes ({}) to {}",
+                content.len(),
+                describe_store(store),
+                self.path.display()
+            )
+        });
+
+        write_checksum(&self.checksum_path(), &content, store.settings.checksum)?;
+
+        self.saved.set(true);
         Ok(())
     }
 }
 
+/// Parse `content` as `T` (one of the strict mirrors) only to reject keys it
+/// doesn't know, reported like any other parse failure of `path`.
+fn check_known_fields<T: DeserializeOwned>(content: &str, path: &Path) -> Result<()> {
+    serde_json::from_str::<T>(content)
+        .map(drop)
+        .map_err(|e| StorageError::corrupt(path, e).into())
+}
+
+/// A copy of `store` with every label's sessions ordered by `created_at`.
+/// The sort is stable, so sessions created at the same instant keep their order.
+fn with_sorted_sessions(store: &Store) -> Store {
+    let mut sorted = store.clone();
+    for label in sorted.labels.values_mut() {
+        label.sessions.sort_by_key(|s| s.created_at);
+    }
+    sorted
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::data::{Label, Session};
     use chrono::Utc;
-    use std::env;
 
     fn temp_path(name: &str) -> PathBuf {
         env::temp_dir().join(format!(
@@ -79,11 +897,13 @@ mod tests {
             path: "/test/path".to_string(),
             description: None,
             created_at: Utc::now(),
+            ..Default::default()
         }
     }
 
     fn cleanup(path: &PathBuf) {
         let _ = fs::remove_file(path);
+        let _ = fs::remove_file(Storage::with_path(path.clone()).checksum_path());
     }
 
     // ==================== Load Tests ====================
@@ -153,6 +973,33 @@ mod tests {
         cleanup(&path);
     }
 
+    #[test]
+    fn test_load_corrupt_json_returns_typed_error() {
+        let path = temp_path("corrupt-typed");
+        fs::write(&path, "{ not valid json }").unwrap();
+
+        let storage = Storage::with_path(path.clone());
+        let err = storage.load().unwrap_err();
+
+        match err.downcast_ref::<StorageError>() {
+            Some(StorageError::Corrupt { path: corrupt, .. }) => assert_eq!(corrupt, &path),
+            None => panic!("expected a corrupt data error, got: {:#}", err),
+        }
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_load_label_names_corrupt_json_returns_typed_error() {
+        let path = temp_path("names-corrupt-typed");
+        fs::write(&path, r#"{"labels":["#).unwrap();
+
+        let storage = Storage::with_path(path.clone());
+        let err = storage.load_label_names().unwrap_err();
+
+        assert!(err.downcast_ref::<StorageError>().is_some());
+        cleanup(&path);
+    }
+
     #[test]
     fn test_load_partial_json_returns_error() {
         let path = temp_path("partial");
@@ -180,6 +1027,23 @@ mod tests {
         cleanup(&path);
     }
 
+    #[test]
+    fn test_was_saved_tracks_writes() {
+        let path = temp_path("was-saved");
+        let mut storage = Storage::with_path(path.clone());
+        storage.set_dry_run(true);
+
+        storage.load().unwrap();
+        assert!(!storage.was_saved());
+        storage.save(&Store::new()).unwrap();
+        assert!(!storage.was_saved());
+
+        storage.set_dry_run(false);
+        storage.save(&Store::new()).unwrap();
+        assert!(storage.was_saved());
+        cleanup(&path);
+    }
+
     #[test]
     fn test_save_writes_valid_json() {
         let path = temp_path("write-json");
@@ -221,50 +1085,312 @@ mod tests {
         cleanup(&path);
     }
 
-    // ==================== Roundtrip Tests ====================
-
     #[test]
-    fn test_save_load_roundtrip_empty_store() {
-        let path = temp_path("roundtrip-empty");
+    fn test_save_dry_run_does_not_write() {
+        let path = temp_path("dry-run");
+        cleanup(&path);
 
-        let storage = Storage::with_path(path.clone());
-        let store = Store::new();
+        let mut storage = Storage::with_path(path.clone());
+        storage.set_dry_run(true);
+        let mut store = Store::new();
+        store.settings.checksum = true;
+        storage.save(&store).unwrap();
+
+        assert!(!path.exists());
+        assert!(!storage.checksum_path().exists());
+    }
+
+    #[test]
+    fn test_save_dry_run_leaves_existing_file() {
+        let path = temp_path("dry-run-existing");
+        let mut storage = Storage::with_path(path.clone());
 
+        let mut store = Store::new();
+        store
+            .labels
+            .insert("original".to_string(), Label::new(None));
         storage.save(&store).unwrap();
-        let loaded = storage.load().unwrap();
 
-        assert!(loaded.labels.is_empty());
+        storage.set_dry_run(true);
+        storage.save(&Store::new()).unwrap();
+
+        assert!(storage.load().unwrap().labels.contains_key("original"));
         cleanup(&path);
     }
 
     #[test]
-    fn test_save_load_roundtrip_with_data() {
-        let path = temp_path("roundtrip-data");
-
+    fn test_save_sorts_sessions_when_enabled() {
+        let path = temp_path("sort-sessions");
         let storage = Storage::with_path(path.clone());
 
-        // Create store with multiple labels and sessions
+        let now = Utc::now();
+        let mut label = Label::new(None);
+        for (id, days_ago) in [("middle", 5), ("newest", 1), ("oldest", 10)] {
+            let mut session = create_test_session(id);
+            session.created_at = now - chrono::Duration::days(days_ago);
+            label.add_session(session);
+        }
         let mut store = Store::new();
+        store.labels.insert("label".to_string(), label);
 
-        let mut label1 = Label::new(Some("First label".to_string()));
-        label1.add_session(create_test_session("sess1"));
-        label1.add_session(create_test_session("sess2"));
-
-        let mut label2 = Label::new(None);
-        label2.add_session(create_test_session("sess3"));
-
-        store.labels.insert("label-1".to_string(), label1);
-        store.labels.insert("label-2".to_string(), label2);
+        storage.save(&store).unwrap();
+        let ids = |store: &Store| -> Vec<String> {
+            store.labels["label"]
+                .sessions
+                .iter()
+                .map(|s| s.session_id.clone())
+                .collect()
+        };
+        assert_eq!(
+            ids(&storage.load().unwrap()),
+            ["middle", "newest", "oldest"]
+        );
 
-        // Save and load
+        store.settings.sort_sessions_on_save = true;
         storage.save(&store).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
         let loaded = storage.load().unwrap();
+        assert_eq!(ids(&loaded), ["oldest", "middle", "newest"]);
 
-        // Verify
-        assert_eq!(loaded.labels.len(), 2);
+        // Saving the reloaded store again writes identical bytes
+        storage.save(&loaded).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        cleanup(&path);
+    }
 
-        let loaded_label1 = loaded.get_label("label-1").unwrap();
-        assert_eq!(loaded_label1.description, Some("First label".to_string()));
+    // ==================== Strict Load Tests ====================
+
+    const MISSPELLED_FIELD: &str = r#"{"labels":{"work":{"sessions":[
+        {"session_id":"s1","path":"/p","created_at":"2024-01-01T00:00:00Z","descriptn":"typo"}
+    ]}}}"#;
+
+    #[test]
+    fn test_unknown_field_loads_by_default() {
+        let path = temp_path("strict-default");
+        fs::write(&path, MISSPELLED_FIELD).unwrap();
+
+        let store = Storage::with_path(path.clone()).load().unwrap();
+        assert_eq!(store.get_label("work").unwrap().sessions.len(), 1);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_strict_load_rejects_unknown_fields() {
+        let path = temp_path("strict-reject");
+        let mut storage = Storage::with_path(path.clone());
+        storage.set_strict_load(true);
+
+        fs::write(&path, MISSPELLED_FIELD).unwrap();
+        let err = storage.load().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StorageError>(),
+            Some(StorageError::Corrupt { .. })
+        ));
+        assert!(format!("{:?}", err).contains("descriptn"));
+        assert!(storage.load_label_names().is_err());
+
+        for json in [
+            r#"{"labels":{"work":{"sessions":[],"colour":"red"}}}"#,
+            r#"{"labels":{},"settings":{"chekcsum":true}}"#,
+            r#"{"labls":{}}"#,
+        ] {
+            fs::write(&path, json).unwrap();
+            assert!(storage.load().is_err(), "{}", json);
+        }
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_strict_load_accepts_every_stored_field() {
+        let path = temp_path("strict-full");
+        let mut session = create_test_session("s1");
+        session.description = Some("d".to_string());
+        session.meta.insert("k".to_string(), "v".to_string());
+        session.resume_count = 2;
+        session.last_resumed_at = Some(Utc::now());
+        let mut label = Label::new(Some("d".to_string()));
+        label.color = Some("red".to_string());
+        label.set_description(Some("d2".to_string()), Utc::now());
+        label.add_session(session);
+        let mut store = Store::new();
+        store.labels.insert("work".to_string(), label);
+        store.settings.checksum = true;
+        store.settings.sort_sessions_on_save = true;
+        store.settings.time_zone = Some("utc".to_string());
+        store.settings.allowed_roots = vec!["/p".to_string()];
+        store
+            .settings
+            .aliases
+            .insert("w".to_string(), "work".to_string());
+
+        // Written directly so every key (including layout) is present
+        let mut json = serde_json::to_value(&store).unwrap();
+        json["settings"]["layout"] = "single".into();
+        fs::write(&path, json.to_string()).unwrap();
+
+        let mut storage = Storage::with_path(path.clone());
+        storage.set_strict_load(true);
+        storage.set_ignore_checksum(true);
+        assert_eq!(storage.load().unwrap().labels.len(), 1);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_strict_load_checks_flat_label_files() {
+        let path = temp_path("strict-flat");
+        let mut storage = Storage::with_path(path.clone());
+        let mut store = Store::new();
+        store.settings.layout = Layout::Flat;
+        store.labels.insert("work".to_string(), Label::new(None));
+        storage.save(&store).unwrap();
+
+        let label_file = storage.labels_dir().join(encode_label_file_name("work"));
+        fs::write(&label_file, r#"{"sessions":[],"extra":1}"#).unwrap();
+        assert!(storage.load().is_ok());
+        storage.set_strict_load(true);
+        assert!(storage.load().is_err());
+
+        cleanup(&path);
+        let _ = fs::remove_dir_all(storage.labels_dir());
+    }
+
+    // ==================== Compact Empty Tests ====================
+
+    fn store_with_empty_label() -> Store {
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("s1"));
+        store.labels.insert("busy".to_string(), label);
+        store.labels.insert("empty".to_string(), Label::new(None));
+        store
+    }
+
+    #[test]
+    fn test_load_view_compact_empty_hides_empty_labels() {
+        let path = temp_path("compact-empty");
+        let mut storage = Storage::with_path(path.clone());
+        storage.save(&store_with_empty_label()).unwrap();
+        storage.set_compact_empty(true);
+
+        let view = storage.load_view().unwrap();
+        assert!(view.labels.contains_key("busy"));
+        assert!(!view.labels.contains_key("empty"));
+        assert_eq!(storage.load_label_names().unwrap(), vec!["busy"]);
+
+        // The data itself is untouched
+        assert!(storage.load().unwrap().labels.contains_key("empty"));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_load_view_without_compact_empty_keeps_everything() {
+        let path = temp_path("compact-empty-off");
+        let storage = Storage::with_path(path.clone());
+        storage.save(&store_with_empty_label()).unwrap();
+
+        assert_eq!(storage.load_view().unwrap().labels.len(), 2);
+        assert_eq!(storage.load_label_names().unwrap(), vec!["busy", "empty"]);
+        cleanup(&path);
+    }
+
+    // ==================== Batch Tests ====================
+
+    #[test]
+    fn test_batch_saves_once_on_commit() {
+        let path = temp_path("batch-commit");
+        let storage = Storage::with_path(path.clone());
+        storage.save(&Store::new()).unwrap();
+        let written = fs::read(&path).unwrap();
+
+        storage.begin_batch().unwrap();
+        storage
+            .update(|store| {
+                store.get_or_create_label("one");
+                Ok(())
+            })
+            .unwrap();
+        let mut store = storage.load().unwrap();
+        store.get_or_create_label("two");
+        storage.save(&store).unwrap();
+
+        // Nothing reaches the file until the batch is committed
+        assert_eq!(fs::read(&path).unwrap(), written);
+        assert_eq!(storage.load_label_names().unwrap(), vec!["one", "two"]);
+        assert!(storage.lock_path().exists());
+
+        storage.commit_batch().unwrap();
+        assert!(!storage.lock_path().exists());
+        let loaded = storage.load().unwrap();
+        assert!(loaded.labels.contains_key("one"));
+        assert!(loaded.labels.contains_key("two"));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_batch_abort_discards_changes() {
+        let path = temp_path("batch-abort");
+        let storage = Storage::with_path(path.clone());
+
+        storage.begin_batch().unwrap();
+        assert!(storage.begin_batch().is_err());
+        storage
+            .update(|store| {
+                store.get_or_create_label("one");
+                Ok(())
+            })
+            .unwrap();
+        storage.abort_batch();
+
+        assert!(storage.load().unwrap().labels.is_empty());
+        assert!(!storage.lock_path().exists());
+        assert!(storage.commit_batch().is_err());
+        cleanup(&path);
+    }
+
+    // ==================== Roundtrip Tests ====================
+
+    #[test]
+    fn test_save_load_roundtrip_empty_store() {
+        let path = temp_path("roundtrip-empty");
+
+        let storage = Storage::with_path(path.clone());
+        let store = Store::new();
+
+        storage.save(&store).unwrap();
+        let loaded = storage.load().unwrap();
+
+        assert!(loaded.labels.is_empty());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_save_load_roundtrip_with_data() {
+        let path = temp_path("roundtrip-data");
+
+        let storage = Storage::with_path(path.clone());
+
+        // Create store with multiple labels and sessions
+        let mut store = Store::new();
+
+        let mut label1 = Label::new(Some("First label".to_string()));
+        label1.add_session(create_test_session("sess1"));
+        label1.add_session(create_test_session("sess2"));
+
+        let mut label2 = Label::new(None);
+        label2.add_session(create_test_session("sess3"));
+
+        store.labels.insert("label-1".to_string(), label1);
+        store.labels.insert("label-2".to_string(), label2);
+
+        // Save and load
+        storage.save(&store).unwrap();
+        let loaded = storage.load().unwrap();
+
+        // Verify
+        assert_eq!(loaded.labels.len(), 2);
+
+        let loaded_label1 = loaded.get_label("label-1").unwrap();
+        assert_eq!(loaded_label1.description, Some("First label".to_string()));
         assert_eq!(loaded_label1.sessions.len(), 2);
 
         let loaded_label2 = loaded.get_label("label-2").unwrap();
@@ -274,6 +1400,600 @@ mod tests {
         cleanup(&path);
     }
 
+    // ==================== Verbose Tests ====================
+
+    #[test]
+    fn test_describe_store_counts() {
+        let mut store = Store::new();
+        assert_eq!(describe_store(&store), "0 labels, 0 sessions");
+
+        let mut label1 = Label::new(None);
+        label1.add_session(create_test_session("s1"));
+        let mut label2 = Label::new(None);
+        label2.add_session(create_test_session("s2"));
+        label2.add_session(create_test_session("s3"));
+        store.labels.insert("one".to_string(), label1);
+        store.labels.insert("two".to_string(), label2);
+        store.labels.insert("empty".to_string(), Label::new(None));
+
+        assert_eq!(describe_store(&store), "3 labels, 3 sessions");
+    }
+
+    #[test]
+    fn test_describe_store_singular() {
+        let mut store = Store::new();
+        let mut label = Label::new(None);
+        label.add_session(create_test_session("s1"));
+        store.labels.insert("one".to_string(), label);
+
+        assert_eq!(describe_store(&store), "1 label, 1 session");
+    }
+
+    #[test]
+    fn test_verbose_load_and_save_succeed() {
+        let path = temp_path("verbose");
+        let mut storage = Storage::with_path(path.clone());
+        storage.set_verbose(true);
+
+        storage.save(&Store::new()).unwrap();
+        assert!(storage.load().is_ok());
+        cleanup(&path);
+    }
+
+    // ==================== Label Name Tests ====================
+
+    #[test]
+    fn test_load_label_names_missing_file_is_empty() {
+        let path = temp_path("names-missing");
+        cleanup(&path);
+
+        let storage = Storage::with_path(path.clone());
+
+        assert!(storage.load_label_names().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_load_label_names_matches_full_load() {
+        let path = temp_path("names-large");
+        let storage = Storage::with_path(path.clone());
+
+        let mut store = Store::new();
+        for i in 0..100 {
+            let mut label = Label::new(Some(format!("Label {}", i)));
+            for j in 0..10 {
+                label.add_session(create_test_session(&format!("sess-{}-{}", i, j)));
+            }
+            store.labels.insert(format!("label-{}", i), label);
+        }
+        store
+            .labels
+            .insert("功能-🚀\nnewline".to_string(), Label::new(None));
+        storage.save(&store).unwrap();
+
+        let streamed = storage.load_label_names().unwrap();
+        let mut parsed: Vec<String> = storage.load().unwrap().labels.into_keys().collect();
+        parsed.sort();
+
+        assert_eq!(streamed.len(), 101);
+        assert_eq!(streamed, parsed);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_load_label_names_corrupt_json_returns_error() {
+        let path = temp_path("names-corrupt");
+        fs::write(&path, "{ not valid json }").unwrap();
+
+        let storage = Storage::with_path(path.clone());
+
+        assert!(storage.load_label_names().is_err());
+        cleanup(&path);
+    }
+
+    // ==================== Checksum Tests ====================
+
+    fn create_checksummed_store() -> Store {
+        let mut store = Store::new();
+        store.settings.checksum = true;
+        store
+            .labels
+            .insert("my-label".to_string(), Label::new(None));
+        store
+    }
+
+    #[test]
+    fn test_checksum_written_when_enabled() {
+        let path = temp_path("checksum-written");
+        let storage = Storage::with_path(path.clone());
+
+        storage.save(&create_checksummed_store()).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        let checksum = fs::read_to_string(storage.checksum_path()).unwrap();
+        assert_eq!(checksum, sha256_hex(&content));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_checksum_not_written_when_disabled() {
+        let path = temp_path("checksum-disabled");
+        let storage = Storage::with_path(path.clone());
+
+        storage.save(&Store::new()).unwrap();
+
+        assert!(!storage.checksum_path().exists());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_checksum_removed_when_disabled() {
+        let path = temp_path("checksum-removed");
+        let storage = Storage::with_path(path.clone());
+
+        let mut store = create_checksummed_store();
+        storage.save(&store).unwrap();
+        store.settings.checksum = false;
+        storage.save(&store).unwrap();
+
+        assert!(!storage.checksum_path().exists());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_load_with_matching_checksum() {
+        let path = temp_path("checksum-match");
+        let storage = Storage::with_path(path.clone());
+
+        storage.save(&create_checksummed_store()).unwrap();
+        let loaded = storage.load().unwrap();
+
+        assert!(loaded.labels.contains_key("my-label"));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_load_with_mismatched_checksum_returns_error() {
+        let path = temp_path("checksum-mismatch");
+        let storage = Storage::with_path(path.clone());
+
+        storage.save(&create_checksummed_store()).unwrap();
+        fs::write(&path, r#"{"labels":{}}"#).unwrap();
+
+        assert!(storage.load().is_err());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_load_with_mismatched_checksum_and_ignore_flag() {
+        let path = temp_path("checksum-ignore");
+        let mut storage = Storage::with_path(path.clone());
+
+        storage.save(&create_checksummed_store()).unwrap();
+        fs::write(&path, r#"{"labels":{}}"#).unwrap();
+        storage.set_ignore_checksum(true);
+
+        let loaded = storage.load().unwrap();
+        assert!(loaded.labels.is_empty());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_load_without_checksum_file_is_tolerated() {
+        let path = temp_path("checksum-missing");
+        let storage = Storage::with_path(path.clone());
+
+        storage.save(&create_checksummed_store()).unwrap();
+        fs::remove_file(storage.checksum_path()).unwrap();
+
+        assert!(storage.load().is_ok());
+        cleanup(&path);
+    }
+
+    // ==================== Flat Layout Tests ====================
+
+    fn create_flat_storage(name: &str) -> (Storage, PathBuf) {
+        let dir = temp_path(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        (Storage::with_path(dir.join("data.json")), dir)
+    }
+
+    fn create_flat_store() -> Store {
+        let mut store = Store::new();
+        store.settings.layout = Layout::Flat;
+
+        let mut nested = Label::new(Some("Nested".to_string()));
+        nested.add_session(create_test_session("sess1"));
+        let mut plain = Label::new(None);
+        plain.add_session(create_test_session("sess2"));
+        plain.add_session(create_test_session("sess3"));
+
+        store.labels.insert("team/web/TICKET-1".to_string(), nested);
+        store.labels.insert("plain".to_string(), plain);
+        store
+            .labels
+            .insert("../escape".to_string(), Label::new(None));
+        store
+    }
+
+    #[test]
+    fn test_label_file_name_encoding() {
+        assert_eq!(encode_label_file_name("plain-name_1"), "plain-name_1.json");
+        assert_eq!(
+            encode_label_file_name("team/web/TICKET-1"),
+            "team%2Fweb%2F%54%49%43%4B%45%54-1.json"
+        );
+        assert_eq!(encode_label_file_name("Work"), "%57ork.json");
+        assert_eq!(encode_label_file_name("../x"), "%2E%2E%2Fx.json");
+        assert_eq!(encode_label_file_name("a b%"), "a%20b%25.json");
+    }
+
+    #[test]
+    fn test_label_file_name_roundtrip() {
+        for name in [
+            "plain",
+            "team/web/TICKET-1",
+            "..",
+            "a%2Fb",
+            "功能-🚀",
+            r"with space\and\backslash",
+            "",
+        ] {
+            let encoded = encode_label_file_name(name);
+            assert!(!encoded.contains('/'));
+            assert!(!encoded.starts_with('.') || name.is_empty());
+            assert_eq!(decode_label_file_name(&encoded).as_deref(), Some(name));
+        }
+    }
+
+    #[test]
+    fn test_flat_layout_keeps_labels_differing_in_case_apart() {
+        let (storage, dir) = create_flat_storage("flat-case");
+        let mut store = Store::new();
+        store.settings.layout = Layout::Flat;
+        store
+            .labels
+            .insert("work".to_string(), Label::new(Some("lower".to_string())));
+        store
+            .labels
+            .insert("Work".to_string(), Label::new(Some("upper".to_string())));
+
+        storage.save(&store).unwrap();
+
+        let names: Vec<String> = fs::read_dir(storage.labels_dir())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_lowercase())
+            .collect();
+        assert_eq!(names.len(), 2, "{:?}", names);
+        let loaded = storage.load().unwrap();
+        assert_eq!(
+            loaded.get_label("work").unwrap().description.as_deref(),
+            Some("lower")
+        );
+        assert_eq!(
+            loaded.get_label("Work").unwrap().description.as_deref(),
+            Some("upper")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_flat_layout_checksums_label_files() {
+        let (storage, dir) = create_flat_storage("flat-checksum");
+        let mut store = create_flat_store();
+        store.settings.checksum = true;
+        storage.save(&store).unwrap();
+
+        let label_file = storage.labels_dir().join(encode_label_file_name("plain"));
+        let checksum = fs::read_to_string(checksum_path_for(&label_file)).unwrap();
+        assert_eq!(
+            checksum,
+            sha256_hex(&fs::read_to_string(&label_file).unwrap())
+        );
+
+        fs::write(&label_file, r#"{"sessions":[]}"#).unwrap();
+        let err = storage.load().unwrap_err();
+        assert!(
+            err.to_string().contains("does not match its checksum"),
+            "{}",
+            err
+        );
+
+        store.settings.checksum = false;
+        storage.save(&store).unwrap();
+        assert!(!checksum_path_for(&label_file).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_decode_label_file_name_rejects_foreign_files() {
+        assert_eq!(decode_label_file_name("notes.txt"), None);
+        assert_eq!(decode_label_file_name("bad%2.json"), None);
+        assert_eq!(decode_label_file_name("bad%ZZ.json"), None);
+        assert_eq!(decode_label_file_name("%FF.json"), None);
+        assert_eq!(decode_label_file_name("Notes.json"), None);
+    }
+
+    #[test]
+    fn test_flat_layout_roundtrip() {
+        let (storage, dir) = create_flat_storage("flat-roundtrip");
+        let store = create_flat_store();
+
+        storage.save(&store).unwrap();
+
+        let data: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(storage.path()).unwrap()).unwrap();
+        assert_eq!(data["labels"], serde_json::json!({}));
+        assert_eq!(data["settings"]["layout"], "flat");
+        assert!(storage
+            .labels_dir()
+            .join(encode_label_file_name("team/web/TICKET-1"))
+            .exists());
+
+        let loaded = storage.load().unwrap();
+        assert_eq!(loaded.labels.len(), 3);
+        assert_eq!(loaded.settings.layout, Layout::Flat);
+        let nested = loaded.get_label("team/web/TICKET-1").unwrap();
+        assert_eq!(nested.description, Some("Nested".to_string()));
+        assert_eq!(nested.sessions.len(), 1);
+        assert_eq!(loaded.get_label("plain").unwrap().sessions.len(), 2);
+        assert!(loaded.get_label("../escape").is_some());
+
+        assert_eq!(
+            storage.load_label_names().unwrap(),
+            vec!["../escape", "plain", "team/web/TICKET-1"]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_flat_layout_removes_deleted_labels() {
+        let (storage, dir) = create_flat_storage("flat-remove");
+        let mut store = create_flat_store();
+        storage.save(&store).unwrap();
+
+        // Files the store didn't write are left alone
+        fs::write(storage.labels_dir().join("README.txt"), "notes").unwrap();
+
+        store.remove_label("plain");
+        storage.save(&store).unwrap();
+
+        assert!(!storage.labels_dir().join("plain.json").exists());
+        assert!(storage.labels_dir().join("README.txt").exists());
+        assert!(storage.load().unwrap().get_label("plain").is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_switching_back_to_single_layout() {
+        let (storage, dir) = create_flat_storage("flat-to-single");
+        let mut store = create_flat_store();
+        storage.save(&store).unwrap();
+
+        store.settings.layout = Layout::Single;
+        storage.save(&store).unwrap();
+
+        let data: Store =
+            serde_json::from_str(&fs::read_to_string(storage.path()).unwrap()).unwrap();
+        assert_eq!(data.labels.len(), 3);
+        assert_eq!(storage.load().unwrap().labels.len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_flat_layout_corrupt_label_file_returns_typed_error() {
+        let (storage, dir) = create_flat_storage("flat-corrupt");
+        storage.save(&create_flat_store()).unwrap();
+
+        let bad = storage.labels_dir().join("plain.json");
+        fs::write(&bad, "{ nope").unwrap();
+
+        let err = storage.load().unwrap_err();
+        match err.downcast_ref::<StorageError>() {
+            Some(StorageError::Corrupt { path, .. }) => assert_eq!(path, &bad),
+            None => panic!("expected a corrupt data error, got: {:#}", err),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // ==================== Update Tests ====================
+
+    #[test]
+    fn test_update_applies_and_saves() {
+        let path = temp_path("update");
+        cleanup(&path);
+        let storage = Storage::with_path(path.clone());
+
+        let count = storage
+            .update(|store| {
+                store
+                    .get_or_create_label("new")
+                    .add_session(create_test_session("s1"));
+                Ok(store.labels.len())
+            })
+            .unwrap();
+
+        assert_eq!(count, 1);
+        assert_eq!(
+            storage
+                .load()
+                .unwrap()
+                .get_label("new")
+                .unwrap()
+                .sessions
+                .len(),
+            1
+        );
+        assert!(!storage.lock_path().exists());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_update_error_does_not_save() {
+        let path = temp_path("update-error");
+        cleanup(&path);
+        let storage = Storage::with_path(path.clone());
+
+        let result: Result<()> = storage.update(|store| {
+            store.get_or_create_label("new");
+            bail!("nope")
+        });
+
+        assert!(result.is_err());
+        assert!(!path.exists());
+        assert!(!storage.lock_path().exists());
+    }
+
+    #[test]
+    fn test_update_retries_after_concurrent_change() {
+        let path = temp_path("update-retry");
+        let storage = Storage::with_path(path.clone());
+        storage.save(&Store::new()).unwrap();
+
+        let mut attempts = 0;
+        storage
+            .update(|store| {
+                attempts += 1;
+                if attempts == 1 {
+                    // A writer that doesn't take the lock sneaks in
+                    let mut other = Store::new();
+                    other.labels.insert("other".to_string(), Label::new(None));
+                    Storage::with_path(path.clone()).save(&other).unwrap();
+                }
+                store.get_or_create_label("mine");
+                Ok(())
+            })
+            .unwrap();
+
+        let store = storage.load().unwrap();
+        assert_eq!(attempts, 2);
+        assert!(store.labels.contains_key("other"));
+        assert!(store.labels.contains_key("mine"));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_update_retries_after_concurrent_label_file_change_in_flat_layout() {
+        let path = temp_path("update-retry-flat");
+        let storage = Storage::with_path(path.clone());
+        let mut store = Store::new();
+        store.settings.layout = Layout::Flat;
+        store
+            .get_or_create_label("shared")
+            .add_session(create_test_session("s1"));
+        storage.save(&store).unwrap();
+
+        let mut attempts = 0;
+        storage
+            .update(|store| {
+                attempts += 1;
+                if attempts == 1 {
+                    // Another process writes only the label file; data.json
+                    // stays byte-for-byte the same
+                    let mut other = store.clone();
+                    other
+                        .get_label_mut("shared")
+                        .unwrap()
+                        .add_session(create_test_session("theirs"));
+                    Storage::with_path(path.clone()).save(&other).unwrap();
+                }
+                store
+                    .get_label_mut("shared")
+                    .unwrap()
+                    .add_session(create_test_session("mine"));
+                Ok(())
+            })
+            .unwrap();
+
+        let store = storage.load().unwrap();
+        let label = store.get_label("shared").unwrap();
+        assert_eq!(attempts, 2);
+        assert!(label.find_session("theirs").is_some());
+        assert!(label.find_session("mine").is_some());
+        cleanup(&path);
+        let _ = fs::remove_dir_all(storage.labels_dir());
+    }
+
+    #[test]
+    fn test_concurrent_updates_in_flat_layout_lose_nothing() {
+        let path = temp_path("update-concurrent-flat");
+        let storage = Storage::with_path(path.clone());
+        let mut store = Store::new();
+        store.settings.layout = Layout::Flat;
+        store
+            .get_or_create_label("counter")
+            .add_session(create_test_session("s1"));
+        storage.save(&store).unwrap();
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    let storage = Storage::with_path(path);
+                    for _ in 0..5 {
+                        storage
+                            .update(|store| {
+                                let label = store.get_label_mut("counter").unwrap();
+                                label.sessions[0].resume_count += 1;
+                                Ok(())
+                            })
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in threads {
+            handle.join().unwrap();
+        }
+
+        let store = storage.load().unwrap();
+        assert_eq!(
+            store.get_label("counter").unwrap().sessions[0].resume_count,
+            20
+        );
+        cleanup(&path);
+        let _ = fs::remove_dir_all(storage.labels_dir());
+    }
+
+    #[test]
+    fn test_concurrent_updates_lose_nothing() {
+        let path = temp_path("update-concurrent");
+        let storage = Storage::with_path(path.clone());
+        let mut store = Store::new();
+        store
+            .get_or_create_label("counter")
+            .add_session(create_test_session("s1"));
+        storage.save(&store).unwrap();
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    let storage = Storage::with_path(path);
+                    for _ in 0..5 {
+                        storage
+                            .update(|store| {
+                                let label = store.get_label_mut("counter").unwrap();
+                                label.sessions[0].resume_count += 1;
+                                Ok(())
+                            })
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in threads {
+            handle.join().unwrap();
+        }
+
+        let store = storage.load().unwrap();
+        assert_eq!(
+            store.get_label("counter").unwrap().sessions[0].resume_count,
+            40
+        );
+        cleanup(&path);
+    }
+
     // ==================== Path Tests ====================
 
     #[test]
@@ -287,11 +2007,149 @@ mod tests {
     #[test]
     fn test_storage_new_creates_valid_path() {
         // This test verifies Storage::new() works without errors
-        let storage = Storage::new();
+        let storage = Storage::new(None);
         assert!(storage.is_ok());
 
         let storage = storage.unwrap();
         let path = storage.path();
         assert!(path.ends_with("data.json"));
     }
+
+    // ==================== Config Dir Tests ====================
+
+    #[test]
+    fn test_resolve_config_dir_uses_primary() {
+        let candidates = [PathBuf::from("/primary"), PathBuf::from("/secondary")];
+
+        let (dir, failures) = resolve_config_dir(&candidates, |_| Ok(())).unwrap();
+        assert_eq!(dir, PathBuf::from("/primary"));
+        assert!(failures.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_config_dir_falls_back_when_unwritable() {
+        let candidates = [PathBuf::from("/locked"), PathBuf::from("/fallback")];
+
+        let (dir, failures) = resolve_config_dir(&candidates, |dir| {
+            if dir == Path::new("/locked") {
+                Err(io::Error::from(ErrorKind::PermissionDenied))
+            } else {
+                Ok(())
+            }
+        })
+        .unwrap();
+
+        assert_eq!(dir, PathBuf::from("/fallback"));
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, PathBuf::from("/locked"));
+    }
+
+    #[test]
+    fn test_resolve_config_dir_real_unwritable_dir() {
+        // A regular file where the directory should be can never be created
+        let blocker = temp_path("config-dir-blocker");
+        let _ = fs::remove_dir_all(&blocker);
+        fs::write(&blocker, "not a directory").unwrap();
+        let fallback = temp_path("config-dir-fallback");
+        let _ = fs::remove_dir_all(&fallback);
+
+        let candidates = [blocker.join("claude-sessions"), fallback.clone()];
+        let (dir, failures) =
+            resolve_config_dir(&candidates, |dir| fs::create_dir_all(dir)).unwrap();
+
+        assert_eq!(dir, fallback);
+        assert_eq!(failures.len(), 1);
+        assert!(fallback.is_dir());
+        fs::remove_file(&blocker).unwrap();
+        fs::remove_dir_all(&fallback).unwrap();
+    }
+
+    #[test]
+    fn test_config_dir_candidates_skip_shared_temp_dir() {
+        let shared = env::temp_dir().join("claude-sessions");
+        assert!(!config_dir_candidates().contains(&shared));
+    }
+
+    #[test]
+    fn test_resolve_config_dir_with_no_candidates_fails() {
+        let err = resolve_config_dir(&[], |_| Ok(())).unwrap_err();
+        assert!(err.to_string().contains("Could not determine"), "{}", err);
+    }
+
+    #[test]
+    fn test_resolve_config_dir_all_fail_names_paths() {
+        let candidates = [PathBuf::from("/one"), PathBuf::from("/two")];
+
+        let err = resolve_config_dir(&candidates, |_| {
+            Err(io::Error::from(ErrorKind::PermissionDenied))
+        })
+        .unwrap_err()
+        .to_string();
+
+        assert!(err.contains("/one"));
+        assert!(err.contains("/two"));
+    }
+
+    // ==================== Profile Tests ====================
+
+    #[test]
+    fn test_default_profile_is_legacy_path() {
+        assert_eq!(profile_file_name(DEFAULT_PROFILE).unwrap(), "data.json");
+        assert_eq!(
+            Storage::new(Some(DEFAULT_PROFILE)).unwrap().path(),
+            Storage::new(None).unwrap().path()
+        );
+    }
+
+    #[test]
+    fn test_profiles_map_to_distinct_files() {
+        assert_eq!(profile_file_name("work").unwrap(), "work.json");
+        assert_eq!(profile_file_name("personal").unwrap(), "personal.json");
+
+        let work = Storage::new(Some("work")).unwrap();
+        let default = Storage::new(None).unwrap();
+        assert_ne!(work.path(), default.path());
+        assert_eq!(work.path().parent(), default.path().parent());
+    }
+
+    #[test]
+    fn test_invalid_profile_names() {
+        assert!(profile_file_name("").is_err());
+        assert!(profile_file_name("../escape").is_err());
+        assert!(profile_file_name("data").is_err());
+    }
+
+    #[test]
+    fn test_profile_side_files_are_separate() {
+        let default = Storage::with_path(PathBuf::from("/cfg/data.json"));
+        assert_eq!(default.labels_dir(), PathBuf::from("/cfg/labels"));
+        assert_eq!(default.history_path(), PathBuf::from("/cfg/resume.log"));
+
+        let work = Storage::with_path(PathBuf::from("/cfg/work.json"));
+        assert_eq!(work.labels_dir(), PathBuf::from("/cfg/work.labels"));
+        assert_eq!(work.history_path(), PathBuf::from("/cfg/work.resume.log"));
+    }
+
+    #[test]
+    fn test_list_profiles() {
+        let dir = temp_path("profiles");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("labels")).unwrap();
+        for name in [
+            "data.json",
+            "work.json",
+            "personal.json",
+            "data.json.sha256",
+            "resume.log",
+        ] {
+            fs::write(dir.join(name), "{}").unwrap();
+        }
+
+        assert_eq!(
+            list_profiles(&dir).unwrap(),
+            ["default", "personal", "work"]
+        );
+        assert!(list_profiles(&dir.join("missing")).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,56 @@
+use serde::Serialize;
+
+/// Build metadata embedded by `build.rs`, for bug reports.
+#[derive(Debug, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_commit: Option<&'static str>,
+    pub rustc: Option<&'static str>,
+    pub target: Option<&'static str>,
+}
+
+fn non_empty(value: &'static str) -> Option<&'static str> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_commit: non_empty(env!("CLAUDE_SESSIONS_GIT_SHA")),
+        rustc: non_empty(env!("CLAUDE_SESSIONS_RUSTC_VERSION")),
+        target: non_empty(env!("CLAUDE_SESSIONS_TARGET")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ==================== Build Info Tests ====================
+
+    #[test]
+    fn test_version_is_not_empty() {
+        assert!(!build_info().version.is_empty());
+        assert_eq!(build_info().version, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn test_build_info_json_keys() {
+        let json = serde_json::to_value(build_info()).unwrap();
+        let mut keys: Vec<&String> = json.as_object().unwrap().keys().collect();
+        keys.sort();
+
+        assert_eq!(keys, vec!["git_commit", "rustc", "target", "version"]);
+        assert!(json["version"].is_string());
+    }
+
+    #[test]
+    fn test_non_empty() {
+        assert_eq!(non_empty(""), None);
+        assert_eq!(non_empty("abc"), Some("abc"));
+    }
+}